@@ -0,0 +1,421 @@
+use std::{
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use crate::{parse_tree::resolver::Binding, string::StringSlice};
+
+use super::{
+    op_code::OpCode,
+    source_map::{SourceMap, SourceMapEntry},
+    BytecodeGenerationError, Function,
+};
+
+/// A location in the bytecode that jumps can target before it's known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Label(usize);
+
+/// Collects the instructions of a function along with it's source map.
+/// Jumps target labels, and are patched once the label is bound.
+/// Derefs to the instructions so codegen can still look back at them.
+#[derive(Debug, Default)]
+pub struct BytecodeBuilder {
+    ops: Vec<OpCode>,
+    source: Option<Arc<str>>,
+    entries: Vec<SourceMapEntry>,
+    labels: Vec<Option<usize>>,
+    /// Instructions waiting for their label to be bound
+    pending: Vec<(usize, Label)>,
+    loops: Vec<LoopScope>,
+    /// How many catches and finally blocks are active
+    handlers: usize,
+    /// How many contexts are pushed
+    contexts: usize,
+    /// The locals declared in each context, innermost last.
+    /// Variables outside of every scope are looked up by name
+    scopes: Vec<Vec<Arc<str>>>,
+    /// What the resolver bound each variable reference to
+    bindings: Arc<HashMap<usize, Binding>>,
+}
+
+#[derive(Debug)]
+struct LoopScope {
+    name: Option<Arc<str>>,
+    break_label: Label,
+    continue_label: Label,
+    handlers: usize,
+    contexts: usize,
+}
+
+impl BytecodeBuilder {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// A builder for a function body, with the params in the first slots of it's context
+    pub fn for_function(params: &[Arc<str>]) -> Self {
+        return Self {
+            scopes: vec![params.to_vec()],
+            ..Self::default()
+        };
+    }
+
+    pub fn with_bindings(mut self, bindings: Arc<HashMap<usize, Binding>>) -> Self {
+        self.bindings = bindings;
+        return self;
+    }
+
+    /// Marks the instructions pushed from now on as generated from the slice
+    pub fn set_slice(&mut self, slice: &StringSlice) {
+        if self.source.is_none() {
+            self.source = Some(slice.src.clone());
+        }
+
+        let entry = SourceMapEntry {
+            offset: self.ops.len(),
+            start: slice.start,
+            end: slice.end,
+        };
+
+        // A later slice at the same offset replaces the earlier one, since nothing was emitted for it
+        if let Some(last) = self.entries.last_mut()
+            && last.offset == entry.offset
+        {
+            *last = entry;
+            return;
+        }
+
+        if self.entries.last().is_some_and(|last| last.start == entry.start && last.end == entry.end) {
+            return;
+        }
+
+        self.entries.push(entry);
+    }
+
+    pub fn push(&mut self, op: OpCode) {
+        self.ops.push(op);
+    }
+
+    pub fn new_label(&mut self) -> Label {
+        self.labels.push(None);
+        return Label(self.labels.len() - 1);
+    }
+
+    /// Binds the label to the next instruction, patching every jump to it
+    pub fn bind(&mut self, label: Label) {
+        let location = self.ops.len();
+        self.labels[label.0] = Some(location);
+
+        let ops = &mut self.ops;
+        self.pending.retain(|(index, pending)| {
+            if *pending != label {
+                return true;
+            }
+
+            if let Some(target) = ops[*index].location_mut() {
+                *target = location;
+            }
+            return false;
+        });
+    }
+
+    /// Pushes an instruction with a location, like a jump or a catch, targeting the label
+    pub fn push_jump(&mut self, op: OpCode, label: Label) {
+        let index = self.ops.len();
+        self.ops.push(op);
+
+        match self.labels[label.0] {
+            Some(location) => {
+                if let Some(target) = self.ops[index].location_mut() {
+                    *target = location;
+                }
+            }
+            None => self.pending.push((index, label)),
+        }
+    }
+
+    pub fn push_context(&mut self) {
+        self.contexts += 1;
+        self.scopes.push(vec![]);
+        self.push(OpCode::PushContext);
+    }
+
+    pub fn pop_context(&mut self) {
+        self.contexts -= 1;
+        self.scopes.pop();
+        self.push(OpCode::PopContext);
+    }
+
+    /// Finds the depth and slot of the innermost local with the name
+    pub fn resolve(&self, name: &str) -> Option<(usize, usize)> {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(slot) = scope.iter().rposition(|it| it.as_ref() == name) {
+                return Some((depth, slot));
+            }
+        }
+
+        return None;
+    }
+
+    /// Declares the variable in the current context, as a local if there's a scope for it
+    pub fn init_variable(&mut self, name: &Arc<str>) {
+        match self.scopes.last_mut() {
+            Some(scope) => {
+                scope.push(name.clone());
+                let slot = scope.len() - 1;
+                self.push(OpCode::InitLocal { slot });
+            }
+            None => self.push(OpCode::InitVariable { name: name.clone() }),
+        }
+    }
+
+    pub fn push_variable(&mut self, name: &Arc<str>) {
+        match self.resolve(name) {
+            Some((depth, slot)) => self.push(OpCode::PushLocal { depth, slot }),
+            None => self.push(OpCode::PushVariable { name: name.clone() }),
+        }
+    }
+
+    /// Stores the value at the top of the stack, leaving it there
+    pub fn store_variable(&mut self, name: &Arc<str>) {
+        match self.resolve(name) {
+            Some((depth, slot)) => self.push(OpCode::StoreLocal { depth, slot }),
+            None => self.push(OpCode::StoreVariable { name: name.clone() }),
+        }
+    }
+
+    /// Pushes the variable a reference in the source points to
+    pub fn push_reference(
+        &mut self,
+        name: &Arc<str>,
+        slice: &StringSlice,
+    ) -> Result<(), BytecodeGenerationError> {
+        match self.reference(name, slice)? {
+            Some((depth, slot)) => self.push(OpCode::PushLocal { depth, slot }),
+            None => self.push(OpCode::PushVariable { name: name.clone() }),
+        }
+
+        return Ok(());
+    }
+
+    /// Stores the value at the top of the stack in the variable a reference points to, leaving it there
+    pub fn store_reference(
+        &mut self,
+        name: &Arc<str>,
+        slice: &StringSlice,
+    ) -> Result<(), BytecodeGenerationError> {
+        match self.reference(name, slice)? {
+            Some((depth, slot)) => self.push(OpCode::StoreLocal { depth, slot }),
+            None => self.push(OpCode::StoreVariable { name: name.clone() }),
+        }
+
+        return Ok(());
+    }
+
+    /// The resolver's binding when there is one. The scopes here have to agree with it,
+    /// otherwise one of them declared the variables in a different order
+    fn reference(
+        &self,
+        name: &Arc<str>,
+        slice: &StringSlice,
+    ) -> Result<Option<(usize, usize)>, BytecodeGenerationError> {
+        let resolved = self.resolve(name);
+
+        let bound = match self.bindings.get(&slice.start) {
+            Some(Binding::Local { depth, slot }) => Some((*depth, *slot)),
+            Some(Binding::Global) => None,
+            None => return Ok(resolved),
+        };
+
+        if bound != resolved {
+            return Err(BytecodeGenerationError::BindingMismatch(slice.clone()));
+        }
+
+        return Ok(bound);
+    }
+
+    /// Marks the variable just declared with `init_variable` as const
+    pub fn mark_variable_const(&mut self, name: &Arc<str>) {
+        match self.resolve(name) {
+            Some((0, slot)) => self.push(OpCode::MarkLocalConst { slot }),
+            _ => self.push(OpCode::MarkVariableConst { name: name.clone() }),
+        }
+    }
+
+    /// Called after pushing a catch or finally, so breaks know to leave it
+    pub fn enter_handler(&mut self) {
+        self.handlers += 1;
+    }
+
+    /// Called once a catch or finally is popped, or has started running
+    pub fn exit_handler(&mut self) {
+        self.handlers -= 1;
+    }
+
+    /// Starts a loop that `break` and `continue` can target, until `pop_loop` is called
+    pub fn push_loop(&mut self, name: Option<Arc<str>>, break_label: Label, continue_label: Label) {
+        self.loops.push(LoopScope {
+            name,
+            break_label,
+            continue_label,
+            handlers: self.handlers,
+            contexts: self.contexts,
+        });
+    }
+
+    pub fn pop_loop(&mut self) {
+        self.loops.pop();
+    }
+
+    /// Jumps to the end of the innermost loop, or the loop with that name.
+    /// Returns false if there isn't a loop to break out of.
+    pub fn push_break(&mut self, name: Option<&str>) -> bool {
+        return self.push_loop_exit(name, true);
+    }
+
+    /// Jumps to the next iteration of the innermost loop, or the loop with that name.
+    /// Returns false if there isn't a loop to continue.
+    pub fn push_continue(&mut self, name: Option<&str>) -> bool {
+        return self.push_loop_exit(name, false);
+    }
+
+    fn push_loop_exit(&mut self, name: Option<&str>, is_break: bool) -> bool {
+        let scope = self
+            .loops
+            .iter()
+            .rev()
+            .find(|it| name.is_none() || it.name.as_deref() == name);
+
+        let Some(scope) = scope else {
+            return false;
+        };
+
+        let label = if is_break {
+            scope.break_label
+        } else {
+            scope.continue_label
+        };
+        let handlers = self.handlers - scope.handlers;
+        let contexts = self.contexts - scope.contexts;
+
+        if handlers == 0 {
+            for _ in 0..contexts {
+                self.ops.push(OpCode::PopContext);
+            }
+            self.push_jump(OpCode::Jump { location: 0 }, label);
+        } else {
+            self.push_jump(
+                OpCode::Leave {
+                    location: 0,
+                    handlers,
+                    contexts,
+                },
+                label,
+            );
+        }
+
+        return true;
+    }
+
+    pub fn source_map(&self) -> SourceMap {
+        return SourceMap {
+            source: self.source.clone(),
+            entries: self.entries.clone().into_boxed_slice().into(),
+        };
+    }
+
+    pub fn finish(self, name: Arc<str>, params: Arc<[Arc<str>]>) -> Function {
+        debug_assert!(self.pending.is_empty(), "A label was never bound");
+
+        let source_map = self.source_map();
+
+        return Function {
+            name,
+            params,
+            body: self.ops.into_boxed_slice().into(),
+            source_map,
+        };
+    }
+}
+
+impl Deref for BytecodeBuilder {
+    type Target = [OpCode];
+
+    fn deref(&self) -> &Self::Target {
+        return &self.ops;
+    }
+}
+
+impl DerefMut for BytecodeBuilder {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        return &mut self.ops;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, sync::Arc};
+
+    use crate::{
+        bytecode::{op_code::OpCode, BytecodeGenerationError},
+        parse_tree::{resolver::Binding, tree::ParseTree},
+        string::ToStringSlice,
+        tokenizer::Tokenizer,
+    };
+
+    use super::BytecodeBuilder;
+
+    #[test]
+    fn labelled_break() {
+        let src = "outer: while true do
+    for each x in 1 to 3 do
+        if x == 2 then
+            break outer
+        end
+    end
+end
+
+while true do
+    break nowhere
+end
+";
+        let mut tokenizer = Tokenizer::new(src.into());
+        let tree = ParseTree::try_parse(&mut tokenizer).unwrap().unwrap();
+
+        assert!(matches!(
+            tree.compile(),
+            Err(BytecodeGenerationError::UnknownLabel(_))
+        ));
+
+        let mut tokenizer = Tokenizer::new(src.split("\n\n").next().unwrap().into());
+        let tree = ParseTree::try_parse(&mut tokenizer).unwrap().unwrap();
+        let body = tree.compile().unwrap().init.body;
+
+        // Leaves the if, for and while blocks before jumping past the outer loop
+        let exit = body.len();
+        let idx = body
+            .iter()
+            .position(|it| *it == OpCode::Jump { location: exit })
+            .unwrap();
+        assert_eq!(body[idx - 3..idx], [OpCode::PopContext, OpCode::PopContext, OpCode::PopContext]);
+        assert_ne!(body[idx - 4], OpCode::PopContext);
+    }
+
+    #[test]
+    fn binding_mismatch() {
+        let src: Arc<str> = "x".into();
+        let slice = src.slice(0, 1);
+        let name: Arc<str> = "x".into();
+
+        let bindings = HashMap::from([(0, Binding::Local { depth: 0, slot: 1 })]);
+        let mut bytecode = BytecodeBuilder::for_function(std::slice::from_ref(&name))
+            .with_bindings(bindings.into());
+
+        assert!(matches!(
+            bytecode.push_reference(&name, &slice),
+            Err(BytecodeGenerationError::BindingMismatch(_))
+        ));
+        assert!(bytecode.is_empty());
+    }
+}
@@ -1,19 +1,34 @@
 use std::sync::Arc;
 
 use op_code::OpCode;
+use source_map::SourceMap;
 
-use crate::{parse_tree::ParserError, string::StringSlice};
+use crate::{
+    parse_tree::{resolver::Diagnostic, ParserError},
+    string::StringSlice,
+};
 
+pub mod builder;
 pub mod op_code;
+pub mod optimizer;
+pub mod peephole;
+pub mod serialize;
+pub mod source_map;
+pub mod verifier;
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct CompiledModule {
     pub functions: Arc<[Function]>,
     pub init: Function,
 }
 
+/// Invoking a function pushes a context holding it's params in the first slots
+#[derive(Debug, Clone, PartialEq)]
 pub struct Function {
+    pub name: Arc<str>,
     pub params: Arc<[Arc<str>]>,
-    pub body: Arc<OpCode>,
+    pub body: Arc<[OpCode]>,
+    pub source_map: SourceMap,
 }
 
 #[derive(Debug)]
@@ -23,6 +38,16 @@ pub enum BytecodeGenerationError {
     IllegalExport(StringSlice),
     IllegalBreak(StringSlice),
     IllegalContinue(StringSlice),
+    /// A `break` or `continue` naming a loop that it isn't inside of
+    UnknownLabel(StringSlice),
+    /// A `const` without a value
+    MissingConstInitializer(StringSlice),
+    /// A range with a literal step of 0
+    ZeroStep(StringSlice),
+    /// The first error the resolver found
+    Resolve(Diagnostic),
+    /// A variable the resolver bound to a different place than the code generator, which is a bug in one of them
+    BindingMismatch(StringSlice),
 }
 
 impl From<ParserError> for BytecodeGenerationError {
@@ -1,13 +1,7 @@
 use std::sync::Arc;
 
-use crate::string::StringSlice;
-
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum OpCode {
-    SetSlice {
-        slice: StringSlice,
-    },
-
     /// Pushes a variable
     PushVariable {
         name: Arc<str>,
@@ -42,11 +36,17 @@ pub enum OpCode {
     PushNewArray {
         initial_size: usize,
     },
+    /// Stack structure: <step> <end> <start>
+    ///
+    /// Pushes an iterable range from start to end, including end. Throws a RangeError if the step is 0
+    PushRange,
+    /// Pops an object and pushes an array of it's own keys, in insertion order
+    PushKeys,
 
     /// Pushes a constant none
     PushConstNone,
 
-    /// Stores the value at the top of the stack
+    /// Stores the value at the top of the stack, throwing a TypeError if the variable is const
     StoreVariable {
         name: Arc<str>,
     },
@@ -61,6 +61,26 @@ pub enum OpCode {
         name: Arc<str>,
     },
 
+    /// Pushes a local, `depth` contexts up from the current one.
+    /// Locals are resolved to slots when compiling, while variables are looked up by name
+    PushLocal {
+        depth: usize,
+        slot: usize,
+    },
+    /// Stores the value at the top of the stack in a local, throwing a TypeError if it's const
+    StoreLocal {
+        depth: usize,
+        slot: usize,
+    },
+    /// Adds a local to the current context, growing it if needed
+    InitLocal {
+        slot: usize,
+    },
+    /// Marks the local in the current context as const
+    MarkLocalConst {
+        slot: usize,
+    },
+
     /// Stack structure: <params...> <function> <this?>
     Invoke {
         param_count: usize,
@@ -77,6 +97,8 @@ pub enum OpCode {
 
     /// Duplicates the value at the top of the stack
     Dupe,
+    /// Duplicates the top two values of the stack, keeping their order
+    DupeTwo,
 
     /// Pops the value at the top of the stack
     Pop,
@@ -96,8 +118,6 @@ pub enum OpCode {
     OpLt,
     OpEq,
     OpNe,
-    OpOr,
-    OpAnd,
     OpUnaryAdd,
     OpUnarySub,
     OpUnaryNot,
@@ -119,12 +139,36 @@ pub enum OpCode {
     JumpFalse {
         location: usize,
     },
-    /// Pushes a catch to move to that location
+    /// Jumps to that location if the value at the top of the stack is none
+    JumpNone {
+        location: usize,
+    },
+    /// Jumps to that location if the value at the top of the stack is not none
+    JumpNotNone {
+        location: usize,
+    },
+    /// Pushes a catch to move to that location. When a value is thrown, the catch is popped, the stack and contexts are restored to how they were when it was pushed, and execution moves to that location.
+    /// Exits thrown by `exit` pass by catches, see `Runtime::new_exit`
     PushCatch {
         location: usize,
     },
     /// Pops the catch
     PopCatch,
+    /// Pushes a finally block at that location. When a value is thrown, or the function returns, the finally is popped, the stack and contexts are restored to how they were when it was pushed, and execution moves to that location
+    PushFinally {
+        location: usize,
+    },
+    /// Pops the finally, without running it
+    PopFinally,
+    /// Ends a finally block, continuing the throw, return or leave that caused it to run, if any
+    EndFinally,
+    /// Pops that many catches and finally blocks, running the finally blocks, then pops that many contexts and jumps to that location.
+    /// The contexts include the ones popped while running the finally blocks
+    Leave {
+        location: usize,
+        handlers: usize,
+        contexts: usize,
+    },
 
     /// This can only be called in the init function.
     Import {
@@ -135,8 +179,85 @@ pub enum OpCode {
         name: Arc<str>,
     },
 
-    /// A temporary instruction to store a break stmt. An error should be thrown if come across during execution
-    TempBreak,
-    /// A temporary instruction to store a continue stmt. An error should be thrown if come across during execution
-    TempContinue,
+    /// `StoreVariable; Pop`
+    StoreVariablePop {
+        name: Arc<str>,
+    },
+    /// `StoreLocal; Pop`
+    StoreLocalPop {
+        depth: usize,
+        slot: usize,
+    },
+    /// `PushLocal; PushConstInt; OpAdd; StoreLocal; Pop`, adding the value to the local
+    IncLocal {
+        depth: usize,
+        slot: usize,
+        value: isize,
+    },
+    /// Stack structure: <right> <left>
+    ///
+    /// A comparison followed by `JumpFalse`, jumping to that location if the comparison doesn't hold
+    CompareJump {
+        op: CompareOp,
+        location: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Ge,
+    Le,
+    Gt,
+    Lt,
+    Eq,
+    Ne,
+}
+
+impl CompareOp {
+    pub const ALL: [CompareOp; 6] = [Self::Ge, Self::Le, Self::Gt, Self::Lt, Self::Eq, Self::Ne];
+
+    pub fn from_op_code(op: &OpCode) -> Option<Self> {
+        return match op {
+            OpCode::OpGe => Some(Self::Ge),
+            OpCode::OpLe => Some(Self::Le),
+            OpCode::OpGt => Some(Self::Gt),
+            OpCode::OpLt => Some(Self::Lt),
+            OpCode::OpEq => Some(Self::Eq),
+            OpCode::OpNe => Some(Self::Ne),
+            _ => None,
+        };
+    }
+}
+
+impl OpCode {
+    /// The location of a jump, or of the handler of a catch or finally
+    pub fn location(&self) -> Option<usize> {
+        return match self {
+            Self::Jump { location }
+            | Self::JumpTrue { location }
+            | Self::JumpFalse { location }
+            | Self::JumpNone { location }
+            | Self::JumpNotNone { location }
+            | Self::PushCatch { location }
+            | Self::PushFinally { location }
+            | Self::Leave { location, .. }
+            | Self::CompareJump { location, .. } => Some(*location),
+            _ => None,
+        };
+    }
+
+    pub fn location_mut(&mut self) -> Option<&mut usize> {
+        return match self {
+            Self::Jump { location }
+            | Self::JumpTrue { location }
+            | Self::JumpFalse { location }
+            | Self::JumpNone { location }
+            | Self::JumpNotNone { location }
+            | Self::PushCatch { location }
+            | Self::PushFinally { location }
+            | Self::Leave { location, .. }
+            | Self::CompareJump { location, .. } => Some(location),
+            _ => None,
+        };
+    }
 }
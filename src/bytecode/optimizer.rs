@@ -0,0 +1,424 @@
+use std::sync::Arc;
+
+use super::{op_code::OpCode, peephole::rewrite, CompiledModule, Function};
+
+/// Optimizes every function in the module
+pub fn optimize_module(module: &CompiledModule) -> CompiledModule {
+    return CompiledModule {
+        functions: module.functions.iter().map(optimize).collect(),
+        init: optimize(&module.init),
+    };
+}
+
+/// Folds constants, threads jumps and removes code that can't run, until nothing changes.
+///
+/// Only operations with one obvious result are folded, like adding two integers without overflowing.
+/// Anything that depends on how the interpreter converts between types is left alone.
+pub fn optimize(function: &Function) -> Function {
+    let mut function = function.clone();
+
+    loop {
+        let next = remove_dead_code(&thread_jumps(&fold(&function)));
+        if same_body(&next.body, &function.body) {
+            return next;
+        }
+        function = next;
+    }
+}
+
+/// Compares bodies with floats compared by their bits, since a NaN constant never equals itself
+fn same_body(lhs: &[OpCode], rhs: &[OpCode]) -> bool {
+    return lhs.len() == rhs.len()
+        && lhs.iter().zip(rhs).all(|ops| match ops {
+            (OpCode::PushConstFloat { value: lhs }, OpCode::PushConstFloat { value: rhs }) => {
+                lhs.to_bits() == rhs.to_bits()
+            }
+            (lhs, rhs) => lhs == rhs,
+        });
+}
+
+/// A constant pushed by an instruction
+#[derive(Debug, Clone, PartialEq)]
+enum Const {
+    Integer(isize),
+    Float(f32),
+    Bool(bool),
+    String(Arc<str>),
+    None,
+}
+
+impl Const {
+    fn from_op(op: &OpCode) -> Option<Self> {
+        return match op {
+            OpCode::PushConstInt { value } => Some(Self::Integer(*value)),
+            OpCode::PushConstFloat { value } => Some(Self::Float(*value)),
+            OpCode::PushConstBool { value } => Some(Self::Bool(*value)),
+            OpCode::PushConstString { value } => Some(Self::String(value.clone())),
+            OpCode::PushConstNone => Some(Self::None),
+            _ => None,
+        };
+    }
+
+    fn into_op(self) -> OpCode {
+        return match self {
+            Self::Integer(value) => OpCode::PushConstInt { value },
+            Self::Float(value) => OpCode::PushConstFloat { value },
+            Self::Bool(value) => OpCode::PushConstBool { value },
+            Self::String(value) => OpCode::PushConstString { value },
+            Self::None => OpCode::PushConstNone,
+        };
+    }
+
+    fn binary(self, op: &OpCode, rhs: Self) -> Option<Self> {
+        return match (self, rhs) {
+            (Self::Integer(lhs), Self::Integer(rhs)) => match op {
+                OpCode::OpAdd => lhs.checked_add(rhs).map(Self::Integer),
+                OpCode::OpSub => lhs.checked_sub(rhs).map(Self::Integer),
+                OpCode::OpMul => lhs.checked_mul(rhs).map(Self::Integer),
+                _ => Self::compare(op, lhs.cmp(&rhs)),
+            },
+            (Self::Float(lhs), Self::Float(rhs)) => match op {
+                OpCode::OpAdd => Some(Self::Float(lhs + rhs)),
+                OpCode::OpSub => Some(Self::Float(lhs - rhs)),
+                OpCode::OpMul => Some(Self::Float(lhs * rhs)),
+                OpCode::OpDiv => Some(Self::Float(lhs / rhs)),
+                // NaN isn't ordered, so only the comparisons that hold without an ordering are folded
+                _ => match lhs.partial_cmp(&rhs) {
+                    Some(ordering) => Self::compare(op, ordering),
+                    None => match op {
+                        OpCode::OpNe => Some(Self::Bool(true)),
+                        OpCode::OpEq
+                        | OpCode::OpLt
+                        | OpCode::OpLe
+                        | OpCode::OpGt
+                        | OpCode::OpGe => Some(Self::Bool(false)),
+                        _ => None,
+                    },
+                },
+            },
+            (Self::String(lhs), Self::String(rhs)) => match op {
+                OpCode::OpAdd => Some(Self::String(format!("{}{}", lhs, rhs).into())),
+                OpCode::OpEq => Some(Self::Bool(lhs == rhs)),
+                OpCode::OpNe => Some(Self::Bool(lhs != rhs)),
+                _ => None,
+            },
+            (Self::Bool(lhs), Self::Bool(rhs)) => match op {
+                OpCode::OpEq => Some(Self::Bool(lhs == rhs)),
+                OpCode::OpNe => Some(Self::Bool(lhs != rhs)),
+                _ => None,
+            },
+            (Self::None, Self::None) => match op {
+                OpCode::OpEq => Some(Self::Bool(true)),
+                OpCode::OpNe => Some(Self::Bool(false)),
+                _ => None,
+            },
+            _ => None,
+        };
+    }
+
+    fn compare(op: &OpCode, ordering: std::cmp::Ordering) -> Option<Self> {
+        let result = match op {
+            OpCode::OpEq => ordering.is_eq(),
+            OpCode::OpNe => ordering.is_ne(),
+            OpCode::OpLt => ordering.is_lt(),
+            OpCode::OpLe => ordering.is_le(),
+            OpCode::OpGt => ordering.is_gt(),
+            OpCode::OpGe => ordering.is_ge(),
+            _ => return None,
+        };
+
+        return Some(Self::Bool(result));
+    }
+
+    fn unary(self, op: &OpCode) -> Option<Self> {
+        return match (op, self) {
+            (OpCode::OpUnarySub, Self::Integer(value)) => value.checked_neg().map(Self::Integer),
+            (OpCode::OpUnarySub, Self::Float(value)) => Some(Self::Float(-value)),
+            (OpCode::OpUnaryAdd, value @ (Self::Integer(_) | Self::Float(_))) => Some(value),
+            (OpCode::OpUnaryNot, Self::Bool(value)) => Some(Self::Bool(!value)),
+            _ => None,
+        };
+    }
+
+    /// Whether a conditional jump is taken for the constant, if that's known
+    fn jumps(&self, op: &OpCode) -> Option<bool> {
+        return match (op, self) {
+            (OpCode::JumpTrue { .. }, Self::Bool(value)) => Some(*value),
+            (OpCode::JumpFalse { .. }, Self::Bool(value)) => Some(!*value),
+            (OpCode::JumpNone { .. }, value) => Some(*value == Self::None),
+            (OpCode::JumpNotNone { .. }, value) => Some(*value != Self::None),
+            _ => None,
+        };
+    }
+}
+
+/// Which instructions are jumped to, including one past the end
+fn jump_targets(body: &[OpCode]) -> Vec<bool> {
+    let mut targets = vec![false; body.len() + 1];
+    for op in body.iter() {
+        if let Some(location) = op.location() {
+            targets[location] = true;
+        }
+    }
+
+    return targets;
+}
+
+/// Folds constant operations and jumps, and drops values that are pushed just to be popped
+fn fold(function: &Function) -> Function {
+    let body = &function.body;
+    let targets = jump_targets(body);
+
+    let mut ops = vec![];
+    let mut old_to_new = vec![0; body.len() + 1];
+    let mut idx = 0;
+
+    while idx < body.len() {
+        let (replacement, len) = fold_at(&body[idx..], &targets[idx..]);
+
+        old_to_new[idx..idx + len].fill(ops.len());
+        ops.extend(replacement);
+        idx += len;
+    }
+    old_to_new[body.len()] = ops.len();
+
+    return rewrite(function, ops, &old_to_new);
+}
+
+/// What the instructions at the start of the slice fold to, and how many of them it replaces
+fn fold_at(ops: &[OpCode], targets: &[bool]) -> (Vec<OpCode>, usize) {
+    let free = |len: usize| ops.len() >= len && !targets[1..len].contains(&true);
+
+    if free(3)
+        && let Some(lhs) = Const::from_op(&ops[0])
+        && let Some(rhs) = Const::from_op(&ops[1])
+        && let Some(value) = lhs.binary(&ops[2], rhs)
+    {
+        return (vec![value.into_op()], 3);
+    }
+
+    if free(2) {
+        if let Some(value) = Const::from_op(&ops[0]) {
+            if let Some(value) = value.clone().unary(&ops[1]) {
+                return (vec![value.into_op()], 2);
+            }
+
+            if let Some(taken) = value.jumps(&ops[1]) {
+                let location = ops[1].location().unwrap();
+                return match taken {
+                    true => (vec![OpCode::Jump { location }], 2),
+                    false => (vec![], 2),
+                };
+            }
+
+            if ops[1] == OpCode::Pop {
+                return (vec![], 2);
+            }
+        }
+
+        if let [OpCode::Dupe, OpCode::Pop] = &ops[..2] {
+            return (vec![], 2);
+        }
+    }
+
+    return (vec![ops[0].clone()], 1);
+}
+
+/// Points jumps that land on another jump at it's target, and removes jumps to the next instruction
+fn thread_jumps(function: &Function) -> Function {
+    let body = &function.body;
+
+    let final_target = |mut location: usize| {
+        // A loop of jumps never ends, so it's followed at most once around
+        for _ in 0..body.len() {
+            match body.get(location) {
+                Some(OpCode::Jump { location: next }) if *next != location => location = *next,
+                _ => break,
+            }
+        }
+        return location;
+    };
+
+    let mut ops = vec![];
+    let mut old_to_new = vec![0; body.len() + 1];
+
+    for (idx, op) in body.iter().enumerate() {
+        old_to_new[idx] = ops.len();
+
+        let mut op = op.clone();
+        match &mut op {
+            OpCode::Jump { location }
+            | OpCode::JumpTrue { location }
+            | OpCode::JumpFalse { location }
+            | OpCode::JumpNone { location }
+            | OpCode::JumpNotNone { location }
+            | OpCode::Leave { location, .. } => *location = final_target(*location),
+            _ => {}
+        }
+
+        if op == (OpCode::Jump { location: idx + 1 }) {
+            continue;
+        }
+        ops.push(op);
+    }
+    old_to_new[body.len()] = ops.len();
+
+    return rewrite(function, ops, &old_to_new);
+}
+
+/// Removes instructions that can't be reached from the start of the function
+fn remove_dead_code(function: &Function) -> Function {
+    let body = &function.body;
+
+    let mut reachable = vec![false; body.len() + 1];
+    let mut worklist = vec![0];
+
+    while let Some(idx) = worklist.pop() {
+        if reachable[idx] {
+            continue;
+        }
+        reachable[idx] = true;
+
+        let Some(op) = body.get(idx) else {
+            continue;
+        };
+
+        if let Some(location) = op.location() {
+            worklist.push(location);
+        }
+
+        match op {
+            OpCode::Jump { .. } | OpCode::Leave { .. } | OpCode::Return | OpCode::Throw => {}
+            _ => worklist.push(idx + 1),
+        }
+    }
+
+    let mut ops = vec![];
+    let mut old_to_new = vec![0; body.len() + 1];
+
+    for (idx, op) in body.iter().enumerate() {
+        old_to_new[idx] = ops.len();
+        if reachable[idx] {
+            ops.push(op.clone());
+        }
+    }
+    old_to_new[body.len()] = ops.len();
+
+    return rewrite(function, ops, &old_to_new);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bytecode::{op_code::OpCode, verifier::verify, BytecodeGenerationError, CompiledModule},
+        parse_tree::tree::ParseTree,
+        tokenizer::Tokenizer,
+    };
+
+    use super::optimize_module;
+
+    fn compile(src: &str) -> Result<(CompiledModule, CompiledModule), BytecodeGenerationError> {
+        let mut tokenizer = Tokenizer::new(src.into());
+        let tree = ParseTree::try_parse(&mut tokenizer).unwrap().unwrap();
+        let module = tree.generate_module()?;
+
+        return Ok((optimize_module(&module), module));
+    }
+
+    fn init(src: &str) -> Vec<OpCode> {
+        return compile(src).unwrap().0.init.body.to_vec();
+    }
+
+    #[test]
+    fn folding() {
+        assert_eq!(
+            init("let day = -60 * 60 * 24\n"),
+            [
+                OpCode::InitVariable { name: "day".into() },
+                OpCode::PushConstInt { value: -86400 },
+                OpCode::StoreVariable { name: "day".into() },
+                OpCode::Pop,
+            ]
+        );
+        assert_eq!(
+            init(
+                "let greeting = \"hello \" + \"world\"\nlet check = 1.5 < 2.5 and \"a\" != \"b\"\n"
+            )[1],
+            OpCode::PushConstString {
+                value: "hello world".into()
+            }
+        );
+
+        // Overflowing, dividing integers and mixing types are left to the interpreter
+        let body = init("let a = 9223372036854775807 + 1\nlet b = 1 / 2\nlet c = 1 + 1.5\n");
+        assert_eq!(
+            body.iter()
+                .filter(|it| matches!(it, OpCode::OpAdd | OpCode::OpDiv))
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn nan_constants() {
+        // NaN never equals itself, which used to keep the optimizer from ever finishing
+        let body = init("let x = NaN\nlet y = 0.0 / 0.0\n");
+        assert_eq!(
+            body.iter()
+                .filter(|it| matches!(it, OpCode::PushConstFloat { value } if value.is_nan()))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn dead_code() {
+        // The whole debug block and the jumps around it are removed
+        assert_eq!(
+            init("let debug = 1\nif false then\n    print(debug)\nend\n"),
+            init("let debug = 1\n")
+        );
+        assert!(!init("if true then\n    print(1)\nend\n")
+            .iter()
+            .any(|it| it.location().is_some()));
+
+        let (optimized, original) = compile(
+            "function f(x)
+    while true do
+        if x then
+            return 1
+        else
+            break
+        end
+        print(\"unreachable\")
+    end
+    return 2
+end
+",
+        )
+        .unwrap();
+        assert_eq!(verify(&optimized), Ok(()));
+
+        let body = &optimized.functions[0].body;
+        assert!(body.len() < original.functions[0].body.len());
+        assert!(!body.contains(&OpCode::PushConstString {
+            value: "unreachable".into()
+        }));
+
+        // No jump lands on another jump, or on the next instruction
+        for (idx, op) in body.iter().enumerate() {
+            if let Some(location) = op.location() {
+                assert_ne!(location, idx + 1);
+                assert!(!matches!(body.get(location), Some(OpCode::Jump { .. })));
+            }
+        }
+
+        // Each instruction still maps to the source it was generated from
+        let offset = body
+            .iter()
+            .position(|it| *it == OpCode::PushConstInt { value: 2 })
+            .unwrap();
+        let slice = optimized.functions[0].source_map.resolve(offset).unwrap();
+        assert_eq!(slice.value().as_ref(), "2");
+    }
+}
@@ -0,0 +1,209 @@
+use super::{
+    op_code::{CompareOp, OpCode},
+    source_map::{SourceMap, SourceMapEntry},
+    CompiledModule, Function,
+};
+
+/// Replaces common sequences of instructions with a single fused one, so they take one dispatch
+pub fn fuse_module(module: &CompiledModule) -> CompiledModule {
+    return CompiledModule {
+        functions: module.functions.iter().map(fuse).collect(),
+        init: fuse(&module.init),
+    };
+}
+
+pub fn fuse(function: &Function) -> Function {
+    let body = &function.body;
+
+    // Nothing can be fused across an instruction that is jumped to
+    let mut targets = vec![false; body.len() + 1];
+    for op in body.iter() {
+        if let Some(location) = op.location() {
+            targets[location] = true;
+        }
+    }
+
+    let mut ops = vec![];
+    let mut old_to_new = vec![0; body.len() + 1];
+    let mut idx = 0;
+
+    while idx < body.len() {
+        let (op, len) = fuse_at(&body[idx..], &targets[idx..]);
+
+        old_to_new[idx..idx + len].fill(ops.len());
+        ops.push(op);
+        idx += len;
+    }
+    old_to_new[body.len()] = ops.len();
+
+    return rewrite(function, ops, &old_to_new);
+}
+
+/// The fused instruction at the start of the slice, and how many instructions it replaces
+fn fuse_at(ops: &[OpCode], targets: &[bool]) -> (OpCode, usize) {
+    let free = |len: usize| ops.len() >= len && !targets[1..len].contains(&true);
+
+    // Only adding is fused, since subtracting from a string throws where adding a negative number wouldn't
+    if free(5)
+        && let [
+            OpCode::PushLocal { depth, slot },
+            OpCode::PushConstInt { value },
+            OpCode::OpAdd,
+            OpCode::StoreLocal {
+                depth: store_depth,
+                slot: store_slot,
+            },
+            OpCode::Pop,
+            ..,
+        ] = ops
+        && (depth, slot) == (store_depth, store_slot)
+    {
+        return (
+            OpCode::IncLocal {
+                depth: *depth,
+                slot: *slot,
+                value: *value,
+            },
+            5,
+        );
+    }
+
+    if free(2) {
+        match &ops[..2] {
+            [OpCode::StoreLocal { depth, slot }, OpCode::Pop] => {
+                return (
+                    OpCode::StoreLocalPop {
+                        depth: *depth,
+                        slot: *slot,
+                    },
+                    2,
+                );
+            }
+            [OpCode::StoreVariable { name }, OpCode::Pop] => {
+                return (OpCode::StoreVariablePop { name: name.clone() }, 2);
+            }
+            [compare, OpCode::JumpFalse { location }] => {
+                if let Some(op) = CompareOp::from_op_code(compare) {
+                    return (
+                        OpCode::CompareJump {
+                            op,
+                            location: *location,
+                        },
+                        2,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    return (ops[0].clone(), 1);
+}
+
+/// Builds the function with a new body, where `old_to_new` maps every old offset, and one past the end, to it's new offset.
+/// Jumps and the source map are moved to the new offsets.
+pub(super) fn rewrite(function: &Function, mut ops: Vec<OpCode>, old_to_new: &[usize]) -> Function {
+    for op in ops.iter_mut() {
+        if let Some(location) = op.location_mut() {
+            *location = old_to_new[*location];
+        }
+    }
+
+    let mut entries: Vec<SourceMapEntry> = vec![];
+    for entry in function.source_map.entries.iter() {
+        let entry = SourceMapEntry {
+            offset: old_to_new[entry.offset],
+            ..*entry
+        };
+
+        // The last entry for an instruction is the most specific one
+        match entries.last_mut() {
+            Some(last) if last.offset == entry.offset => *last = entry,
+            _ => entries.push(entry),
+        }
+    }
+
+    return Function {
+        name: function.name.clone(),
+        params: function.params.clone(),
+        body: ops.into_boxed_slice().into(),
+        source_map: SourceMap {
+            source: function.source_map.source.clone(),
+            entries: entries.into_boxed_slice().into(),
+        },
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bytecode::{
+            op_code::{CompareOp, OpCode},
+            verifier::verify,
+        },
+        parse_tree::tree::ParseTree,
+        tokenizer::Tokenizer,
+    };
+
+    #[test]
+    fn fused_loop() {
+        let src = "function count(limit)
+    let total = 0
+    let i = 0
+    while i < limit do
+        total += i
+        i += 1
+    end
+    return total
+end
+";
+        let mut tokenizer = Tokenizer::new(src.into());
+        let tree = ParseTree::try_parse(&mut tokenizer).unwrap().unwrap();
+        let module = tree.compile().unwrap();
+        assert_eq!(verify(&module), Ok(()));
+
+        let body = &module.functions[0].body;
+        assert!(
+            !body
+                .iter()
+                .any(|it| matches!(it, OpCode::PushVariable { .. }))
+        );
+        assert!(body.contains(&OpCode::IncLocal {
+            depth: 1,
+            slot: 1,
+            value: 1
+        }));
+        assert!(body.contains(&OpCode::StoreLocalPop { depth: 1, slot: 0 }));
+
+        // The loop jumps back to it's condition, which jumps past the loop once it's done
+        let condition = body
+            .iter()
+            .position(|it| {
+                matches!(
+                    it,
+                    OpCode::CompareJump {
+                        op: CompareOp::Lt,
+                        ..
+                    }
+                )
+            })
+            .unwrap();
+        assert_eq!(
+            body[condition - 2..condition],
+            [
+                OpCode::PushLocal { depth: 0, slot: 1 },
+                OpCode::PushLocal { depth: 1, slot: 0 }
+            ]
+        );
+        assert!(body.contains(&OpCode::Jump {
+            location: condition - 2
+        }));
+
+        // Subtracting isn't fused, `s -= 1` on a string has to throw rather than add -1 to it
+        let mut tokenizer = Tokenizer::new("function f(s)\n    s -= 1\n    return s\nend\n".into());
+        let tree = ParseTree::try_parse(&mut tokenizer).unwrap().unwrap();
+        let body = &tree.compile().unwrap().functions[0].body;
+        assert!(body.contains(&OpCode::OpSub));
+        assert!(!body.iter().any(|it| matches!(it, OpCode::IncLocal { .. })));
+    }
+}
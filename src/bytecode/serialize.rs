@@ -0,0 +1,667 @@
+use std::{
+    fmt::{self, Display},
+    sync::Arc,
+};
+
+use indexmap::IndexSet;
+
+use super::{
+    op_code::{CompareOp, OpCode},
+    source_map::{SourceMap, SourceMapEntry},
+    CompiledModule, Function,
+};
+
+pub const MAGIC: &[u8; 4] = b"BURC";
+pub const VERSION: u64 = 2;
+
+/// Why a compiled module couldn't be loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    BadMagic,
+    UnsupportedVersion(u64),
+    UnexpectedEof,
+    VarintOverflow,
+    InvalidString,
+    InvalidStringIndex(u64),
+    UnknownOpCode(u8),
+    /// A jump, catch or finally that points outside of it's function
+    InvalidJump {
+        function: Arc<str>,
+        offset: usize,
+        location: usize,
+    },
+    /// A source map entry that points outside of it's function or source
+    InvalidSourceMap {
+        function: Arc<str>,
+    },
+    TrailingBytes,
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            LoadError::BadMagic => write!(f, "Not a compiled burrow module"),
+            LoadError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported bytecode version {}", version)
+            }
+            LoadError::UnexpectedEof => write!(f, "Unexpected end of file"),
+            LoadError::VarintOverflow => write!(f, "Integer is too large"),
+            LoadError::InvalidString => write!(f, "String is not valid UTF-8"),
+            LoadError::InvalidStringIndex(idx) => write!(f, "Invalid string index {}", idx),
+            LoadError::UnknownOpCode(tag) => write!(f, "Unknown instruction {}", tag),
+            LoadError::InvalidJump {
+                function,
+                offset,
+                location,
+            } => write!(
+                f,
+                "Instruction {} in {} jumps to {}, which is out of bounds",
+                offset, function, location
+            ),
+            LoadError::InvalidSourceMap { function } => {
+                write!(f, "Invalid source map in {}", function)
+            }
+            LoadError::TrailingBytes => write!(f, "Unexpected data after the module"),
+        };
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Layout: magic, version, string pool, function count, init function, functions.
+/// Every integer is a LEB128 varint, signed ones are zigzag encoded first.
+pub fn to_bytes(module: &CompiledModule) -> Vec<u8> {
+    let mut writer = Writer::default();
+
+    writer.varint(module.functions.len() as u64);
+    writer.function(&module.init);
+    for function in module.functions.iter() {
+        writer.function(function);
+    }
+
+    let mut out = MAGIC.to_vec();
+    write_varint(&mut out, VERSION);
+
+    write_varint(&mut out, writer.strings.len() as u64);
+    for string in writer.strings.iter() {
+        write_varint(&mut out, string.len() as u64);
+        out.extend_from_slice(string.as_bytes());
+    }
+
+    out.extend_from_slice(&writer.out);
+
+    return out;
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Result<CompiledModule, LoadError> {
+    let mut reader = Reader {
+        bytes,
+        position: 0,
+        strings: vec![],
+    };
+
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(LoadError::BadMagic);
+    }
+
+    let version = reader.varint()?;
+    if version != VERSION {
+        return Err(LoadError::UnsupportedVersion(version));
+    }
+
+    let string_count = reader.length()?;
+    for _ in 0..string_count {
+        let len = reader.length()?;
+        let string =
+            std::str::from_utf8(reader.take(len)?).map_err(|_| LoadError::InvalidString)?;
+        reader.strings.push(string.into());
+    }
+
+    let function_count = reader.length()?;
+    let init = reader.function()?;
+
+    let mut functions = vec![];
+    for _ in 0..function_count {
+        functions.push(reader.function()?);
+    }
+
+    if reader.position != bytes.len() {
+        return Err(LoadError::TrailingBytes);
+    }
+
+    return Ok(CompiledModule {
+        functions: functions.into_boxed_slice().into(),
+        init,
+    });
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+
+        out.push(byte | 0x80);
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    out: Vec<u8>,
+    strings: IndexSet<Arc<str>>,
+}
+
+impl Writer {
+    fn varint(&mut self, value: u64) {
+        write_varint(&mut self.out, value);
+    }
+
+    fn signed(&mut self, value: isize) {
+        let value = value as i64;
+        self.varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn string(&mut self, value: &Arc<str>) {
+        let (idx, _) = self.strings.insert_full(value.clone());
+        self.varint(idx as u64);
+    }
+
+    fn function(&mut self, function: &Function) {
+        self.string(&function.name);
+
+        self.varint(function.params.len() as u64);
+        for param in function.params.iter() {
+            self.string(param);
+        }
+
+        self.varint(function.body.len() as u64);
+        for op in function.body.iter() {
+            self.op(op);
+        }
+
+        // The source is optional, so it's stored as it's index plus one
+        match &function.source_map.source {
+            Some(source) => {
+                let (idx, _) = self.strings.insert_full(source.clone());
+                self.varint(idx as u64 + 1);
+            }
+            None => self.varint(0),
+        }
+
+        self.varint(function.source_map.entries.len() as u64);
+        let mut offset = 0;
+        for entry in function.source_map.entries.iter() {
+            self.varint((entry.offset - offset) as u64);
+            self.varint(entry.start as u64);
+            self.varint(entry.end.saturating_sub(entry.start) as u64);
+            offset = entry.offset;
+        }
+    }
+
+    fn op(&mut self, op: &OpCode) {
+        self.out.push(tag(op));
+
+        match op {
+            OpCode::PushVariable { name }
+            | OpCode::StoreVariable { name }
+            | OpCode::InitVariable { name }
+            | OpCode::MarkVariableConst { name }
+            | OpCode::StoreVariablePop { name }
+            | OpCode::Export { name } => self.string(name),
+            OpCode::PushLocal { depth, slot }
+            | OpCode::StoreLocal { depth, slot }
+            | OpCode::StoreLocalPop { depth, slot } => {
+                self.varint(*depth as u64);
+                self.varint(*slot as u64);
+            }
+            OpCode::InitLocal { slot } | OpCode::MarkLocalConst { slot } => {
+                self.varint(*slot as u64)
+            }
+            OpCode::IncLocal { depth, slot, value } => {
+                self.varint(*depth as u64);
+                self.varint(*slot as u64);
+                self.signed(*value);
+            }
+            OpCode::CompareJump { op, location } => {
+                self.out
+                    .push(CompareOp::ALL.iter().position(|it| it == op).unwrap() as u8);
+                self.varint(*location as u64);
+            }
+            OpCode::PushConstString { value } => self.string(value),
+            OpCode::Import { path } => self.string(path),
+            OpCode::PushConstInt { value } => self.signed(*value),
+            OpCode::PushConstFloat { value } => {
+                self.out.extend_from_slice(&value.to_le_bytes());
+            }
+            OpCode::PushConstBool { value } => self.out.push(*value as u8),
+            OpCode::PushFunction { index } => self.varint(*index as u64),
+            OpCode::PushNewArray { initial_size } => self.varint(*initial_size as u64),
+            OpCode::Invoke {
+                param_count,
+                this_call,
+            } => {
+                self.varint(*param_count as u64);
+                self.out.push(*this_call as u8);
+            }
+            OpCode::Leave {
+                location,
+                handlers,
+                contexts,
+            } => {
+                self.varint(*location as u64);
+                self.varint(*handlers as u64);
+                self.varint(*contexts as u64);
+            }
+            _ => {
+                if let Some(location) = op.location() {
+                    self.varint(location as u64);
+                }
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    strings: Vec<Arc<str>>,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], LoadError> {
+        let end = self
+            .position
+            .checked_add(len)
+            .ok_or(LoadError::UnexpectedEof)?;
+        let bytes = self
+            .bytes
+            .get(self.position..end)
+            .ok_or(LoadError::UnexpectedEof)?;
+        self.position = end;
+        return Ok(bytes);
+    }
+
+    fn byte(&mut self) -> Result<u8, LoadError> {
+        return Ok(self.take(1)?[0]);
+    }
+
+    fn bool(&mut self) -> Result<bool, LoadError> {
+        return Ok(self.byte()? != 0);
+    }
+
+    fn varint(&mut self) -> Result<u64, LoadError> {
+        let mut value = 0u64;
+        let mut shift = 0;
+
+        loop {
+            let byte = self.byte()?;
+            if shift >= 64 || (shift == 63 && byte > 1) {
+                return Err(LoadError::VarintOverflow);
+            }
+
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    fn usize(&mut self) -> Result<usize, LoadError> {
+        return usize::try_from(self.varint()?).map_err(|_| LoadError::VarintOverflow);
+    }
+
+    /// A count of things that follow, which can't be more than the bytes left
+    fn length(&mut self) -> Result<usize, LoadError> {
+        let len = self.usize()?;
+        if len > self.bytes.len() - self.position {
+            return Err(LoadError::UnexpectedEof);
+        }
+        return Ok(len);
+    }
+
+    fn signed(&mut self) -> Result<isize, LoadError> {
+        let value = self.varint()?;
+        let value = ((value >> 1) as i64) ^ -((value & 1) as i64);
+        return isize::try_from(value).map_err(|_| LoadError::VarintOverflow);
+    }
+
+    fn string(&mut self) -> Result<Arc<str>, LoadError> {
+        let idx = self.varint()?;
+        return self
+            .strings
+            .get(idx as usize)
+            .cloned()
+            .ok_or(LoadError::InvalidStringIndex(idx));
+    }
+
+    fn function(&mut self) -> Result<Function, LoadError> {
+        let name = self.string()?;
+
+        let param_count = self.length()?;
+        let mut params = vec![];
+        for _ in 0..param_count {
+            params.push(self.string()?);
+        }
+
+        let op_count = self.length()?;
+        let mut body = vec![];
+        for _ in 0..op_count {
+            body.push(self.op()?);
+        }
+
+        // Jumping to the very end is allowed, and returns from the function
+        for (offset, op) in body.iter().enumerate() {
+            if let Some(location) = op.location()
+                && location > body.len()
+            {
+                return Err(LoadError::InvalidJump {
+                    function: name,
+                    offset,
+                    location,
+                });
+            }
+        }
+
+        let source = match self.varint()? {
+            0 => None,
+            idx => Some(
+                self.strings
+                    .get(idx as usize - 1)
+                    .cloned()
+                    .ok_or(LoadError::InvalidStringIndex(idx - 1))?,
+            ),
+        };
+
+        let entry_count = self.length()?;
+        let mut entries = vec![];
+        let mut offset = 0usize;
+        for _ in 0..entry_count {
+            offset = offset
+                .checked_add(self.usize()?)
+                .ok_or(LoadError::VarintOverflow)?;
+            let start = self.usize()?;
+            let end = start
+                .checked_add(self.usize()?)
+                .ok_or(LoadError::VarintOverflow)?;
+
+            let in_source = source.as_ref().is_some_and(|it| end <= it.len());
+            if offset > body.len() || !in_source {
+                return Err(LoadError::InvalidSourceMap { function: name });
+            }
+
+            entries.push(SourceMapEntry { offset, start, end });
+        }
+
+        return Ok(Function {
+            name,
+            params: params.into_boxed_slice().into(),
+            body: body.into_boxed_slice().into(),
+            source_map: SourceMap {
+                source,
+                entries: entries.into_boxed_slice().into(),
+            },
+        });
+    }
+
+    fn op(&mut self) -> Result<OpCode, LoadError> {
+        let tag = self.byte()?;
+
+        return Ok(match tag {
+            0 => OpCode::PushVariable {
+                name: self.string()?,
+            },
+            1 => OpCode::PushException,
+            2 => OpCode::PushThis,
+            3 => OpCode::PushPrototype,
+            4 => OpCode::StoreProtorype,
+            5 => OpCode::PushConstInt {
+                value: self.signed()?,
+            },
+            6 => OpCode::PushConstFloat {
+                value: f32::from_le_bytes(self.take(4)?.try_into().unwrap()),
+            },
+            7 => OpCode::PushConstBool {
+                value: self.bool()?,
+            },
+            8 => OpCode::PushConstString {
+                value: self.string()?,
+            },
+            9 => OpCode::PushFunction {
+                index: self.usize()?,
+            },
+            10 => OpCode::PushNewObject,
+            11 => OpCode::PushNewArray {
+                initial_size: self.usize()?,
+            },
+            12 => OpCode::PushRange,
+            13 => OpCode::PushKeys,
+            14 => OpCode::PushConstNone,
+            15 => OpCode::StoreVariable {
+                name: self.string()?,
+            },
+            16 => OpCode::InitVariable {
+                name: self.string()?,
+            },
+            17 => OpCode::MarkVariableConst {
+                name: self.string()?,
+            },
+            18 => OpCode::Invoke {
+                param_count: self.usize()?,
+                this_call: self.bool()?,
+            },
+            19 => OpCode::PushContext,
+            20 => OpCode::PopContext,
+            21 => OpCode::PushIndex,
+            22 => OpCode::StoreIndex,
+            23 => OpCode::Dupe,
+            24 => OpCode::DupeTwo,
+            25 => OpCode::Pop,
+            26 => OpCode::Throw,
+            27 => OpCode::Return,
+            28 => OpCode::OpAdd,
+            29 => OpCode::OpSub,
+            30 => OpCode::OpMul,
+            31 => OpCode::OpDiv,
+            32 => OpCode::OpRem,
+            33 => OpCode::OpGe,
+            34 => OpCode::OpLe,
+            35 => OpCode::OpGt,
+            36 => OpCode::OpLt,
+            37 => OpCode::OpEq,
+            38 => OpCode::OpNe,
+            39 => OpCode::OpUnaryAdd,
+            40 => OpCode::OpUnarySub,
+            41 => OpCode::OpUnaryNot,
+            42 => OpCode::ProtoEq,
+            43 => OpCode::ProtoNe,
+            44 => OpCode::Jump {
+                location: self.usize()?,
+            },
+            45 => OpCode::JumpTrue {
+                location: self.usize()?,
+            },
+            46 => OpCode::JumpFalse {
+                location: self.usize()?,
+            },
+            47 => OpCode::JumpNone {
+                location: self.usize()?,
+            },
+            48 => OpCode::JumpNotNone {
+                location: self.usize()?,
+            },
+            49 => OpCode::PushCatch {
+                location: self.usize()?,
+            },
+            50 => OpCode::PopCatch,
+            51 => OpCode::PushFinally {
+                location: self.usize()?,
+            },
+            52 => OpCode::PopFinally,
+            53 => OpCode::EndFinally,
+            54 => OpCode::Leave {
+                location: self.usize()?,
+                handlers: self.usize()?,
+                contexts: self.usize()?,
+            },
+            55 => OpCode::Import {
+                path: self.string()?,
+            },
+            56 => OpCode::Export {
+                name: self.string()?,
+            },
+            57 => OpCode::PushLocal {
+                depth: self.usize()?,
+                slot: self.usize()?,
+            },
+            58 => OpCode::StoreLocal {
+                depth: self.usize()?,
+                slot: self.usize()?,
+            },
+            59 => OpCode::InitLocal {
+                slot: self.usize()?,
+            },
+            60 => OpCode::MarkLocalConst {
+                slot: self.usize()?,
+            },
+            61 => OpCode::StoreVariablePop {
+                name: self.string()?,
+            },
+            62 => OpCode::StoreLocalPop {
+                depth: self.usize()?,
+                slot: self.usize()?,
+            },
+            63 => OpCode::IncLocal {
+                depth: self.usize()?,
+                slot: self.usize()?,
+                value: self.signed()?,
+            },
+            64 => {
+                let op = self.byte()?;
+                OpCode::CompareJump {
+                    op: *CompareOp::ALL
+                        .get(op as usize)
+                        .ok_or(LoadError::UnknownOpCode(tag))?,
+                    location: self.usize()?,
+                }
+            }
+            _ => return Err(LoadError::UnknownOpCode(tag)),
+        });
+    }
+}
+
+/// The tag written before each instruction, these can't change without bumping the version
+fn tag(op: &OpCode) -> u8 {
+    return match op {
+        OpCode::PushVariable { .. } => 0,
+        OpCode::PushException => 1,
+        OpCode::PushThis => 2,
+        OpCode::PushPrototype => 3,
+        OpCode::StoreProtorype => 4,
+        OpCode::PushConstInt { .. } => 5,
+        OpCode::PushConstFloat { .. } => 6,
+        OpCode::PushConstBool { .. } => 7,
+        OpCode::PushConstString { .. } => 8,
+        OpCode::PushFunction { .. } => 9,
+        OpCode::PushNewObject => 10,
+        OpCode::PushNewArray { .. } => 11,
+        OpCode::PushRange => 12,
+        OpCode::PushKeys => 13,
+        OpCode::PushConstNone => 14,
+        OpCode::StoreVariable { .. } => 15,
+        OpCode::InitVariable { .. } => 16,
+        OpCode::MarkVariableConst { .. } => 17,
+        OpCode::Invoke { .. } => 18,
+        OpCode::PushContext => 19,
+        OpCode::PopContext => 20,
+        OpCode::PushIndex => 21,
+        OpCode::StoreIndex => 22,
+        OpCode::Dupe => 23,
+        OpCode::DupeTwo => 24,
+        OpCode::Pop => 25,
+        OpCode::Throw => 26,
+        OpCode::Return => 27,
+        OpCode::OpAdd => 28,
+        OpCode::OpSub => 29,
+        OpCode::OpMul => 30,
+        OpCode::OpDiv => 31,
+        OpCode::OpRem => 32,
+        OpCode::OpGe => 33,
+        OpCode::OpLe => 34,
+        OpCode::OpGt => 35,
+        OpCode::OpLt => 36,
+        OpCode::OpEq => 37,
+        OpCode::OpNe => 38,
+        OpCode::OpUnaryAdd => 39,
+        OpCode::OpUnarySub => 40,
+        OpCode::OpUnaryNot => 41,
+        OpCode::ProtoEq => 42,
+        OpCode::ProtoNe => 43,
+        OpCode::Jump { .. } => 44,
+        OpCode::JumpTrue { .. } => 45,
+        OpCode::JumpFalse { .. } => 46,
+        OpCode::JumpNone { .. } => 47,
+        OpCode::JumpNotNone { .. } => 48,
+        OpCode::PushCatch { .. } => 49,
+        OpCode::PopCatch => 50,
+        OpCode::PushFinally { .. } => 51,
+        OpCode::PopFinally => 52,
+        OpCode::EndFinally => 53,
+        OpCode::Leave { .. } => 54,
+        OpCode::Import { .. } => 55,
+        OpCode::Export { .. } => 56,
+        OpCode::PushLocal { .. } => 57,
+        OpCode::StoreLocal { .. } => 58,
+        OpCode::InitLocal { .. } => 59,
+        OpCode::MarkLocalConst { .. } => 60,
+        OpCode::StoreVariablePop { .. } => 61,
+        OpCode::StoreLocalPop { .. } => 62,
+        OpCode::IncLocal { .. } => 63,
+        OpCode::CompareJump { .. } => 64,
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{parse_tree::tree::ParseTree, tokenizer::Tokenizer};
+
+    use super::{from_bytes, to_bytes, LoadError, MAGIC};
+
+    const SRC: &str = r#"let total = 0
+
+export function add(a, b)
+    return a + b
+end
+
+outer: for each i in 0 to 10 step 2 do
+    try
+        total += add(i, -3.5)
+    catch e
+        break outer
+    finally
+        total = total ?? 0
+    end
+end
+"#;
+
+    #[test]
+    fn roundtrip() {
+        let mut tokenizer = Tokenizer::new(SRC.into());
+        let tree = ParseTree::try_parse(&mut tokenizer).unwrap().unwrap();
+        let module = tree.compile().unwrap();
+
+        let bytes = to_bytes(&module);
+        assert_eq!(&bytes[..4], MAGIC);
+        assert_eq!(from_bytes(&bytes).unwrap(), module);
+
+        assert_eq!(
+            from_bytes(&bytes[..bytes.len() - 1]).unwrap_err(),
+            LoadError::UnexpectedEof
+        );
+        assert_eq!(from_bytes(b"BURD").unwrap_err(), LoadError::BadMagic);
+    }
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use crate::string::StringSlice;
+
+/// Maps instruction offsets of a function back to the source they were generated from
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceMap {
+    pub source: Option<Arc<str>>,
+    /// Sorted by offset, each entry covers the instructions up to the next one
+    pub entries: Arc<[SourceMapEntry]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceMapEntry {
+    pub offset: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl SourceMap {
+    pub fn empty() -> Self {
+        return Self {
+            source: None,
+            entries: Arc::new([]),
+        };
+    }
+
+    /// Finds the source span of the instruction at the offset
+    pub fn resolve(&self, offset: usize) -> Option<StringSlice> {
+        let source = self.source.as_ref()?;
+
+        let idx = match self.entries.binary_search_by_key(&offset, |it| it.offset) {
+            Ok(idx) => idx,
+            Err(0) => return None,
+            Err(idx) => idx - 1,
+        };
+
+        let entry = &self.entries[idx];
+
+        return Some(StringSlice {
+            src: source.clone(),
+            start: entry.start,
+            end: entry.end,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{bytecode::op_code::OpCode, parse_tree::tree::ParseTree, tokenizer::Tokenizer};
+
+    #[test]
+    fn resolve_throw() {
+        let mut tokenizer = Tokenizer::new("let a = 1\nthrow a\n".into());
+        let tree = ParseTree::try_parse(&mut tokenizer).unwrap().unwrap();
+        let module = tree.compile().unwrap();
+
+        let init = &module.init;
+        let offset = init
+            .body
+            .iter()
+            .position(|it| *it == OpCode::Throw)
+            .unwrap();
+
+        let slice = init.source_map.resolve(offset).unwrap();
+        assert_eq!(slice.line_column(), (2, 1));
+        assert_eq!(slice.value().as_ref(), "throw a");
+    }
+}
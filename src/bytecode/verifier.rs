@@ -0,0 +1,430 @@
+use std::{
+    fmt::{self, Display},
+    sync::Arc,
+};
+
+use super::{op_code::OpCode, CompiledModule, Function};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    pub function: Arc<str>,
+    pub offset: usize,
+    pub kind: VerifyErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyErrorKind {
+    /// A jump, catch or finally that points past the end of the function
+    JumpOutOfBounds(usize),
+    FunctionOutOfBounds(usize),
+    /// A local in a context that isn't pushed
+    ContextOutOfBounds(usize),
+    StackUnderflow,
+    /// Two paths reach the same instruction with different stacks, contexts or handlers
+    InconsistentState,
+    UnbalancedContext,
+    UnbalancedHandlers,
+    ImportOutsideInit,
+    /// The end of the function is reached with values, contexts or handlers left over
+    UnbalancedEnd,
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match &self.kind {
+            VerifyErrorKind::JumpOutOfBounds(location) => {
+                format!("jumps to {}, which is out of bounds", location)
+            }
+            VerifyErrorKind::FunctionOutOfBounds(index) => {
+                format!("pushes function {}, which doesn't exist", index)
+            }
+            VerifyErrorKind::ContextOutOfBounds(depth) => {
+                format!("uses a local {} contexts up, which doesn't exist", depth)
+            }
+            VerifyErrorKind::StackUnderflow => "pops from an empty stack".to_string(),
+            VerifyErrorKind::InconsistentState => {
+                "is reached with different stack depths, contexts or handlers".to_string()
+            }
+            VerifyErrorKind::UnbalancedContext => "pops a context that wasn't pushed".to_string(),
+            VerifyErrorKind::UnbalancedHandlers => {
+                "pops a catch or finally that wasn't pushed".to_string()
+            }
+            VerifyErrorKind::ImportOutsideInit => "imports outside of the init function".to_string(),
+            VerifyErrorKind::UnbalancedEnd => {
+                "ends with values, contexts or handlers left over".to_string()
+            }
+        };
+
+        return write!(f, "Instruction {} in {} {}", self.offset, self.function, message);
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Handler {
+    Catch(usize),
+    Finally(usize),
+}
+
+/// What is known about the machine before an instruction runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct State {
+    stack: usize,
+    contexts: usize,
+    handlers: Vec<Handler>,
+}
+
+/// Checks that every function in the module can run without corrupting the interpreter.
+///
+/// A catch or finally handler starts with the stack and contexts it had when it was pushed.
+pub fn verify(module: &CompiledModule) -> Result<(), VerifyError> {
+    verify_function(&module.init, module.functions.len(), true)?;
+
+    for function in module.functions.iter() {
+        verify_function(function, module.functions.len(), false)?;
+    }
+
+    return Ok(());
+}
+
+pub fn verify_function(
+    function: &Function,
+    function_count: usize,
+    is_init: bool,
+) -> Result<(), VerifyError> {
+    let body = &function.body;
+    let error = |offset: usize, kind: VerifyErrorKind| VerifyError {
+        function: function.name.clone(),
+        offset,
+        kind,
+    };
+
+    // One past the end is the state the function ends with
+    let mut states: Vec<Option<State>> = vec![None; body.len() + 1];
+    let mut worklist = vec![0];
+    states[0] = Some(State {
+        stack: 0,
+        contexts: 0,
+        handlers: vec![],
+    });
+
+    let visit = |states: &mut Vec<Option<State>>,
+                     worklist: &mut Vec<usize>,
+                     from: usize,
+                     location: usize,
+                     state: State|
+     -> Result<(), VerifyError> {
+        let Some(existing) = states.get(location) else {
+            return Err(error(from, VerifyErrorKind::JumpOutOfBounds(location)));
+        };
+
+        match existing {
+            Some(existing) if *existing != state => {
+                return Err(error(location, VerifyErrorKind::InconsistentState));
+            }
+            Some(_) => {}
+            None => {
+                states[location] = Some(state);
+                worklist.push(location);
+            }
+        }
+
+        return Ok(());
+    };
+
+    while let Some(offset) = worklist.pop() {
+        let mut state = states[offset].clone().unwrap();
+
+        let Some(op) = body.get(offset) else {
+            if state.stack != 0 || state.contexts != 0 || !state.handlers.is_empty() {
+                return Err(error(offset, VerifyErrorKind::UnbalancedEnd));
+            }
+            continue;
+        };
+
+        let (pops, pushes) = stack_effect(op);
+        if state.stack < pops {
+            return Err(error(offset, VerifyErrorKind::StackUnderflow));
+        }
+        state.stack = state.stack - pops + pushes;
+
+        let mut falls_through = true;
+
+        match op {
+            OpCode::PushFunction { index } if *index >= function_count => {
+                return Err(error(offset, VerifyErrorKind::FunctionOutOfBounds(*index)));
+            }
+            OpCode::Import { .. } if !is_init => {
+                return Err(error(offset, VerifyErrorKind::ImportOutsideInit));
+            }
+            // Functions have a context holding their params, under the ones they push
+            OpCode::PushLocal { depth, .. }
+            | OpCode::StoreLocal { depth, .. }
+            | OpCode::StoreLocalPop { depth, .. }
+            | OpCode::IncLocal { depth, .. }
+                if *depth + is_init as usize > state.contexts =>
+            {
+                return Err(error(offset, VerifyErrorKind::ContextOutOfBounds(*depth)));
+            }
+            OpCode::InitLocal { .. } | OpCode::MarkLocalConst { .. }
+                if is_init && state.contexts == 0 =>
+            {
+                return Err(error(offset, VerifyErrorKind::ContextOutOfBounds(0)));
+            }
+
+            OpCode::PushContext => state.contexts += 1,
+            OpCode::PopContext => {
+                if state.contexts == 0 {
+                    return Err(error(offset, VerifyErrorKind::UnbalancedContext));
+                }
+                state.contexts -= 1;
+            }
+
+            OpCode::PushCatch { location } | OpCode::PushFinally { location } => {
+                visit(&mut states, &mut worklist, offset, *location, state.clone())?;
+
+                state.handlers.push(match op {
+                    OpCode::PushCatch { .. } => Handler::Catch(*location),
+                    _ => Handler::Finally(*location),
+                });
+            }
+            OpCode::PopCatch => {
+                let Some(Handler::Catch(_)) = state.handlers.pop() else {
+                    return Err(error(offset, VerifyErrorKind::UnbalancedHandlers));
+                };
+            }
+            OpCode::PopFinally => {
+                let Some(Handler::Finally(_)) = state.handlers.pop() else {
+                    return Err(error(offset, VerifyErrorKind::UnbalancedHandlers));
+                };
+            }
+
+            OpCode::Leave {
+                location,
+                handlers,
+                contexts,
+            } => {
+                if state.handlers.len() < *handlers {
+                    return Err(error(offset, VerifyErrorKind::UnbalancedHandlers));
+                }
+                if state.contexts < *contexts {
+                    return Err(error(offset, VerifyErrorKind::UnbalancedContext));
+                }
+
+                state.handlers.truncate(state.handlers.len() - handlers);
+                state.contexts -= contexts;
+
+                visit(&mut states, &mut worklist, offset, *location, state.clone())?;
+                falls_through = false;
+            }
+            OpCode::Jump { location } => {
+                visit(&mut states, &mut worklist, offset, *location, state.clone())?;
+                falls_through = false;
+            }
+            OpCode::JumpTrue { location }
+            | OpCode::JumpFalse { location }
+            | OpCode::CompareJump { location, .. }
+            | OpCode::JumpNone { location }
+            | OpCode::JumpNotNone { location } => {
+                visit(&mut states, &mut worklist, offset, *location, state.clone())?;
+            }
+
+            OpCode::Throw | OpCode::Return => falls_through = false,
+
+            _ => {}
+        }
+
+        if falls_through {
+            visit(&mut states, &mut worklist, offset, offset + 1, state)?;
+        }
+    }
+
+    return Ok(());
+}
+
+/// How many values the instruction pops, and how many it pushes
+fn stack_effect(op: &OpCode) -> (usize, usize) {
+    return match op {
+        OpCode::PushVariable { .. }
+        | OpCode::PushLocal { .. }
+        | OpCode::PushException
+        | OpCode::PushThis
+        | OpCode::PushConstInt { .. }
+        | OpCode::PushConstFloat { .. }
+        | OpCode::PushConstBool { .. }
+        | OpCode::PushConstString { .. }
+        | OpCode::PushFunction { .. }
+        | OpCode::PushNewObject
+        | OpCode::PushNewArray { .. }
+        | OpCode::PushConstNone
+        | OpCode::Import { .. } => (0, 1),
+
+        OpCode::PushPrototype
+        | OpCode::PushKeys
+        | OpCode::StoreVariable { .. }
+        | OpCode::StoreLocal { .. }
+        | OpCode::OpUnaryAdd
+        | OpCode::OpUnarySub
+        | OpCode::OpUnaryNot => (1, 1),
+
+        OpCode::StoreProtorype => (2, 0),
+        OpCode::PushRange => (3, 1),
+
+        OpCode::Invoke {
+            param_count,
+            this_call,
+        } => (param_count.saturating_add(1 + *this_call as usize), 1),
+
+        OpCode::PushIndex
+        | OpCode::OpAdd
+        | OpCode::OpSub
+        | OpCode::OpMul
+        | OpCode::OpDiv
+        | OpCode::OpRem
+        | OpCode::OpGe
+        | OpCode::OpLe
+        | OpCode::OpGt
+        | OpCode::OpLt
+        | OpCode::OpEq
+        | OpCode::OpNe
+        | OpCode::ProtoEq
+        | OpCode::ProtoNe => (2, 1),
+        OpCode::StoreIndex => (3, 1),
+        OpCode::CompareJump { .. } => (2, 0),
+
+        OpCode::Dupe => (1, 2),
+        OpCode::DupeTwo => (2, 4),
+
+        OpCode::Pop
+        | OpCode::StoreVariablePop { .. }
+        | OpCode::StoreLocalPop { .. }
+        | OpCode::Throw
+        | OpCode::Return
+        | OpCode::JumpTrue { .. }
+        | OpCode::JumpFalse { .. }
+        | OpCode::JumpNone { .. }
+        | OpCode::JumpNotNone { .. } => (1, 0),
+
+        OpCode::InitVariable { .. }
+        | OpCode::MarkVariableConst { .. }
+        | OpCode::InitLocal { .. }
+        | OpCode::MarkLocalConst { .. }
+        | OpCode::IncLocal { .. }
+        | OpCode::PushContext
+        | OpCode::PopContext
+        | OpCode::Jump { .. }
+        | OpCode::PushCatch { .. }
+        | OpCode::PopCatch
+        | OpCode::PushFinally { .. }
+        | OpCode::PopFinally
+        | OpCode::EndFinally
+        | OpCode::Leave { .. }
+        | OpCode::Export { .. } => (0, 0),
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        bytecode::{op_code::OpCode, source_map::SourceMap, Function},
+        parse_tree::tree::ParseTree,
+        tokenizer::Tokenizer,
+    };
+
+    use super::{verify, verify_function, VerifyErrorKind};
+
+    fn function(body: Vec<OpCode>) -> Function {
+        return Function {
+            name: "test".into(),
+            params: Arc::new([]),
+            body: body.into_boxed_slice().into(),
+            source_map: SourceMap::empty(),
+        };
+    }
+
+    fn verify_body(body: Vec<OpCode>, is_init: bool) -> Option<VerifyErrorKind> {
+        return verify_function(&function(body), 0, is_init)
+            .err()
+            .map(|it| it.kind);
+    }
+
+    #[test]
+    fn compiled_code_verifies() {
+        let src = r##"from "#fs" import everything as fs
+
+let values = [1, 2, { a = 3 }]
+let total = 0
+
+function Point.add(this, x)
+    return this.x?.add(x) ?? x
+end
+
+outer: for each i, value in values do
+    try
+        while total < 10 do
+            total += i
+            if total == 5 then
+                continue outer
+            else if total > 8 then
+                break
+            end
+        end
+    catch e is Error
+        break outer
+    finally
+        total = total or 0
+    end
+end
+"##;
+        let mut tokenizer = Tokenizer::new(src.into());
+        let tree = ParseTree::try_parse(&mut tokenizer).unwrap().unwrap();
+
+        assert_eq!(verify(&tree.compile().unwrap()), Ok(()));
+    }
+
+    #[test]
+    fn malformed_code() {
+        assert_eq!(
+            verify_body(vec![OpCode::Jump { location: 2 }], true),
+            Some(VerifyErrorKind::JumpOutOfBounds(2))
+        );
+        assert_eq!(
+            verify_body(vec![OpCode::Pop], true),
+            Some(VerifyErrorKind::StackUnderflow)
+        );
+        assert_eq!(
+            verify_body(vec![OpCode::PushFunction { index: 0 }, OpCode::Pop], true),
+            Some(VerifyErrorKind::FunctionOutOfBounds(0))
+        );
+        assert_eq!(
+            verify_body(vec![OpCode::Import { path: "#fs".into() }, OpCode::Pop], false),
+            Some(VerifyErrorKind::ImportOutsideInit)
+        );
+        assert_eq!(
+            verify_body(vec![OpCode::PushContext], true),
+            Some(VerifyErrorKind::UnbalancedEnd)
+        );
+        assert_eq!(
+            verify_body(vec![OpCode::PushCatch { location: 2 }, OpCode::PopFinally], true),
+            Some(VerifyErrorKind::UnbalancedHandlers)
+        );
+
+        // The jump skips a push, so the end is reached with two different stacks
+        assert_eq!(
+            verify_body(
+                vec![
+                    OpCode::PushConstBool { value: true },
+                    OpCode::JumpTrue { location: 3 },
+                    OpCode::PushConstNone,
+                    OpCode::PushConstNone,
+                    OpCode::Pop,
+                ],
+                true
+            ),
+            Some(VerifyErrorKind::InconsistentState)
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::{fs, time::Instant};
+
+use crate::bytecode::{op_code::OpCode, optimizer, peephole, CompiledModule};
+
+use super::{parse_file, usage};
+
+/// How many times each file is compiled, the fastest is reported
+const RUNS: usize = 20;
+
+/// `burrow bench [files...]`, defaulting to the scripts in `bench/`.
+///
+/// This doesn't measure how fast scripts run, there is no interpreter to time yet. It compares the instructions
+/// before and after optimizing and fusing, how many of them look variables up by name, and how long generating
+/// the code takes.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let files = if args.is_empty() {
+        default_files()?
+    } else {
+        args.to_vec()
+    };
+
+    if files.iter().any(|it| it.starts_with('-')) {
+        return Err(usage());
+    }
+
+    println!(
+        "{:<28} {:>10} {:>10} {:>8} {:>10} {:>10} {:>12}",
+        "file", "plain", "optimized", "saved", "by name", "slots", "compile"
+    );
+
+    for file in files.iter() {
+        let tree = parse_file(file)?;
+
+        let mut fastest = None;
+        let mut module = None;
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            let generated = tree
+                .generate_module()
+                .map_err(|err| format!("{}: {:?}", file, err))?;
+            let elapsed = start.elapsed();
+
+            fastest = Some(fastest.map_or(elapsed, |it: std::time::Duration| it.min(elapsed)));
+            module = Some(generated);
+        }
+
+        let plain = module.unwrap();
+        let optimized = peephole::fuse_module(&optimizer::optimize_module(&plain));
+
+        let before = Counts::of(&plain);
+        let after = Counts::of(&optimized);
+
+        println!(
+            "{:<28} {:>10} {:>10} {:>7.1}% {:>10} {:>10} {:>10.0}us",
+            file,
+            before.instructions,
+            after.instructions,
+            100.0 - after.instructions as f64 * 100.0 / before.instructions.max(1) as f64,
+            after.named,
+            after.slots,
+            fastest.unwrap().as_secs_f64() * 1_000_000.0,
+        );
+    }
+
+    println!();
+    println!(
+        "Run time isn't measured, since there is no interpreter yet. compile is the fastest of {} code generations",
+        RUNS
+    );
+
+    return Ok(());
+}
+
+fn default_files() -> Result<Vec<String>, String> {
+    let entries = fs::read_dir("bench").map_err(|err| format!("bench: {}", err))?;
+
+    let mut files = entries
+        .filter_map(|it| it.ok())
+        .map(|it| it.path())
+        .filter(|it| it.extension().is_some_and(|ext| ext == "bur"))
+        .map(|it| it.display().to_string())
+        .collect::<Vec<_>>();
+    files.sort();
+
+    return Ok(files);
+}
+
+struct Counts {
+    instructions: usize,
+    /// Variables looked up by name at runtime
+    named: usize,
+    /// Locals resolved to slots
+    slots: usize,
+}
+
+impl Counts {
+    fn of(module: &CompiledModule) -> Self {
+        let mut counts = Counts {
+            instructions: 0,
+            named: 0,
+            slots: 0,
+        };
+
+        for function in module.functions.iter().chain([&module.init]) {
+            counts.instructions += function.body.len();
+
+            for op in function.body.iter() {
+                match op {
+                    OpCode::PushVariable { .. }
+                    | OpCode::StoreVariable { .. }
+                    | OpCode::StoreVariablePop { .. } => counts.named += 1,
+                    OpCode::PushLocal { .. }
+                    | OpCode::StoreLocal { .. }
+                    | OpCode::StoreLocalPop { .. }
+                    | OpCode::IncLocal { .. } => counts.slots += 1,
+                    _ => {}
+                }
+            }
+        }
+
+        return counts;
+    }
+}
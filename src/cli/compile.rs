@@ -0,0 +1,37 @@
+use std::{fs, path::PathBuf};
+
+use crate::bytecode::serialize;
+
+use super::{compile_tree, parse_file, usage};
+
+/// `burrow compile [-o <file>] <file>`
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut out = None;
+    let mut file = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--out" => {
+                let Some(path) = args.next() else {
+                    return Err(usage());
+                };
+                out = Some(PathBuf::from(path));
+            }
+            _ if file.is_none() => file = Some(arg.clone()),
+            _ => return Err(usage()),
+        }
+    }
+
+    let Some(file) = file else {
+        return Err(usage());
+    };
+
+    let out = out.unwrap_or_else(|| PathBuf::from(&file).with_extension("burc"));
+
+    let tree = parse_file(&file)?;
+    let module = compile_tree(&file, &tree)?;
+
+    return fs::write(&out, serialize::to_bytes(&module))
+        .map_err(|err| format!("{}: {}", out.display(), err));
+}
@@ -0,0 +1,85 @@
+use std::{fs, path::PathBuf, sync::Arc};
+
+use crate::doc::{html, markdown, ModuleDoc};
+
+use super::{parse_file, usage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Markdown,
+    Html,
+}
+
+/// `burrow doc [--format markdown|html] [-o <dir>] <files...>`
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut format = Format::Markdown;
+    let mut out = PathBuf::from("docs");
+    let mut files = vec![];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = match args.next().map(String::as_str) {
+                    Some("markdown" | "md") => Format::Markdown,
+                    Some("html") => Format::Html,
+                    _ => return Err(usage()),
+                };
+            }
+            "-o" | "--out" => {
+                let Some(dir) = args.next() else {
+                    return Err(usage());
+                };
+                out = dir.into();
+            }
+            _ => files.push(arg.clone()),
+        }
+    }
+
+    if files.is_empty() {
+        return Err(usage());
+    }
+
+    let mut modules = vec![];
+    for file in files.iter() {
+        let tree = parse_file(file)?;
+        modules.push(ModuleDoc::new(module_name(file), &tree));
+    }
+
+    // Sorted so the output doesn't depend on the argument order
+    modules.sort_by(|a, b| a.name.cmp(&b.name));
+
+    fs::create_dir_all(&out).map_err(|err| format!("{}: {}", out.display(), err))?;
+
+    let (extension, index) = match format {
+        Format::Markdown => ("md", markdown::render_index(&modules)),
+        Format::Html => ("html", html::render_index(&modules)),
+    };
+
+    write(out.join(format!("index.{}", extension)), &index)?;
+
+    for module in modules.iter() {
+        let page = match format {
+            Format::Markdown => markdown::render_module(module),
+            Format::Html => html::render_module(module),
+        };
+
+        write(
+            out.join(format!("{}.{}", module.page_name(), extension)),
+            &page,
+        )?;
+    }
+
+    return Ok(());
+}
+
+fn module_name(path: &str) -> Arc<str> {
+    let path = path.replace('\\', "/");
+    let path = path.strip_prefix("./").unwrap_or(&path);
+
+    return path.strip_suffix(".bur").unwrap_or(path).into();
+}
+
+fn write(path: PathBuf, contents: &str) -> Result<(), String> {
+    return fs::write(&path, contents).map_err(|err| format!("{}: {}", path.display(), err));
+}
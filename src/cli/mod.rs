@@ -0,0 +1,67 @@
+use std::{fs, sync::Arc};
+
+use crate::{
+    bytecode::{BytecodeGenerationError, CompiledModule},
+    parse_tree::tree::ParseTree,
+    tokenizer::Tokenizer,
+};
+
+pub mod bench;
+pub mod compile;
+pub mod doc;
+pub mod run;
+
+/// Runs the subcommand named by the first argument
+pub fn run(args: &[String]) -> Result<(), String> {
+    let Some(command) = args.first() else {
+        return Err(usage());
+    };
+
+    return match command.as_str() {
+        "bench" => bench::run(&args[1..]),
+        "compile" => compile::run(&args[1..]),
+        "doc" => doc::run(&args[1..]),
+        "run" => run::run(&args[1..]),
+        _ => Err(usage()),
+    };
+}
+
+fn usage() -> String {
+    return [
+        "Usage:",
+        "    burrow bench [files...]",
+        "    burrow compile [-o <file>] <file>",
+        "    burrow doc [--format markdown|html] [-o <dir>] <files...>",
+        "    burrow run <file>",
+    ]
+    .join("\n");
+}
+
+fn parse_file(path: &str) -> Result<ParseTree, String> {
+    let src: Arc<str> = fs::read_to_string(path)
+        .map_err(|err| format!("{}: {}", path, err))?
+        .into();
+
+    let mut tokenizer = Tokenizer::new(src);
+
+    return match ParseTree::try_parse(&mut tokenizer) {
+        Ok(Some(tree)) => Ok(tree),
+        Ok(None) => Err(format!("{}: Expected a module", path)),
+        Err(err) => Err(format!("{}: {:?}", path, err)),
+    };
+}
+
+/// Compiles the module, printing the resolver's warnings
+fn compile_tree(path: &str, tree: &ParseTree) -> Result<CompiledModule, String> {
+    let resolution = tree.resolve();
+    for diagnostic in resolution.diagnostics.iter() {
+        if !diagnostic.kind.is_error() {
+            eprintln!("{}:{}", path, diagnostic);
+        }
+    }
+
+    return tree.compile().map_err(|err| match err {
+        BytecodeGenerationError::Resolve(diagnostic) => format!("{}:{}", path, diagnostic),
+        err => format!("{}: {:?}", path, err),
+    });
+}
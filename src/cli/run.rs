@@ -0,0 +1,31 @@
+use std::fs;
+
+use crate::bytecode::{serialize, verifier, CompiledModule};
+
+use super::{compile_tree, parse_file, usage};
+
+/// `burrow run <file>`, with either a source file or a compiled `.burc` file
+pub fn run(args: &[String]) -> Result<(), String> {
+    let [file] = args else {
+        return Err(usage());
+    };
+
+    let module = load(file)?;
+    verifier::verify(&module).map_err(|err| format!("{}: {}", file, err))?;
+
+    return Err(format!(
+        "{}: The module is valid, but there is no interpreter to run it yet",
+        file
+    ));
+}
+
+fn load(file: &str) -> Result<CompiledModule, String> {
+    if !file.ends_with(".burc") {
+        let tree = parse_file(file)?;
+        return compile_tree(file, &tree);
+    }
+
+    let bytes = fs::read(file).map_err(|err| format!("{}: {}", file, err))?;
+
+    return serialize::from_bytes(&bytes).map_err(|err| format!("{}: {}", file, err));
+}
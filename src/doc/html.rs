@@ -0,0 +1,145 @@
+use super::{ClassDoc, ItemDoc, ModuleDoc};
+
+pub fn render_index(modules: &[ModuleDoc]) -> String {
+    let mut body = "<h1>Modules</h1>\n<ul>\n".to_string();
+
+    for module in modules {
+        body.push_str(&format!(
+            "<li><a href=\"{}.html\"><code>{}</code></a></li>\n",
+            escape(&module.page_name()),
+            escape(&module.name)
+        ));
+    }
+    body.push_str("</ul>\n");
+
+    return page("Modules", &body);
+}
+
+pub fn render_module(module: &ModuleDoc) -> String {
+    let mut body = format!("<h1>Module <code>{}</code></h1>\n", escape(&module.name));
+
+    if !module.functions.is_empty() {
+        body.push_str("<h2>Functions</h2>\n");
+        for func in module.functions.iter() {
+            render_item(&mut body, func, 3);
+        }
+    }
+
+    if !module.classes.is_empty() {
+        body.push_str("<h2>Classes</h2>\n");
+        for class in module.classes.iter() {
+            render_class(&mut body, class, 3);
+        }
+    }
+
+    if !module.variables.is_empty() {
+        body.push_str("<h2>Variables</h2>\n");
+        for variable in module.variables.iter() {
+            render_item(&mut body, variable, 3);
+        }
+    }
+
+    if !module.declarations.is_empty() {
+        body.push_str("<h2>Declarations</h2>\n");
+        for decl in module.declarations.iter() {
+            render_item(&mut body, decl, 3);
+        }
+    }
+
+    if !module.native_modules.is_empty() {
+        body.push_str("<h2>Native modules</h2>\n");
+        for native in module.native_modules.iter() {
+            body.push_str(&format!(
+                "<h3>Module <code>{}</code></h3>\n",
+                escape(&native.name)
+            ));
+            render_doc(&mut body, &native.doc);
+
+            for func in native.functions.iter() {
+                render_item(&mut body, func, 4);
+            }
+            for class in native.classes.iter() {
+                render_class(&mut body, class, 4);
+            }
+            for variable in native.variables.iter() {
+                render_item(&mut body, variable, 4);
+            }
+        }
+    }
+
+    return page(&module.name, &body);
+}
+
+fn page(title: &str, body: &str) -> String {
+    return format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        body
+    );
+}
+
+fn render_item(out: &mut String, item: &ItemDoc, level: usize) {
+    out.push_str(&format!(
+        "<h{0}><code>{1}</code></h{0}>\n",
+        level,
+        escape(&item.name)
+    ));
+    out.push_str(&format!(
+        "<pre><code>{}</code></pre>\n",
+        escape(&item.signature)
+    ));
+    render_doc(out, &item.doc);
+}
+
+fn render_class(out: &mut String, class: &ClassDoc, level: usize) {
+    out.push_str(&format!(
+        "<h{0}><code>{1}</code></h{0}>\n",
+        level,
+        escape(&class.name)
+    ));
+    out.push_str(&format!(
+        "<pre><code>{}</code></pre>\n",
+        escape(&class.signature)
+    ));
+    render_doc(out, &class.doc);
+
+    if !class.params.is_empty() {
+        out.push_str(&format!("<h{0}>Parameters</h{0}>\n<ul>\n", level + 1));
+        for param in class.params.iter() {
+            out.push_str(&format!("<li><code>{}</code></li>\n", escape(param)));
+        }
+        out.push_str("</ul>\n");
+    }
+
+    if !class.methods.is_empty() {
+        out.push_str(&format!("<h{0}>Methods</h{0}>\n", level + 1));
+        for method in class.methods.iter() {
+            render_item(out, method, usize::min(level + 2, 6));
+        }
+    }
+}
+
+fn render_doc(out: &mut String, doc: &Option<String>) {
+    if let Some(doc) = doc {
+        for paragraph in doc.split("\n\n") {
+            out.push_str(&format!("<p>{}</p>\n", escape(paragraph)));
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+
+    return out;
+}
@@ -0,0 +1,95 @@
+use super::{ClassDoc, ItemDoc, ModuleDoc};
+
+pub fn render_index(modules: &[ModuleDoc]) -> String {
+    let mut out = "# Modules\n\n".to_string();
+
+    for module in modules {
+        out.push_str(&format!("- [`{}`]({}.md)\n", module.name, module.page_name()));
+    }
+
+    return out;
+}
+
+pub fn render_module(module: &ModuleDoc) -> String {
+    let mut out = format!("# Module `{}`\n", module.name);
+
+    if !module.functions.is_empty() {
+        out.push_str("\n## Functions\n");
+        for func in module.functions.iter() {
+            render_item(&mut out, func, 3);
+        }
+    }
+
+    if !module.classes.is_empty() {
+        out.push_str("\n## Classes\n");
+        for class in module.classes.iter() {
+            render_class(&mut out, class, 3);
+        }
+    }
+
+    if !module.variables.is_empty() {
+        out.push_str("\n## Variables\n");
+        for variable in module.variables.iter() {
+            render_item(&mut out, variable, 3);
+        }
+    }
+
+    if !module.declarations.is_empty() {
+        out.push_str("\n## Declarations\n");
+        for decl in module.declarations.iter() {
+            render_item(&mut out, decl, 3);
+        }
+    }
+
+    if !module.native_modules.is_empty() {
+        out.push_str("\n## Native modules\n");
+        for native in module.native_modules.iter() {
+            out.push_str(&format!("\n### Module `{}`\n", native.name));
+            render_doc(&mut out, &native.doc);
+
+            for func in native.functions.iter() {
+                render_item(&mut out, func, 4);
+            }
+            for class in native.classes.iter() {
+                render_class(&mut out, class, 4);
+            }
+            for variable in native.variables.iter() {
+                render_item(&mut out, variable, 4);
+            }
+        }
+    }
+
+    return out;
+}
+
+fn render_item(out: &mut String, item: &ItemDoc, level: usize) {
+    out.push_str(&format!("\n{} `{}`\n", "#".repeat(level), item.name));
+    out.push_str(&format!("\n```burrow\n{}\n```\n", item.signature));
+    render_doc(out, &item.doc);
+}
+
+fn render_class(out: &mut String, class: &ClassDoc, level: usize) {
+    out.push_str(&format!("\n{} `{}`\n", "#".repeat(level), class.name));
+    out.push_str(&format!("\n```burrow\n{}\n```\n", class.signature));
+    render_doc(out, &class.doc);
+
+    if !class.params.is_empty() {
+        out.push_str(&format!("\n{} Parameters\n\n", "#".repeat(level + 1)));
+        for param in class.params.iter() {
+            out.push_str(&format!("- `{}`\n", param));
+        }
+    }
+
+    if !class.methods.is_empty() {
+        out.push_str(&format!("\n{} Methods\n", "#".repeat(level + 1)));
+        for method in class.methods.iter() {
+            render_item(out, method, level + 2);
+        }
+    }
+}
+
+fn render_doc(out: &mut String, doc: &Option<String>) {
+    if let Some(doc) = doc {
+        out.push_str(&format!("\n{}\n", doc));
+    }
+}
@@ -0,0 +1,412 @@
+use std::sync::Arc;
+
+use crate::{
+    parse_tree::{
+        decl::{
+            class::ClassDecl, function::FunctionDecl, variable::VariableDecl, IdeDecl,
+            IdeDeclKind, VariableList, VariableName,
+        },
+        stmt::{control::ControlKind, StmtKind},
+        tree::ParseTree,
+    },
+    string::StringSlice,
+};
+
+pub mod html;
+pub mod markdown;
+
+/// The documentation for a single source file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleDoc {
+    pub name: Arc<str>,
+    pub functions: Vec<ItemDoc>,
+    pub classes: Vec<ClassDoc>,
+    pub variables: Vec<ItemDoc>,
+    /// Top level `declare` statements that aren't inside of a `declare module` block
+    pub declarations: Vec<ItemDoc>,
+    pub native_modules: Vec<NativeModuleDoc>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemDoc {
+    pub name: Arc<str>,
+    pub signature: String,
+    pub doc: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassDoc {
+    pub name: Arc<str>,
+    pub signature: String,
+    pub doc: Option<String>,
+    pub params: Vec<String>,
+    pub methods: Vec<ItemDoc>,
+}
+
+/// The contents of a `declare module` block
+#[derive(Debug, Clone, PartialEq)]
+pub struct NativeModuleDoc {
+    pub name: Arc<str>,
+    pub doc: Option<String>,
+    pub functions: Vec<ItemDoc>,
+    pub classes: Vec<ClassDoc>,
+    pub variables: Vec<ItemDoc>,
+}
+
+impl ModuleDoc {
+    pub fn new(name: Arc<str>, tree: &ParseTree) -> Self {
+        let mut functions = vec![];
+        let mut classes = vec![];
+        let mut variables = vec![];
+        let mut declarations = vec![];
+        let mut native_modules = vec![];
+
+        for func in tree.functions.iter() {
+            if func.export && func.decl.base.is_none() {
+                functions.push(function_item(&func.decl, &func.slice));
+            }
+        }
+
+        for class in tree.classes.iter() {
+            if !class.export {
+                continue;
+            }
+
+            let mut doc = class_item(class, &class.slice);
+
+            for func in tree.functions.iter() {
+                if func.decl.base.as_ref() == Some(&class.name) {
+                    doc.methods.push(function_item(&func.decl, &func.slice));
+                }
+            }
+
+            classes.push(doc);
+        }
+
+        for stmt in tree.stmts.iter() {
+            match &stmt.kind {
+                StmtKind::Variable(variable) if variable.decl.export => {
+                    variables.push(variable_item(&variable.decl, &variable.slice));
+                }
+                StmtKind::Control(control) => {
+                    let ControlKind::Export(name) = &control.kind else {
+                        continue;
+                    };
+
+                    // `export name` refers back to a top level declaration, which holds the type and the comment
+                    let decl = tree.stmts.iter().find_map(|it| match &it.kind {
+                        StmtKind::Variable(variable) if &variable.decl.param.name == name => {
+                            Some(variable)
+                        }
+                        _ => None,
+                    });
+
+                    if let Some(variable) = decl {
+                        variables.push(variable_item(&variable.decl, &variable.slice));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for decl in tree.declares.iter() {
+            match &decl.kind {
+                IdeDeclKind::Module(module) => {
+                    let mut doc = NativeModuleDoc {
+                        name: module.name.clone(),
+                        doc: doc_comment(&decl.slice),
+                        functions: vec![],
+                        classes: vec![],
+                        variables: vec![],
+                    };
+
+                    for value in module.values.iter() {
+                        match &value.kind {
+                            IdeDeclKind::Function(func) => {
+                                doc.functions.push(function_item(func, &value.slice))
+                            }
+                            IdeDeclKind::Class(class) => {
+                                doc.classes.push(class_item(class, &value.slice))
+                            }
+                            IdeDeclKind::Variable(variable) => {
+                                doc.variables.push(variable_item(variable, &value.slice))
+                            }
+                            IdeDeclKind::Module(_) => {}
+                        }
+                    }
+
+                    native_modules.push(doc);
+                }
+                _ => declarations.push(declaration_item(decl)),
+            }
+        }
+
+        return Self {
+            name,
+            functions,
+            classes,
+            variables,
+            declarations,
+            native_modules,
+        };
+    }
+
+    /// A file name for this module's page, without an extension
+    pub fn page_name(&self) -> String {
+        return self
+            .name
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+            .collect();
+    }
+}
+
+fn declaration_item(decl: &IdeDecl) -> ItemDoc {
+    return match &decl.kind {
+        IdeDeclKind::Function(func) => function_item(func, &decl.slice),
+        IdeDeclKind::Variable(variable) => variable_item(variable, &decl.slice),
+        IdeDeclKind::Class(class) => {
+            let class = class_item(class, &decl.slice);
+            ItemDoc {
+                name: class.name,
+                signature: class.signature,
+                doc: class.doc,
+            }
+        }
+        IdeDeclKind::Module(module) => ItemDoc {
+            name: module.name.clone(),
+            signature: format!("module \"{}\"", module.name),
+            doc: doc_comment(&decl.slice),
+        },
+    };
+}
+
+fn function_item(decl: &FunctionDecl, slice: &StringSlice) -> ItemDoc {
+    let mut signature = "function ".to_string();
+
+    if let Some(base) = &decl.base {
+        signature.push_str(base);
+        signature.push('.');
+    }
+    signature.push_str(&decl.name);
+
+    if let Some(generics) = &decl.generics {
+        signature.push_str(&format!("[{}]", variable_list(generics)));
+    }
+
+    let mut params = vec![];
+    if decl.this {
+        params.push(match &decl.this_ty {
+            Some(ty) => format!("this {}", ty),
+            None => "this".to_string(),
+        });
+    }
+    if let Some(list) = &decl.params {
+        params.push(variable_list(list));
+    }
+    signature.push_str(&format!("({})", params.join(", ")));
+
+    if let Some(ty) = &decl.ty {
+        signature.push_str(&format!(": {}", ty));
+    }
+
+    return ItemDoc {
+        name: decl.name.clone(),
+        signature,
+        doc: doc_comment(slice),
+    };
+}
+
+fn class_item(class: &ClassDecl, slice: &StringSlice) -> ClassDoc {
+    let mut signature = format!("class {}", class.name);
+
+    if let Some(generics) = &class.generics {
+        signature.push_str(&format!("[{}]", variable_list(generics)));
+    }
+
+    if let Some(extends) = &class.extends {
+        signature.push_str(&format!(" extends {}", extends));
+    }
+
+    let params = class
+        .params
+        .as_ref()
+        .map(|it| it.values.iter().map(variable_name).collect())
+        .unwrap_or_default();
+
+    return ClassDoc {
+        name: class.name.clone(),
+        signature,
+        doc: doc_comment(slice),
+        params,
+        methods: vec![],
+    };
+}
+
+fn variable_item(decl: &VariableDecl, slice: &StringSlice) -> ItemDoc {
+    let keyword = if decl.is_const { "const" } else { "let" };
+
+    return ItemDoc {
+        name: decl.param.name.clone(),
+        signature: format!("{} {}", keyword, variable_name(&decl.param)),
+        doc: doc_comment(slice),
+    };
+}
+
+fn variable_name(name: &VariableName) -> String {
+    return match &name.ty {
+        Some(ty) => format!("{}: {}", name.name, ty),
+        None => name.name.to_string(),
+    };
+}
+
+fn variable_list(list: &VariableList) -> String {
+    return list
+        .values
+        .iter()
+        .map(variable_name)
+        .collect::<Vec<_>>()
+        .join(", ");
+}
+
+/// Collects the `##` comment lines directly above the start of the slice
+fn doc_comment(slice: &StringSlice) -> Option<String> {
+    let before = slice.src.get(..slice.start)?;
+
+    let (before, indent) = match before.rfind('\n') {
+        Some(idx) => (&before[..idx], &before[idx + 1..]),
+        None => ("", before),
+    };
+
+    // The declaration has to be the first thing on it's line
+    if !indent.trim().is_empty() {
+        return None;
+    }
+
+    let mut lines = vec![];
+    for line in before.lines().rev() {
+        let Some(comment) = line.trim().strip_prefix("##") else {
+            break;
+        };
+
+        lines.push(comment.strip_prefix(' ').unwrap_or(comment));
+    }
+
+    if lines.is_empty() {
+        return None;
+    }
+
+    lines.reverse();
+    return Some(lines.join("\n"));
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{parse_tree::tree::ParseTree, tokenizer::Tokenizer};
+
+    use super::{markdown, ModuleDoc};
+
+    const SRC: &str = r##"from "#fs" import everything as fs
+
+## Filesystem access
+declare module "#fs" is
+    function readToString(file: string): string
+end
+
+## A point in space
+export class Point is
+    x: int,
+    y: int
+end
+
+## Moves the point
+function Point.move(this Point, x: int, y: int)
+    this.x = x
+end
+
+## Says hello
+export function greet(name: string): string or none
+    return name
+end
+
+function hidden()
+    return none
+end
+
+## The file
+let file: string or none = none
+
+export file
+"##;
+
+    #[test]
+    fn markdown_snapshot() {
+        let mut tokenizer = Tokenizer::new(SRC.into());
+        let tree = ParseTree::try_parse(&mut tokenizer).unwrap().unwrap();
+
+        let doc = ModuleDoc::new("example".into(), &tree);
+
+        assert_eq!(
+            markdown::render_module(&doc),
+            r##"# Module `example`
+
+## Functions
+
+### `greet`
+
+```burrow
+function greet(name: string): string or none
+```
+
+Says hello
+
+## Classes
+
+### `Point`
+
+```burrow
+class Point
+```
+
+A point in space
+
+#### Parameters
+
+- `x: int`
+- `y: int`
+
+#### Methods
+
+##### `move`
+
+```burrow
+function Point.move(this Point, x: int, y: int)
+```
+
+Moves the point
+
+## Variables
+
+### `file`
+
+```burrow
+let file: string or none
+```
+
+The file
+
+## Native modules
+
+### Module `#fs`
+
+Filesystem access
+
+#### `readToString`
+
+```burrow
+function readToString(file: string): string
+```
+"##
+        );
+    }
+}
@@ -1,9 +1,11 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use crate::{
+    bytecode::{builder::BytecodeBuilder, op_code::OpCode, BytecodeGenerationError, Function},
     parse_tree::{
         if_next_or_none, if_parse_or_none, is_next, next_else, peek_nth, require_next,
-        require_parse, stmt::Block, try_next, try_parse, ty::Type, ParserError,
+        require_parse, resolver::Binding, stmt::Block, try_next, try_parse, ty::Type,
+        ParserError,
     },
     string::StringSlice,
     tokenizer::{
@@ -35,6 +37,32 @@ pub struct FunctionDecl {
 }
 
 impl FunctionImpl {
+    pub fn generate_bytecode(
+        &self,
+        bindings: &Arc<HashMap<usize, Binding>>,
+    ) -> Result<Function, BytecodeGenerationError> {
+        let params: Arc<[Arc<str>]> = match &self.decl.params {
+            Some(params) => params.values.iter().map(|it| it.name.clone()).collect(),
+            None => Arc::new([]),
+        };
+
+        let mut bytecode = BytecodeBuilder::for_function(&params).with_bindings(bindings.clone());
+
+        self.block.generate_bytecode(&mut bytecode, false)?;
+
+        // Falling off the end of the body returns none
+        bytecode.set_slice(&self.slice);
+        bytecode.push(OpCode::PushConstNone);
+        bytecode.push(OpCode::Return);
+
+        let name: Arc<str> = match &self.decl.base {
+            Some(base) => format!("{}.{}", base, self.decl.name).into(),
+            None => self.decl.name.clone(),
+        };
+
+        return Ok(bytecode.finish(name, params));
+    }
+
     pub fn try_parse(tokenizer: &mut Tokenizer) -> Result<Option<Self>, ParserError> {
         let (decl, export) = if let Some(decl) = FunctionDecl::try_parse_with_export(tokenizer)? {
             (decl, true)
@@ -1,5 +1,5 @@
 use crate::{
-    bytecode::{op_code::OpCode, BytecodeGenerationError},
+    bytecode::{builder::BytecodeBuilder, op_code::OpCode, BytecodeGenerationError},
     parse_tree::{
         expr::Expr, if_next, if_next_or_none, if_parse_fn, peek_nth, require_parse, try_parse,
         try_parse_fn, ParserError,
@@ -96,7 +96,7 @@ impl VariableDecl {
         return Ok(Some(Self {
             slice: start.merge(&end),
             export,
-            is_const: false,
+            is_const: true,
             param,
         }));
     }
@@ -105,26 +105,32 @@ impl VariableDecl {
 impl VariableImpl {
     pub fn generate_bytecode(
         &self,
-        bytecode: &mut Vec<OpCode>,
+        bytecode: &mut BytecodeBuilder,
         allow_export: bool,
     ) -> Result<(), BytecodeGenerationError> {
         if !allow_export && self.decl.export {
             return Err(BytecodeGenerationError::IllegalExport(self.slice.clone()));
         }
 
+        if self.decl.is_const && self.init.is_none() {
+            return Err(BytecodeGenerationError::MissingConstInitializer(
+                self.slice.clone(),
+            ));
+        }
+
         let name = self.decl.param.name.clone();
 
-        bytecode.push(OpCode::InitVariable { name: name.clone() });
+        bytecode.init_variable(&name);
 
         if let Some(init) = &self.init {
             init.generate_bytecode(bytecode)?;
 
-            bytecode.push(OpCode::StoreVariable { name: name.clone() });
+            bytecode.store_variable(&name);
             bytecode.push(OpCode::Pop);
         }
 
         if self.decl.is_const {
-            bytecode.push(OpCode::MarkVariableConst { name: name.clone() });
+            bytecode.mark_variable_const(&name);
         }
 
         if self.decl.export {
@@ -155,3 +161,42 @@ impl VariableImpl {
         }));
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bytecode::{op_code::OpCode, BytecodeGenerationError},
+        parse_tree::{resolver::DiagnosticKind, tree::ParseTree},
+        tokenizer::Tokenizer,
+    };
+
+    fn compile(src: &str) -> Result<Vec<OpCode>, BytecodeGenerationError> {
+        let mut tokenizer = Tokenizer::new(src.into());
+        let tree = ParseTree::try_parse(&mut tokenizer).unwrap().unwrap();
+
+        return tree.compile().map(|it| it.init.body.to_vec());
+    }
+
+    #[test]
+    fn const_declarations() {
+        let body = compile("const limit = 10\nwhile true do\n    const half = limit / 2\n    print(half)\nend\n").unwrap();
+        assert!(body.contains(&OpCode::MarkVariableConst {
+            name: "limit".into()
+        }));
+        assert!(body.contains(&OpCode::MarkLocalConst { slot: 0 }));
+
+        assert!(matches!(
+            compile("const limit = 10\nif true then\n    limit += 1\nend\n"),
+            Err(BytecodeGenerationError::Resolve(diagnostic))
+                if diagnostic.kind == DiagnosticKind::ConstAssignment("limit".into())
+        ));
+
+        // A shadowing declaration isn't an assignment
+        assert!(compile("const limit = 10\nif true then\n    let limit = 1\n    limit = 2\n    print(limit)\nend\n").is_ok());
+
+        assert!(matches!(
+            compile("const limit\n"),
+            Err(BytecodeGenerationError::MissingConstInitializer(_))
+        ));
+    }
+}
@@ -1,8 +1,12 @@
 use std::sync::Arc;
 
 use crate::{
-    bytecode::{op_code::OpCode, BytecodeGenerationError},
-    parse_tree::{if_next, require_next, require_parse, ParserError},
+    bytecode::{
+        builder::{BytecodeBuilder, Label},
+        op_code::OpCode,
+        BytecodeGenerationError,
+    },
+    parse_tree::{if_next, is_next, require_next, require_parse, ParserError},
     string::StringSlice,
     tokenizer::{
         token::{Keyword, Symbol, TokenKind},
@@ -11,6 +15,7 @@ use crate::{
 };
 
 use super::{
+    op::binary::BinOpKind,
     value::literal::{LiteralExpr, LiteralExprKind},
     Expr, ExprKind,
 };
@@ -25,6 +30,8 @@ pub struct AccessExpr {
 #[derive(Debug, PartialEq, Clone)]
 pub struct AccessArm {
     pub slice: StringSlice,
+    /// Short circuits the rest of the chain to none if the value it's applied to is none
+    pub optional: bool,
     pub kind: AccessKind,
 }
 
@@ -34,61 +41,84 @@ pub enum AccessKind {
     Index(Arc<Expr>),
     Invoke(Arc<[Expr]>),
     Assign(Arc<Expr>),
+    /// `+=`, `-=`, `*=`, `/=` and `%=`
+    CompoundAssign(BinOpKind, Arc<Expr>),
     Prototype,
 }
 
 impl AccessExpr {
     pub fn generate_bytecode(
         &self,
-        bytecode: &mut Vec<OpCode>,
+        bytecode: &mut BytecodeBuilder,
     ) -> Result<(), BytecodeGenerationError> {
         if self.access.len() == 1
-            && let AccessKind::Assign(assign) = &self.access[0].kind
+            && let Some((op, assign)) = self.assignment(0)
             && let ExprKind::Literal(LiteralExpr {
-                slice: _,
+                slice: target,
                 kind: LiteralExprKind::Variable(name),
             }) = &self.base.kind
         {
+            if op.is_some() {
+                bytecode.push_reference(name, target)?;
+            }
+
             assign.generate_bytecode(bytecode)?;
 
-            bytecode.push(OpCode::SetSlice {
-                slice: self.slice.clone(),
-            });
-            bytecode.push(OpCode::StoreVariable { name: name.clone() });
+            bytecode.set_slice(&self.slice);
+            if let Some(op) = op.and_then(BinOpKind::op_code) {
+                bytecode.push(op);
+            }
+            bytecode.store_reference(name, target)?;
 
             return Ok(());
         }
 
+        if self.access.iter().any(|it| it.optional)
+            && let Some(arm) = self.access.last()
+            && self.assignment(self.access.len() - 1).is_some()
+        {
+            return Err(BytecodeGenerationError::IllegalAssignment(arm.slice.clone()));
+        }
+
         self.base.generate_bytecode(bytecode)?;
 
+        let exit_label = bytecode.new_label();
         let mut idx = 0;
 
         while idx < self.access.len() {
             let value = &self.access[idx];
-            bytecode.push(OpCode::SetSlice {
-                slice: value.slice.clone(),
-            });
+            bytecode.set_slice(&value.slice);
+
+            if value.optional {
+                bytecode.push(OpCode::Dupe);
+                bytecode.push_jump(OpCode::JumpNone { location: 0 }, exit_label);
+            }
 
             match &value.kind {
-                AccessKind::Assign(_) => {
+                AccessKind::Assign(_) | AccessKind::CompoundAssign(_, _) => {
                     return Err(BytecodeGenerationError::IllegalAssignment(
                         value.slice.clone(),
                     ))
                 }
 
                 AccessKind::Ident(name) => {
-                    if idx < self.access.len() - 1
-                        && let AccessKind::Assign(assignment) = &self.access[idx + 1].kind
-                    {
+                    if let Some((op, assignment)) = self.assignment(idx + 1) {
                         bytecode.push(OpCode::PushConstString {
                             value: name.clone(),
                         });
 
+                        if op.is_some() {
+                            bytecode.push(OpCode::DupeTwo);
+                            bytecode.push(OpCode::PushIndex);
+                        }
+
                         assignment.generate_bytecode(bytecode)?;
 
-                        bytecode.push(OpCode::SetSlice {
-                            slice: value.slice.clone(),
-                        });
+                        bytecode.set_slice(&value.slice);
+
+                        if let Some(op) = op.and_then(BinOpKind::op_code) {
+                            bytecode.push(op);
+                        }
 
                         bytecode.push(OpCode::StoreIndex);
 
@@ -106,12 +136,14 @@ impl AccessExpr {
                         });
                         bytecode.push(OpCode::PushIndex);
 
+                        if self.access[idx + 1].optional {
+                            Self::generate_optional_method(bytecode, exit_label);
+                        }
+
                         for value in invocation.iter() {
                             value.generate_bytecode(bytecode)?;
                         }
-                        bytecode.push(OpCode::SetSlice {
-                            slice: self.access[idx + 1].slice.clone(),
-                        });
+                        bytecode.set_slice(&self.access[idx + 1].slice);
 
                         bytecode.push(OpCode::Invoke {
                             param_count: invocation.len(),
@@ -129,16 +161,22 @@ impl AccessExpr {
                 }
 
                 AccessKind::Index(index) => {
-                    if idx < self.access.len() - 1
-                        && let AccessKind::Assign(assignment) = &self.access[idx + 1].kind
-                    {
+                    if let Some((op, assignment)) = self.assignment(idx + 1) {
                         index.generate_bytecode(bytecode)?;
 
+                        if op.is_some() {
+                            bytecode.set_slice(&value.slice);
+                            bytecode.push(OpCode::DupeTwo);
+                            bytecode.push(OpCode::PushIndex);
+                        }
+
                         assignment.generate_bytecode(bytecode)?;
 
-                        bytecode.push(OpCode::SetSlice {
-                            slice: value.slice.clone(),
-                        });
+                        bytecode.set_slice(&value.slice);
+
+                        if let Some(op) = op.and_then(BinOpKind::op_code) {
+                            bytecode.push(op);
+                        }
 
                         bytecode.push(OpCode::StoreIndex);
 
@@ -153,18 +191,18 @@ impl AccessExpr {
 
                         index.generate_bytecode(bytecode)?;
 
-                        bytecode.push(OpCode::SetSlice {
-                            slice: value.slice.clone(),
-                        });
+                        bytecode.set_slice(&value.slice);
 
                         bytecode.push(OpCode::PushIndex);
 
+                        if self.access[idx + 1].optional {
+                            Self::generate_optional_method(bytecode, exit_label);
+                        }
+
                         for value in invocation.iter() {
                             value.generate_bytecode(bytecode)?;
                         }
-                        bytecode.push(OpCode::SetSlice {
-                            slice: self.access[idx + 1].slice.clone(),
-                        });
+                        bytecode.set_slice(&self.access[idx + 1].slice);
 
                         bytecode.push(OpCode::Invoke {
                             param_count: invocation.len(),
@@ -176,9 +214,7 @@ impl AccessExpr {
                     }
 
                     index.generate_bytecode(bytecode)?;
-                    bytecode.push(OpCode::SetSlice {
-                        slice: value.slice.clone(),
-                    });
+                    bytecode.set_slice(&value.slice);
 
                     bytecode.push(OpCode::PushIndex);
                 }
@@ -187,9 +223,7 @@ impl AccessExpr {
                     for value in invocation.iter() {
                         value.generate_bytecode(bytecode)?;
                     }
-                    bytecode.push(OpCode::SetSlice {
-                        slice: value.slice.clone(),
-                    });
+                    bytecode.set_slice(&value.slice);
 
                     bytecode.push(OpCode::Invoke {
                         param_count: invocation.len(),
@@ -198,13 +232,15 @@ impl AccessExpr {
                 }
 
                 AccessKind::Prototype => {
-                    if idx < self.access.len() - 1
-                        && let AccessKind::Assign(assignment) = &self.access[idx + 1].kind
-                    {
+                    if let Some((op, assignment)) = self.assignment(idx + 1) {
+                        if op.is_some() {
+                            return Err(BytecodeGenerationError::IllegalAssignment(
+                                self.access[idx + 1].slice.clone(),
+                            ));
+                        }
+
                         assignment.generate_bytecode(bytecode)?;
-                        bytecode.push(OpCode::SetSlice {
-                            slice: value.slice.clone(),
-                        });
+                        bytecode.set_slice(&value.slice);
 
                         bytecode.push(OpCode::StoreProtorype);
 
@@ -220,12 +256,14 @@ impl AccessExpr {
                         bytecode.push(OpCode::PushPrototype);
                         bytecode.push(OpCode::PushIndex);
 
+                        if self.access[idx + 1].optional {
+                            Self::generate_optional_method(bytecode, exit_label);
+                        }
+
                         for value in invocation.iter() {
                             value.generate_bytecode(bytecode)?;
                         }
-                        bytecode.push(OpCode::SetSlice {
-                            slice: self.access[idx + 1].slice.clone(),
-                        });
+                        bytecode.set_slice(&self.access[idx + 1].slice);
 
                         bytecode.push(OpCode::Invoke {
                             param_count: invocation.len(),
@@ -244,9 +282,36 @@ impl AccessExpr {
             idx += 1;
         }
 
+        bytecode.bind(exit_label);
+
         return Ok(());
     }
 
+    /// Stack structure: <method> <this>
+    ///
+    /// Replaces both with none and leaves the chain if the method is none
+    fn generate_optional_method(bytecode: &mut BytecodeBuilder, exit_label: Label) {
+        let found_label = bytecode.new_label();
+
+        bytecode.push(OpCode::Dupe);
+        bytecode.push_jump(OpCode::JumpNotNone { location: 0 }, found_label);
+        bytecode.push(OpCode::Pop);
+        bytecode.push(OpCode::Pop);
+        bytecode.push(OpCode::PushConstNone);
+        bytecode.push_jump(OpCode::Jump { location: 0 }, exit_label);
+
+        bytecode.bind(found_label);
+    }
+
+    /// The assignment at that arm, along with the operator if it's a compound assignment
+    pub fn assignment(&self, idx: usize) -> Option<(Option<BinOpKind>, &Arc<Expr>)> {
+        return match &self.access.get(idx)?.kind {
+            AccessKind::Assign(value) => Some((None, value)),
+            AccessKind::CompoundAssign(op, value) => Some((Some(*op), value)),
+            _ => None,
+        };
+    }
+
     pub fn try_parse(tokenizer: &mut Tokenizer, base: &Expr) -> Result<Option<Self>, ParserError> {
         let mut access = vec![];
         let mut end = base.slice.clone();
@@ -254,79 +319,82 @@ impl AccessExpr {
             let start = tokenizer.peek(0)?.slice;
 
             if_next!(TokenKind::Symbol(Symbol::Dot), tokenizer, {
-                end = tokenizer.peek(0)?.slice;
-                if_next!(TokenKind::Keyword(Keyword::Prototype), tokenizer, {
-                    access.push(AccessArm {
-                        slice: start.merge(&end),
-                        kind: AccessKind::Prototype,
-                    });
-
-                    continue;
-                });
-
-                require_next!(TokenKind::Identifier(ident), tokenizer);
-
-                access.push(AccessArm {
-                    slice: start.merge(&end),
-                    kind: AccessKind::Ident(ident),
-                });
+                let arm = AccessArm::parse_member(tokenizer, start, false)?;
+                end = arm.slice.clone();
+                access.push(arm);
+                continue;
+            });
 
+            if_next!(TokenKind::Symbol(Symbol::OptionalDot), tokenizer, {
+                let arm = if is_next!(TokenKind::Symbol(Symbol::ParenOpen), tokenizer) {
+                    AccessArm::parse_invoke(tokenizer, start, true)?
+                } else if is_next!(TokenKind::Symbol(Symbol::BracketOpen), tokenizer) {
+                    AccessArm::parse_index(tokenizer, start, true)?
+                } else {
+                    AccessArm::parse_member(tokenizer, start, true)?
+                };
+                end = arm.slice.clone();
+                access.push(arm);
                 continue;
             });
 
-            if_next!(TokenKind::Symbol(Symbol::BracketOpen), tokenizer, {
-                require_parse!(expr, Expr, tokenizer);
+            if let TokenKind::Symbol(Symbol::Question) = tokenizer.peek(0)?.kind
+                && let TokenKind::Symbol(Symbol::BracketOpen) = tokenizer.peek(1)?.kind
+            {
+                tokenizer.next()?;
+                tokenizer.next()?;
 
-                end = tokenizer.peek(0)?.slice;
-                require_next!(TokenKind::Symbol(Symbol::BracketClose), tokenizer);
+                let arm = AccessArm::parse_index(tokenizer, start, true)?;
+                end = arm.slice.clone();
+                access.push(arm);
+                continue;
+            }
 
-                access.push(AccessArm {
-                    slice: start.merge(&end),
-                    kind: AccessKind::Index(Arc::new(expr)),
-                });
+            if_next!(TokenKind::Symbol(Symbol::BracketOpen), tokenizer, {
+                let arm = AccessArm::parse_index(tokenizer, start, false)?;
+                end = arm.slice.clone();
+                access.push(arm);
                 continue;
             });
 
             if_next!(TokenKind::Symbol(Symbol::ParenOpen), tokenizer, {
-                let mut values = vec![];
-
-                end = loop {
-                    let end = tokenizer.peek(0)?.slice;
-                    if_next!(TokenKind::Symbol(Symbol::ParenClose), tokenizer, {
-                        break end;
-                    });
-
-                    require_parse!(value, Expr, tokenizer);
-                    values.push(value);
+                let arm = AccessArm::parse_invoke(tokenizer, start, false)?;
+                end = arm.slice.clone();
+                access.push(arm);
+                continue;
+            });
 
-                    let end = tokenizer.peek(0)?.slice;
-                    if_next!(TokenKind::Symbol(Symbol::ParenClose), tokenizer, {
-                        break end;
-                    });
+            if_next!(TokenKind::Symbol(Symbol::Assign), tokenizer, {
+                require_parse!(expr, Expr, tokenizer);
 
-                    require_next!(TokenKind::Symbol(Symbol::Comma), tokenizer);
-                };
+                end = expr.slice.clone();
 
                 access.push(AccessArm {
                     slice: start.merge(&end),
-                    kind: AccessKind::Invoke(values.into_boxed_slice().into()),
+                    optional: false,
+                    kind: AccessKind::Assign(Arc::new(expr)),
                 });
 
-                continue;
+                break;
             });
 
-            if_next!(TokenKind::Symbol(Symbol::Assign), tokenizer, {
+            if let TokenKind::Symbol(symbol) = tokenizer.peek(0)?.kind
+                && let Some(op) = BinOpKind::from_compound_assign(symbol)
+            {
+                tokenizer.next()?;
+
                 require_parse!(expr, Expr, tokenizer);
 
                 end = expr.slice.clone();
 
                 access.push(AccessArm {
                     slice: start.merge(&end),
-                    kind: AccessKind::Assign(Arc::new(expr)),
+                    optional: false,
+                    kind: AccessKind::CompoundAssign(op, Arc::new(expr)),
                 });
 
                 break;
-            });
+            }
 
             break;
         }
@@ -342,3 +410,198 @@ impl AccessExpr {
         }));
     }
 }
+
+impl AccessArm {
+    /// Parses what comes after a `.` or `?.`
+    fn parse_member(
+        tokenizer: &mut Tokenizer,
+        start: StringSlice,
+        optional: bool,
+    ) -> Result<Self, ParserError> {
+        let end = tokenizer.peek(0)?.slice;
+        if_next!(TokenKind::Keyword(Keyword::Prototype), tokenizer, {
+            return Ok(Self {
+                slice: start.merge(&end),
+                optional,
+                kind: AccessKind::Prototype,
+            });
+        });
+
+        require_next!(TokenKind::Identifier(ident), tokenizer);
+
+        return Ok(Self {
+            slice: start.merge(&end),
+            optional,
+            kind: AccessKind::Ident(ident),
+        });
+    }
+
+    /// Parses what comes after a `[`, `?[` or `?.[`
+    fn parse_index(
+        tokenizer: &mut Tokenizer,
+        start: StringSlice,
+        optional: bool,
+    ) -> Result<Self, ParserError> {
+        require_parse!(expr, Expr, tokenizer);
+
+        let end = tokenizer.peek(0)?.slice;
+        require_next!(TokenKind::Symbol(Symbol::BracketClose), tokenizer);
+
+        return Ok(Self {
+            slice: start.merge(&end),
+            optional,
+            kind: AccessKind::Index(Arc::new(expr)),
+        });
+    }
+
+    /// Parses what comes after a `(` or `?.(`
+    fn parse_invoke(
+        tokenizer: &mut Tokenizer,
+        start: StringSlice,
+        optional: bool,
+    ) -> Result<Self, ParserError> {
+        let mut values = vec![];
+
+        let end = loop {
+            let end = tokenizer.peek(0)?.slice;
+            if_next!(TokenKind::Symbol(Symbol::ParenClose), tokenizer, {
+                break end;
+            });
+
+            require_parse!(value, Expr, tokenizer);
+            values.push(value);
+
+            let end = tokenizer.peek(0)?.slice;
+            if_next!(TokenKind::Symbol(Symbol::ParenClose), tokenizer, {
+                break end;
+            });
+
+            require_next!(TokenKind::Symbol(Symbol::Comma), tokenizer);
+        };
+
+        return Ok(Self {
+            slice: start.merge(&end),
+            optional,
+            kind: AccessKind::Invoke(values.into_boxed_slice().into()),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bytecode::{op_code::OpCode, verifier::verify, CompiledModule},
+        parse_tree::tree::ParseTree,
+        tokenizer::Tokenizer,
+    };
+
+    fn compile(src: &str) -> CompiledModule {
+        let mut tokenizer = Tokenizer::new(src.into());
+        let tree = ParseTree::try_parse(&mut tokenizer).unwrap().unwrap();
+        let module = tree.compile().unwrap();
+        assert_eq!(verify(&module), Ok(()));
+
+        return module;
+    }
+
+    fn contains(body: &[OpCode], ops: &[OpCode]) -> bool {
+        return body.windows(ops.len()).any(|it| it == ops);
+    }
+
+    #[test]
+    fn compound_assignment() {
+        let module = compile("let x = 1\nx += 1\n");
+        assert!(module.init.body.ends_with(&[
+            OpCode::PushVariable { name: "x".into() },
+            OpCode::PushConstInt { value: 1 },
+            OpCode::OpAdd,
+            OpCode::StoreVariablePop { name: "x".into() },
+        ]));
+
+        // The object and the key are duplicated instead of evaluated twice
+        let module = compile("let a = { b = 1 }\na.b *= 2\n");
+        assert!(contains(
+            &module.init.body,
+            &[
+                OpCode::PushVariable { name: "a".into() },
+                OpCode::PushConstString { value: "b".into() },
+                OpCode::DupeTwo,
+                OpCode::PushIndex,
+                OpCode::PushConstInt { value: 2 },
+                OpCode::OpMul,
+                OpCode::StoreIndex,
+            ]
+        ));
+
+        let module = compile("function f() return 0 end\nlet a = [1]\na[f()] %= 3\n");
+        let body = &module.init.body;
+        assert_eq!(
+            body.iter()
+                .filter(|it| matches!(it, OpCode::Invoke { .. }))
+                .count(),
+            1
+        );
+        assert!(contains(
+            body,
+            &[
+                OpCode::PushVariable { name: "a".into() },
+                OpCode::PushVariable { name: "f".into() },
+                OpCode::Invoke {
+                    param_count: 0,
+                    this_call: false
+                },
+                OpCode::DupeTwo,
+                OpCode::PushIndex,
+                OpCode::PushConstInt { value: 3 },
+                OpCode::OpRem,
+                OpCode::StoreIndex,
+            ]
+        ));
+    }
+
+    #[test]
+    fn optional_chaining() {
+        let module = compile(
+            "let a = none
+let i = 0
+let b = a?.b
+let c = a?.[i]
+let d = a?[i]
+let e = a?.m()
+let f = a?.b.c
+",
+        );
+        let body = &module.init.body;
+
+        // Each chain jumps past all of its accesses to the store, leaving the none it checked as the value
+        for name in ["b", "c", "d", "e", "f"] {
+            let end = body
+                .iter()
+                .position(|it| *it == OpCode::StoreVariablePop { name: name.into() })
+                .unwrap();
+            let start = body[..end]
+                .iter()
+                .rposition(|it| *it == OpCode::PushVariable { name: "a".into() })
+                .unwrap();
+
+            assert_eq!(body[start + 1], OpCode::Dupe);
+            assert_eq!(body[start + 2], OpCode::JumpNone { location: end });
+            assert!(body[start + 3..end]
+                .iter()
+                .all(|it| it.location().is_none()));
+        }
+
+        assert!(contains(
+            body,
+            &[
+                OpCode::Dupe,
+                OpCode::PushConstString { value: "m".into() },
+                OpCode::PushIndex,
+                OpCode::Invoke {
+                    param_count: 0,
+                    this_call: true
+                },
+            ]
+        ));
+    }
+}
@@ -5,10 +5,11 @@ use op::{
     binary::{BinOpExpr, BinOpKind},
     unary::{UnaryOpExpr, UnaryOpKind},
 };
+use range::RangeExpr;
 use value::{array::ArrayExpr, literal::LiteralExpr, object::ObjectExpr};
 
 use crate::{
-    bytecode::{op_code::OpCode, BytecodeGenerationError},
+    bytecode::{builder::BytecodeBuilder, BytecodeGenerationError},
     parse_tree::{if_next, if_parse, require_next, require_parse},
     string::StringSlice,
     tokenizer::{
@@ -21,6 +22,7 @@ use super::ParserError;
 
 pub mod access;
 pub mod op;
+pub mod range;
 pub mod value;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -37,16 +39,15 @@ pub enum ExprKind {
     BinOp(BinOpExpr),
     UnaryOp(UnaryOpExpr),
     Access(AccessExpr),
+    Range(RangeExpr),
 }
 
 impl Expr {
     pub fn generate_bytecode(
         &self,
-        bytecode: &mut Vec<OpCode>,
+        bytecode: &mut BytecodeBuilder,
     ) -> Result<(), BytecodeGenerationError> {
-        bytecode.push(OpCode::SetSlice {
-            slice: self.slice.clone(),
-        });
+        bytecode.set_slice(&self.slice);
         return match &self.kind {
             ExprKind::Literal(lit) => lit.generate_bytecode(bytecode),
             ExprKind::Object(obj) => obj.generate_bytecode(bytecode),
@@ -54,11 +55,23 @@ impl Expr {
             ExprKind::BinOp(op) => op.generate_bytecode(bytecode),
             ExprKind::UnaryOp(op) => op.generate_bytecode(bytecode),
             ExprKind::Access(access) => access.generate_bytecode(bytecode),
+            ExprKind::Range(range) => range.generate_bytecode(bytecode),
         };
     }
 
     pub fn try_parse(tokenizer: &mut Tokenizer) -> Result<Option<Self>, ParserError> {
-        return Self::try_parse_binop(tokenizer, 0);
+        let Some(expr) = Self::try_parse_binop(tokenizer, 0)? else {
+            return Ok(None);
+        };
+
+        if let Some(range) = RangeExpr::try_parse(tokenizer, &expr)? {
+            return Ok(Some(Self {
+                slice: range.slice.clone(),
+                kind: ExprKind::Range(range),
+            }));
+        }
+
+        return Ok(Some(expr));
     }
 
     pub fn try_parse_binop(
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use crate::{
-    bytecode::{op_code::OpCode, BytecodeGenerationError},
+    bytecode::{builder::BytecodeBuilder, op_code::OpCode, BytecodeGenerationError},
     parse_tree::{expr::Expr, ParserError},
     string::StringSlice,
     tokenizer::{
@@ -41,42 +41,52 @@ pub enum BinOpKind {
     And,
 
     Or,
+
+    /// `??`, which evaluates to the right side only if the left side is none
+    NoneCoalesce,
 }
 
 impl BinOpExpr {
     pub fn generate_bytecode(
         &self,
-        bytecode: &mut Vec<OpCode>,
+        bytecode: &mut BytecodeBuilder,
     ) -> Result<(), BytecodeGenerationError> {
         self.lhs.generate_bytecode(bytecode)?;
+
+        let Some(op) = self.op.op_code() else {
+            return self.generate_short_circuit(bytecode);
+        };
+
         self.rhs.generate_bytecode(bytecode)?;
 
-        bytecode.push(OpCode::SetSlice {
-            slice: self.slice.clone(),
-        });
+        bytecode.set_slice(&self.slice);
 
-        match self.op {
-            BinOpKind::Add => bytecode.push(OpCode::OpAdd),
-            BinOpKind::Sub => bytecode.push(OpCode::OpSub),
+        bytecode.push(op);
 
-            BinOpKind::Mul => bytecode.push(OpCode::OpAdd),
-            BinOpKind::Div => bytecode.push(OpCode::OpAdd),
-            BinOpKind::Rem => bytecode.push(OpCode::OpAdd),
+        return Ok(());
+    }
 
-            BinOpKind::Greater => bytecode.push(OpCode::OpGt),
-            BinOpKind::Less => bytecode.push(OpCode::OpLt),
-            BinOpKind::GreaterEqual => bytecode.push(OpCode::OpGe),
-            BinOpKind::LessEqual => bytecode.push(OpCode::OpLe),
+    /// The left side is already on the stack, and is kept as the result if it decides the value
+    fn generate_short_circuit(
+        &self,
+        bytecode: &mut BytecodeBuilder,
+    ) -> Result<(), BytecodeGenerationError> {
+        bytecode.set_slice(&self.slice);
 
-            BinOpKind::Equal => bytecode.push(OpCode::OpEq),
-            BinOpKind::NotEqual => bytecode.push(OpCode::OpNe),
+        bytecode.push(OpCode::Dupe);
 
-            BinOpKind::Is => bytecode.push(OpCode::ProtoEq),
-            BinOpKind::IsNot => bytecode.push(OpCode::ProtoNe),
+        let exit_label = bytecode.new_label();
+        let jump = match self.op {
+            BinOpKind::And => OpCode::JumpFalse { location: 0 },
+            BinOpKind::Or => OpCode::JumpTrue { location: 0 },
+            _ => OpCode::JumpNotNone { location: 0 },
+        };
+        bytecode.push_jump(jump, exit_label);
 
-            BinOpKind::And => bytecode.push(OpCode::OpAnd),
-            BinOpKind::Or => bytecode.push(OpCode::OpOr),
-        }
+        bytecode.push(OpCode::Pop);
+        self.rhs.generate_bytecode(bytecode)?;
+
+        bytecode.bind(exit_label);
 
         return Ok(());
     }
@@ -113,12 +123,54 @@ impl BinOpKind {
             TokenKind::Keyword(Keyword::And) => Self::And,
             TokenKind::Keyword(Keyword::Or) => Self::Or,
 
+            TokenKind::Symbol(Symbol::NoneCoalesce) => Self::NoneCoalesce,
+
             _ => return Ok(None),
         }));
     }
 
+    /// The operator a compound assignment symbol applies, such as `Add` for `+=`
+    pub fn from_compound_assign(symbol: Symbol) -> Option<Self> {
+        return Some(match symbol {
+            Symbol::AddAssign => Self::Add,
+            Symbol::SubAssign => Self::Sub,
+            Symbol::MulAssign => Self::Mul,
+            Symbol::DivAssign => Self::Div,
+            Symbol::RemAssign => Self::Rem,
+
+            _ => return None,
+        });
+    }
+
+    /// The instruction for the operator, or none if the operator short circuits
+    pub fn op_code(self) -> Option<OpCode> {
+        return Some(match self {
+            Self::Add => OpCode::OpAdd,
+            Self::Sub => OpCode::OpSub,
+
+            Self::Mul => OpCode::OpMul,
+            Self::Div => OpCode::OpDiv,
+            Self::Rem => OpCode::OpRem,
+
+            Self::Greater => OpCode::OpGt,
+            Self::Less => OpCode::OpLt,
+            Self::GreaterEqual => OpCode::OpGe,
+            Self::LessEqual => OpCode::OpLe,
+
+            Self::Equal => OpCode::OpEq,
+            Self::NotEqual => OpCode::OpNe,
+
+            Self::Is => OpCode::ProtoEq,
+            Self::IsNot => OpCode::ProtoNe,
+
+            Self::And | Self::Or | Self::NoneCoalesce => return None,
+        });
+    }
+
     pub fn binding(self) -> (usize, usize) {
         return match self {
+            Self::NoneCoalesce => (1, 2),
+
             Self::Or => (3, 4),
 
             Self::And => (5, 6),
@@ -142,3 +194,110 @@ impl BinOpKind {
         };
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bytecode::{op_code::OpCode, verifier::verify},
+        parse_tree::tree::ParseTree,
+        tokenizer::Tokenizer,
+    };
+
+    fn init(src: &str) -> Vec<OpCode> {
+        let mut tokenizer = Tokenizer::new(src.into());
+        let tree = ParseTree::try_parse(&mut tokenizer).unwrap().unwrap();
+        let module = tree.compile().unwrap();
+        assert_eq!(verify(&module), Ok(()));
+
+        return module.init.body.to_vec();
+    }
+
+    #[test]
+    fn arithmetic_op_codes() {
+        let ops = init("let a = 6\nlet b = 4\nlet c = [a + b, a - b, a * b, a / b, a % b]\n")
+            .into_iter()
+            .filter(|it| {
+                matches!(
+                    it,
+                    OpCode::OpAdd | OpCode::OpSub | OpCode::OpMul | OpCode::OpDiv | OpCode::OpRem
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            ops,
+            [
+                OpCode::OpAdd,
+                OpCode::OpSub,
+                OpCode::OpMul,
+                OpCode::OpDiv,
+                OpCode::OpRem
+            ]
+        );
+    }
+
+    /// The instructions computing the value of `let result = <expr>`, and the location they start at
+    fn value_of(body: &[OpCode]) -> (usize, &[OpCode]) {
+        let start = body
+            .iter()
+            .position(|it| {
+                *it == OpCode::InitVariable {
+                    name: "result".into(),
+                }
+            })
+            .unwrap()
+            + 1;
+        let end = body
+            .iter()
+            .position(|it| {
+                *it == OpCode::StoreVariablePop {
+                    name: "result".into(),
+                }
+            })
+            .unwrap();
+
+        return (start, &body[start..end]);
+    }
+
+    #[test]
+    fn short_circuits() {
+        // The right side is skipped when the left side decides the value, leaving the left side as the result
+        for op in ["and", "or"] {
+            let body = init(&format!("let a = 1\nlet b = 2\nlet result = a {} b\n", op));
+            let (start, value) = value_of(&body);
+
+            let location = start + value.len();
+            let jump = match op {
+                "and" => OpCode::JumpFalse { location },
+                _ => OpCode::JumpTrue { location },
+            };
+            assert_eq!(
+                value,
+                [
+                    OpCode::PushVariable { name: "a".into() },
+                    OpCode::Dupe,
+                    jump,
+                    OpCode::Pop,
+                    OpCode::PushVariable { name: "b".into() },
+                ]
+            );
+        }
+
+        // Only none falls through to the right side, so `false` and `0` are kept
+        for left in ["none", "false", "0"] {
+            let body = init(&format!("let result = {} ?? 1\n", left));
+            let (start, value) = value_of(&body);
+
+            assert!(matches!(
+                value,
+                [
+                    _,
+                    OpCode::Dupe,
+                    OpCode::JumpNotNone { location },
+                    OpCode::Pop,
+                    OpCode::PushConstInt { value: 1 },
+                ] if *location == start + value.len()
+            ));
+        }
+    }
+}
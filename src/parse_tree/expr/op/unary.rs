@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use crate::{
-    bytecode::{op_code::OpCode, BytecodeGenerationError},
+    bytecode::{builder::BytecodeBuilder, op_code::OpCode, BytecodeGenerationError},
     parse_tree::{expr::Expr, ParserError},
     string::StringSlice,
     tokenizer::{
@@ -27,13 +27,11 @@ pub enum UnaryOpKind {
 impl UnaryOpExpr {
     pub fn generate_bytecode(
         &self,
-        bytecode: &mut Vec<OpCode>,
+        bytecode: &mut BytecodeBuilder,
     ) -> Result<(), BytecodeGenerationError> {
         self.value.generate_bytecode(bytecode)?;
 
-        bytecode.push(OpCode::SetSlice {
-            slice: self.slice.clone(),
-        });
+        bytecode.set_slice(&self.slice);
 
         match self.op {
             UnaryOpKind::Add => bytecode.push(OpCode::OpUnaryAdd),
@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use crate::{
+    bytecode::{builder::BytecodeBuilder, op_code::OpCode, BytecodeGenerationError},
+    parse_tree::{
+        expr::{
+            op::unary::{UnaryOpExpr, UnaryOpKind},
+            value::literal::{LiteralExpr, LiteralExprKind},
+            Expr, ExprKind,
+        },
+        require_parse_fn, ParserError,
+    },
+    string::StringSlice,
+    tokenizer::{
+        token::{Number, Symbol, TokenKind},
+        Tokenizer,
+    },
+};
+
+/// `start to end step step`, where the end is included.
+/// `to` and `step` are only keywords here, so they can still be used as names
+#[derive(Debug, PartialEq, Clone)]
+pub struct RangeExpr {
+    pub slice: StringSlice,
+    pub start: Arc<Expr>,
+    pub end: Arc<Expr>,
+    pub step: Option<Arc<Expr>>,
+}
+
+impl RangeExpr {
+    pub fn generate_bytecode(
+        &self,
+        bytecode: &mut BytecodeBuilder,
+    ) -> Result<(), BytecodeGenerationError> {
+        self.start.generate_bytecode(bytecode)?;
+        self.end.generate_bytecode(bytecode)?;
+
+        self.generate_step(bytecode)?;
+
+        bytecode.set_slice(&self.slice);
+        bytecode.push(OpCode::PushRange);
+
+        return Ok(());
+    }
+
+    /// Pushes the step, 1 if it's left out. A literal step of 0 would never reach the end, so it's an error
+    pub fn generate_step(
+        &self,
+        bytecode: &mut BytecodeBuilder,
+    ) -> Result<(), BytecodeGenerationError> {
+        let Some(step) = &self.step else {
+            bytecode.push(OpCode::PushConstInt { value: 1 });
+            return Ok(());
+        };
+
+        if is_zero(step) {
+            return Err(BytecodeGenerationError::ZeroStep(step.slice.clone()));
+        }
+
+        return step.generate_bytecode(bytecode);
+    }
+
+    pub fn try_parse(tokenizer: &mut Tokenizer, start: &Expr) -> Result<Option<Self>, ParserError> {
+        if !Self::is_next(tokenizer, &start.slice, "to")? {
+            return Ok(None);
+        }
+
+        require_parse_fn!(end, Self::try_parse_bound, tokenizer);
+
+        let step = match Self::is_next(tokenizer, &end.slice, "step")? {
+            true => {
+                require_parse_fn!(step, Self::try_parse_bound, tokenizer);
+                Some(Arc::new(step))
+            }
+            false => None,
+        };
+
+        let slice = match &step {
+            Some(step) => start.slice.merge(&step.slice),
+            None => start.slice.merge(&end.slice),
+        };
+
+        return Ok(Some(Self {
+            slice,
+            start: Arc::new(start.clone()),
+            end: Arc::new(end),
+            step,
+        }));
+    }
+
+    fn try_parse_bound(tokenizer: &mut Tokenizer) -> Result<Option<Expr>, ParserError> {
+        return Expr::try_parse_binop(tokenizer, 0);
+    }
+
+    /// Consumes the name if it's next on the same line as what it follows, unless it's being assigned to or
+    /// accessed. Otherwise it's the start of the next statement, like `to(3)` or `to = 1`
+    fn is_next(
+        tokenizer: &mut Tokenizer,
+        after: &StringSlice,
+        name: &str,
+    ) -> Result<bool, ParserError> {
+        let peek = tokenizer.peek(0)?;
+        let TokenKind::Identifier(ident) = peek.kind else {
+            return Ok(false);
+        };
+
+        let between = after.src.get(after.end..peek.slice.start).unwrap_or("");
+        if ident.as_ref() != name
+            || between.contains('\n')
+            || matches!(
+                tokenizer.peek(1)?.kind,
+                TokenKind::Symbol(
+                    Symbol::Assign
+                        | Symbol::AddAssign
+                        | Symbol::SubAssign
+                        | Symbol::MulAssign
+                        | Symbol::DivAssign
+                        | Symbol::RemAssign
+                        | Symbol::Dot
+                        | Symbol::OptionalDot
+                )
+            )
+        {
+            return Ok(false);
+        }
+
+        tokenizer.next()?;
+
+        return Ok(true);
+    }
+}
+
+/// A literal 0, or -0
+fn is_zero(expr: &Expr) -> bool {
+    return match &expr.kind {
+        ExprKind::Literal(LiteralExpr {
+            kind: LiteralExprKind::Number(number),
+            ..
+        }) => match number {
+            Number::Integer(value) => *value == 0,
+            Number::Floating(value) => *value == 0.0,
+        },
+        ExprKind::UnaryOp(UnaryOpExpr {
+            op: UnaryOpKind::Add | UnaryOpKind::Sub,
+            value,
+            ..
+        }) => is_zero(value),
+        _ => false,
+    };
+}
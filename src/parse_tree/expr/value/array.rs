@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use crate::{
-    bytecode::{op_code::OpCode, BytecodeGenerationError},
+    bytecode::{builder::BytecodeBuilder, op_code::OpCode, BytecodeGenerationError},
     parse_tree::{expr::Expr, if_next, require_next, require_parse, try_next, ParserError},
     string::StringSlice,
     tokenizer::{
@@ -19,17 +19,16 @@ pub struct ArrayExpr {
 impl ArrayExpr {
     pub fn generate_bytecode(
         &self,
-        bytecode: &mut Vec<OpCode>,
+        bytecode: &mut BytecodeBuilder,
     ) -> Result<(), BytecodeGenerationError> {
-        bytecode.push(OpCode::SetSlice {
-            slice: self.slice.clone(),
-        });
+        bytecode.set_slice(&self.slice);
 
         let len = self.values.len();
         bytecode.push(OpCode::PushNewArray { initial_size: len });
 
         for index in 0..len {
             let value = &self.values[index];
+            bytecode.push(OpCode::Dupe);
             bytecode.push(OpCode::PushConstInt {
                 value: index as isize,
             });
@@ -2,7 +2,7 @@ use core::f32;
 use std::sync::Arc;
 
 use crate::{
-    bytecode::{op_code::OpCode, BytecodeGenerationError},
+    bytecode::{builder::BytecodeBuilder, op_code::OpCode, BytecodeGenerationError},
     parse_tree::{if_next, ParserError},
     string::StringSlice,
     tokenizer::{
@@ -32,11 +32,9 @@ pub enum LiteralExprKind {
 impl LiteralExpr {
     pub fn generate_bytecode(
         &self,
-        bytecode: &mut Vec<OpCode>,
+        bytecode: &mut BytecodeBuilder,
     ) -> Result<(), BytecodeGenerationError> {
-        bytecode.push(OpCode::SetSlice {
-            slice: self.slice.clone(),
-        });
+        bytecode.set_slice(&self.slice);
 
         match self.kind.clone() {
             LiteralExprKind::Number(Number::Integer(value)) => {
@@ -47,7 +45,7 @@ impl LiteralExpr {
             }
             LiteralExprKind::String(value) => bytecode.push(OpCode::PushConstString { value }),
             LiteralExprKind::Bool(value) => bytecode.push(OpCode::PushConstBool { value }),
-            LiteralExprKind::Variable(name) => bytecode.push(OpCode::PushVariable { name }),
+            LiteralExprKind::Variable(name) => bytecode.push_reference(&name, &self.slice)?,
             LiteralExprKind::This => bytecode.push(OpCode::PushThis),
             LiteralExprKind::Infinity => bytecode.push(OpCode::PushConstFloat {
                 value: f32::INFINITY,
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use crate::{
-    bytecode::{op_code::OpCode, BytecodeGenerationError},
+    bytecode::{builder::BytecodeBuilder, op_code::OpCode, BytecodeGenerationError},
     parse_tree::{
         allow_accidental, expr::Expr, if_next, require_next, require_parse, try_next, ParserError,
     },
@@ -28,23 +28,20 @@ pub struct ObjectValue {
 impl ObjectExpr {
     pub fn generate_bytecode(
         &self,
-        bytecode: &mut Vec<OpCode>,
+        bytecode: &mut BytecodeBuilder,
     ) -> Result<(), BytecodeGenerationError> {
-        bytecode.push(OpCode::SetSlice {
-            slice: self.slice.clone(),
-        });
+        bytecode.set_slice(&self.slice);
 
         bytecode.push(OpCode::PushNewObject);
 
         for value in self.values.iter() {
+            bytecode.push(OpCode::Dupe);
             bytecode.push(OpCode::PushConstString {
                 value: value.name.clone(),
             });
             value.value.generate_bytecode(bytecode)?;
 
-            bytecode.push(OpCode::SetSlice {
-                slice: self.slice.clone(),
-            });
+            bytecode.set_slice(&self.slice);
             bytecode.push(OpCode::StoreIndex);
             bytecode.push(OpCode::Pop);
         }
@@ -1,7 +1,11 @@
-use crate::tokenizer::{token::Token, TokenizeError};
+use crate::{
+    string::StringSlice,
+    tokenizer::{token::Token, TokenizeError},
+};
 
 pub mod decl;
 pub mod expr;
+pub mod resolver;
 pub mod stmt;
 pub mod tree;
 pub mod ty;
@@ -130,6 +134,8 @@ pub enum ParserError {
         token: Token,
         throwing_location: String,
     },
+    /// A catch clause after a catch without a type, which would never be reached
+    UnreachableCatch(StringSlice),
 }
 
 impl ParserError {
@@ -0,0 +1,508 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+    sync::Arc,
+};
+
+use crate::string::StringSlice;
+
+use super::{
+    decl::{
+        function::FunctionImpl,
+        import::{FromImportKind, ImportKind},
+    },
+    expr::{access::AccessKind, value::literal::LiteralExprKind, Expr, ExprKind},
+    stmt::{
+        control::{ControlKind, ControlStmt},
+        Block, Stmt, StmtKind,
+    },
+    tree::ParseTree,
+};
+
+/// Where a variable reference points
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    /// A local `depth` contexts up from the reference
+    Local { depth: usize, slot: usize },
+    /// Looked up by name when running, like top level variables and natives
+    Global,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub slice: StringSlice,
+    pub kind: DiagnosticKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// A variable used in a block before the block declares it
+    UseBeforeDeclare(Arc<str>),
+    ConstAssignment(Arc<str>),
+    /// A `let` or `const` declaring a name that's already declared in the same block
+    DuplicateDeclaration(Arc<str>),
+    /// A local `let` or `const` that's never read. Names starting with `_` are ignored
+    UnusedVariable(Arc<str>),
+}
+
+impl DiagnosticKind {
+    pub fn is_error(&self) -> bool {
+        return !matches!(self, Self::UnusedVariable(_));
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (line, column) = self.slice.line_column();
+        let level = if self.kind.is_error() {
+            "error"
+        } else {
+            "warning"
+        };
+
+        let message = match &self.kind {
+            DiagnosticKind::UseBeforeDeclare(name) => {
+                format!("`{}` is used before it's declared", name)
+            }
+            DiagnosticKind::ConstAssignment(name) => {
+                format!("`{}` is const and can't be assigned to", name)
+            }
+            DiagnosticKind::DuplicateDeclaration(name) => {
+                format!("`{}` is already declared in this block", name)
+            }
+            DiagnosticKind::UnusedVariable(name) => format!("`{}` is never used", name),
+        };
+
+        return write!(f, "{}:{}: {}: {}", line, column, level, message);
+    }
+}
+
+/// What the resolver found in a module
+#[derive(Debug, Clone, Default)]
+pub struct Resolution {
+    /// The binding of each variable reference, by the start of it's slice
+    pub bindings: HashMap<usize, Binding>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolution {
+    pub fn first_error(&self) -> Option<&Diagnostic> {
+        return self.diagnostics.iter().find(|it| it.kind.is_error());
+    }
+}
+
+#[derive(Debug)]
+struct Variable {
+    name: Arc<str>,
+    slice: StringSlice,
+    slot: Option<usize>,
+    is_const: bool,
+    /// Declared with `let`, `const`, or at the top level, rather than by a loop or catch
+    explicit: bool,
+    used: bool,
+}
+
+#[derive(Debug)]
+struct Scope {
+    variables: Vec<Variable>,
+    /// Names declared somewhere in the scope, which can't be used until they are
+    upcoming: HashSet<Arc<str>>,
+    /// Whether the scope is a context with slots, the top level is looked up by name instead
+    is_context: bool,
+}
+
+/// Resolves variables to the slots the code generator gives them, so it has to
+/// declare them in the same order, including the hidden variables of loops
+#[derive(Debug, Default)]
+struct Resolver {
+    scopes: Vec<Scope>,
+    resolution: Resolution,
+}
+
+impl ParseTree {
+    /// Builds the lexical scopes of the module, binding every variable reference and reporting misuses
+    pub fn resolve(&self) -> Resolution {
+        let mut resolver = Resolver::default();
+
+        resolver.scopes.push(Scope {
+            variables: vec![],
+            upcoming: Resolver::declared_in(&self.stmts),
+            is_context: false,
+        });
+
+        for import in self.imports.iter() {
+            let ImportKind::From(from) = &import.kind else {
+                continue;
+            };
+
+            for value in from.values.iter() {
+                let name = match &value.kind {
+                    FromImportKind::Everything { name } => name,
+                    FromImportKind::Single { name, rename } => rename.as_ref().unwrap_or(name),
+                };
+                resolver.declare(name, &value.slice, true, true);
+            }
+        }
+
+        for class in self.classes.iter() {
+            resolver.declare(&class.name, &class.slice, true, true);
+        }
+
+        for function in self.functions.iter() {
+            if function.decl.base.is_none() {
+                resolver.declare(&function.decl.name, &function.slice, true, true);
+            }
+        }
+
+        for stmt in self.stmts.iter() {
+            resolver.stmt(stmt);
+        }
+
+        // Functions run after the top level is declared, so they can use all of it
+        resolver.scopes[0].upcoming.clear();
+        for function in self.functions.iter() {
+            resolver.function(function);
+        }
+
+        return resolver.resolution;
+    }
+}
+
+impl Resolver {
+    fn function(&mut self, function: &FunctionImpl) {
+        self.scopes.push(Scope {
+            variables: vec![],
+            upcoming: HashSet::new(),
+            is_context: true,
+        });
+
+        if let Some(params) = &function.decl.params {
+            for param in params.values.iter() {
+                self.declare(&param.name, &param.slice, false, false);
+            }
+        }
+
+        self.block(&function.block);
+        self.scopes.pop();
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.scopes.push(Scope {
+            variables: vec![],
+            upcoming: Self::declared_in(&block.stmts),
+            is_context: true,
+        });
+
+        for stmt in block.stmts.iter() {
+            self.stmt(stmt);
+        }
+
+        let scope = self.scopes.pop().unwrap();
+        for variable in scope.variables {
+            if variable.explicit && !variable.used && !variable.name.starts_with('_') {
+                self.resolution.diagnostics.push(Diagnostic {
+                    slice: variable.slice,
+                    kind: DiagnosticKind::UnusedVariable(variable.name),
+                });
+            }
+        }
+    }
+
+    /// The names the statements declare in their own scope
+    fn declared_in(stmts: &[Stmt]) -> HashSet<Arc<str>> {
+        let mut names = HashSet::new();
+
+        for stmt in stmts.iter() {
+            match &stmt.kind {
+                StmtKind::Variable(variable) => {
+                    names.insert(variable.decl.param.name.clone());
+                }
+                StmtKind::Control(ControlStmt {
+                    slice: _,
+                    kind: ControlKind::For(for_stmt),
+                }) => names.extend(for_stmt.locals()),
+                _ => {}
+            }
+        }
+
+        return names;
+    }
+
+    fn declare(&mut self, name: &Arc<str>, slice: &StringSlice, is_const: bool, explicit: bool) {
+        let scope = self.scopes.last_mut().unwrap();
+
+        if explicit
+            && scope
+                .variables
+                .iter()
+                .any(|it| it.explicit && it.name == *name)
+        {
+            self.resolution.diagnostics.push(Diagnostic {
+                slice: slice.clone(),
+                kind: DiagnosticKind::DuplicateDeclaration(name.clone()),
+            });
+        }
+
+        let slot = scope.is_context.then_some(scope.variables.len());
+        scope.variables.push(Variable {
+            name: name.clone(),
+            slice: slice.clone(),
+            slot,
+            is_const,
+            explicit,
+            used: false,
+        });
+    }
+
+    /// Binds a reference to the innermost variable with the name
+    fn reference(&mut self, name: &Arc<str>, slice: &StringSlice, read: bool, write: bool) {
+        let mut depth = 0;
+        let mut binding = Binding::Global;
+        let mut diagnostic = None;
+
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(variable) = scope.variables.iter_mut().rev().find(|it| it.name == *name) {
+                variable.used |= read;
+                if write && variable.is_const {
+                    diagnostic = Some(DiagnosticKind::ConstAssignment(name.clone()));
+                }
+                if let Some(slot) = variable.slot {
+                    binding = Binding::Local { depth, slot };
+                }
+                break;
+            }
+
+            if scope.upcoming.contains(name) {
+                diagnostic = Some(DiagnosticKind::UseBeforeDeclare(name.clone()));
+                break;
+            }
+
+            if scope.is_context {
+                depth += 1;
+            }
+        }
+
+        if let Some(kind) = diagnostic {
+            self.resolution.diagnostics.push(Diagnostic {
+                slice: slice.clone(),
+                kind,
+            });
+        }
+        self.resolution.bindings.insert(slice.start, binding);
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Expr(expr) => self.expr(expr),
+            StmtKind::Variable(variable) => {
+                if let Some(init) = &variable.init {
+                    self.expr(init);
+                }
+
+                let decl = &variable.decl;
+                self.declare(&decl.param.name, &decl.param.slice, decl.is_const, true);
+            }
+            StmtKind::Control(control) => self.control(control),
+        }
+    }
+
+    fn control(&mut self, control: &ControlStmt) {
+        match &control.kind {
+            ControlKind::While(while_stmt) => {
+                self.expr(&while_stmt.arm.condition);
+                self.block(&while_stmt.arm.block);
+            }
+            ControlKind::If(if_stmt) => {
+                for arm in if_stmt.arms.iter() {
+                    self.expr(&arm.condition);
+                    self.block(&arm.block);
+                }
+                if let Some(block) = &if_stmt.else_arm {
+                    self.block(block);
+                }
+            }
+            ControlKind::For(for_stmt) => {
+                self.expr(&for_stmt.expr);
+                for name in for_stmt.locals() {
+                    self.declare(&name, &for_stmt.slice, false, false);
+                }
+                self.block(&for_stmt.block);
+            }
+            ControlKind::Try(try_stmt) => {
+                self.block(&try_stmt.try_block);
+                for clause in try_stmt.catches.iter() {
+                    if let Some(ty) = &clause.ty {
+                        self.expr(ty);
+                    }
+                    self.scopes.push(Scope {
+                        variables: vec![],
+                        upcoming: HashSet::new(),
+                        is_context: true,
+                    });
+                    self.declare(&clause.name, &clause.slice, true, false);
+                    self.block(&clause.block);
+                    self.scopes.pop();
+                }
+                if let Some(block) = &try_stmt.finally_block {
+                    self.block(block);
+                }
+            }
+            ControlKind::Throw(expr) | ControlKind::Return(Some(expr)) => self.expr(expr),
+            ControlKind::Export(name) => self.reference(name, &control.slice, true, false),
+            ControlKind::Return(None) | ControlKind::Continue(_) | ControlKind::Break(_) => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Literal(literal) => {
+                if let LiteralExprKind::Variable(name) = &literal.kind {
+                    self.reference(name, &literal.slice, true, false);
+                }
+            }
+            ExprKind::Object(object) => {
+                for value in object.values.iter() {
+                    self.expr(&value.value);
+                }
+            }
+            ExprKind::Array(array) => {
+                for value in array.values.iter() {
+                    self.expr(value);
+                }
+            }
+            ExprKind::BinOp(bin_op) => {
+                self.expr(&bin_op.lhs);
+                self.expr(&bin_op.rhs);
+            }
+            ExprKind::UnaryOp(unary_op) => self.expr(&unary_op.value),
+            ExprKind::Range(range) => {
+                self.expr(&range.start);
+                self.expr(&range.end);
+                if let Some(step) = &range.step {
+                    self.expr(step);
+                }
+            }
+            ExprKind::Access(access) => {
+                // Assigning to a variable directly, rather than to one of it's properties
+                if access.access.len() == 1
+                    && let Some((op, value)) = access.assignment(0)
+                    && let ExprKind::Literal(literal) = &access.base.kind
+                    && let LiteralExprKind::Variable(name) = &literal.kind
+                {
+                    self.expr(value);
+                    self.reference(name, &literal.slice, op.is_some(), true);
+                    return;
+                }
+
+                self.expr(&access.base);
+                for arm in access.access.iter() {
+                    match &arm.kind {
+                        AccessKind::Index(value)
+                        | AccessKind::Assign(value)
+                        | AccessKind::CompoundAssign(_, value) => self.expr(value),
+                        AccessKind::Invoke(params) => {
+                            for param in params.iter() {
+                                self.expr(param);
+                            }
+                        }
+                        AccessKind::Ident(_) | AccessKind::Prototype => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bytecode::BytecodeGenerationError, parse_tree::tree::ParseTree, tokenizer::Tokenizer,
+    };
+
+    use super::{Binding, DiagnosticKind};
+
+    fn diagnostics(src: &str) -> Vec<DiagnosticKind> {
+        let mut tokenizer = Tokenizer::new(src.into());
+        let tree = ParseTree::try_parse(&mut tokenizer).unwrap().unwrap();
+
+        return tree
+            .resolve()
+            .diagnostics
+            .into_iter()
+            .map(|it| it.kind)
+            .collect();
+    }
+
+    #[test]
+    fn diagnostics_and_bindings() {
+        assert_eq!(
+            diagnostics(
+                "from \"#fs\" import everything as fs
+
+function f(a)
+    print(b)
+    let b = a
+    let _ignored = 1
+    let unused = 2
+    let b = b
+    return b
+end
+
+fs = none
+"
+            ),
+            [
+                DiagnosticKind::ConstAssignment("fs".into()),
+                DiagnosticKind::UseBeforeDeclare("b".into()),
+                DiagnosticKind::DuplicateDeclaration("b".into()),
+                DiagnosticKind::UnusedVariable("unused".into()),
+            ]
+        );
+
+        // Catches and loops can declare the same name again
+        assert_eq!(
+            diagnostics(
+                "while true do
+    for each i in 1 to 3 do print(i) end
+    for each i in 1 to 3 do print(i) end
+    try throw 1 catch e print(e) end
+    try throw 2 catch e print(e) end
+end
+"
+            ),
+            []
+        );
+
+        let src = "function f(a)
+    let total = a
+    if total then
+        total = a
+    end
+end
+
+let x = 1
+let y = x
+";
+        let mut tokenizer = Tokenizer::new(src.into());
+        let tree = ParseTree::try_parse(&mut tokenizer).unwrap().unwrap();
+        let resolution = tree.resolve();
+
+        // The binding of the reference at an offset into the first match of the pattern
+        let binding = |pattern: &str, offset: usize| {
+            return resolution.bindings[&(src.find(pattern).unwrap() + offset)];
+        };
+
+        assert_eq!(binding("= a\n    if", 2), Binding::Local { depth: 1, slot: 0 });
+        assert_eq!(binding("if total", 3), Binding::Local { depth: 0, slot: 0 });
+        assert_eq!(binding("total = a\n    end", 0), Binding::Local { depth: 1, slot: 0 });
+        assert_eq!(binding("total = a\n    end", 8), Binding::Local { depth: 2, slot: 0 });
+        assert_eq!(binding("y = x", 4), Binding::Global);
+
+        let mut tokenizer = Tokenizer::new("let x = 1\nlet x = 2\n".into());
+        let tree = ParseTree::try_parse(&mut tokenizer).unwrap().unwrap();
+        assert!(matches!(
+            tree.compile(),
+            Err(BytecodeGenerationError::Resolve(_))
+        ));
+    }
+}
@@ -1,14 +1,19 @@
 use std::sync::Arc;
 
 use crate::{
-    bytecode::{op_code::OpCode, BytecodeGenerationError},
+    bytecode::{
+        builder::{BytecodeBuilder, Label},
+        op_code::OpCode,
+        BytecodeGenerationError,
+    },
     parse_tree::{
-        expr::Expr, if_next, if_parse, is_next, peek_nth, require_next, require_parse,
+        expr::{range::RangeExpr, Expr, ExprKind},
+        if_next, if_next_or_none, if_parse, is_next, peek_nth, require_next, require_parse,
         require_parse_fn, try_next, try_parse, ParserError,
     },
     string::StringSlice,
     tokenizer::{
-        token::{Keyword, TokenKind},
+        token::{Keyword, Symbol, TokenKind},
         Tokenizer,
     },
 };
@@ -30,21 +35,34 @@ pub enum ControlKind {
     Throw(Expr),
     Return(Option<Expr>),
     Export(Arc<str>),
-    Continue,
-    Break,
+    /// `continue` or `continue label`
+    Continue(Option<Arc<str>>),
+    /// `break` or `break label`
+    Break(Option<Arc<str>>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct TryStmt {
     pub slice: StringSlice,
     pub try_block: Block,
-    pub catch_name: Arc<str>,
-    pub catch_block: Block,
+    pub catches: Arc<[CatchClause]>,
+    pub finally_block: Option<Block>,
+}
+
+/// `catch name` or `catch name is Type`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatchClause {
+    pub slice: StringSlice,
+    pub name: Arc<str>,
+    pub ty: Option<Expr>,
+    pub block: Block,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct WhileStmt {
     pub slice: StringSlice,
+    /// The name in `name: while`, for `break name` and `continue name`
+    pub label: Option<Arc<str>>,
     pub until: bool,
     pub arm: ConditionArm,
 }
@@ -59,6 +77,10 @@ pub struct IfStmt {
 #[derive(Debug, Clone, PartialEq)]
 pub struct ForStmt {
     pub slice: StringSlice,
+    /// The name in `name: for each`, for `break name` and `continue name`
+    pub label: Option<Arc<str>>,
+    /// The first name in `for each key, value in`
+    pub key: Option<Arc<str>>,
     pub name: Arc<str>,
     pub expr: Expr,
     pub block: Block,
@@ -74,25 +96,26 @@ pub struct ConditionArm {
 impl ControlStmt {
     pub fn generate_bytecode(
         &self,
-        bytecode: &mut Vec<OpCode>,
+        bytecode: &mut BytecodeBuilder,
         allow_export: bool,
-        allow_break_continue: bool,
     ) -> Result<(), BytecodeGenerationError> {
-        bytecode.push(OpCode::SetSlice {
-            slice: self.slice.clone(),
-        });
+        bytecode.set_slice(&self.slice);
         match &self.kind {
-            ControlKind::Break => {
-                if !allow_break_continue {
-                    return Err(BytecodeGenerationError::IllegalBreak(self.slice.clone()));
+            ControlKind::Break(label) => {
+                if !bytecode.push_break(label.as_deref()) {
+                    return Err(match label {
+                        Some(_) => BytecodeGenerationError::UnknownLabel(self.slice.clone()),
+                        None => BytecodeGenerationError::IllegalBreak(self.slice.clone()),
+                    });
                 }
-                bytecode.push(OpCode::TempBreak);
             }
-            ControlKind::Continue => {
-                if !allow_break_continue {
-                    return Err(BytecodeGenerationError::IllegalContinue(self.slice.clone()));
+            ControlKind::Continue(label) => {
+                if !bytecode.push_continue(label.as_deref()) {
+                    return Err(match label {
+                        Some(_) => BytecodeGenerationError::UnknownLabel(self.slice.clone()),
+                        None => BytecodeGenerationError::IllegalContinue(self.slice.clone()),
+                    });
                 }
-                bytecode.push(OpCode::TempContinue);
             }
             ControlKind::Export(name) => {
                 if !allow_export {
@@ -103,9 +126,7 @@ impl ControlStmt {
             ControlKind::Return(value) => {
                 if let Some(value) = value {
                     value.generate_bytecode(bytecode)?;
-                    bytecode.push(OpCode::SetSlice {
-                        slice: self.slice.clone(),
-                    });
+                    bytecode.set_slice(&self.slice);
                 } else {
                     bytecode.push(OpCode::PushConstNone);
                 }
@@ -113,9 +134,7 @@ impl ControlStmt {
             }
             ControlKind::Throw(value) => {
                 value.generate_bytecode(bytecode)?;
-                bytecode.push(OpCode::SetSlice {
-                    slice: self.slice.clone(),
-                });
+                bytecode.set_slice(&self.slice);
                 bytecode.push(OpCode::Throw);
             }
 
@@ -132,6 +151,29 @@ impl ControlStmt {
     }
 
     pub fn try_parse(tokenizer: &mut Tokenizer) -> Result<Option<Self>, ParserError> {
+        if let Some((start, label)) = Self::try_parse_label(tokenizer)? {
+            if let Some(mut stmt) = WhileStmt::try_parse(tokenizer)? {
+                stmt.slice = start.merge(&stmt.slice);
+                stmt.label = Some(label);
+
+                return Ok(Some(Self {
+                    slice: stmt.slice.clone(),
+                    kind: ControlKind::While(stmt),
+                }));
+            }
+
+            let Some(mut stmt) = ForStmt::try_parse(tokenizer)? else {
+                return Err(ParserError::unexpected_token(tokenizer.peek(0)?));
+            };
+            stmt.slice = start.merge(&stmt.slice);
+            stmt.label = Some(label);
+
+            return Ok(Some(Self {
+                slice: stmt.slice.clone(),
+                kind: ControlKind::For(stmt),
+            }));
+        }
+
         if_parse!(stmt, WhileStmt, tokenizer, {
             return Ok(Some(Self {
                 slice: stmt.slice.clone(),
@@ -184,16 +226,18 @@ impl ControlStmt {
         });
 
         if_next!(TokenKind::Keyword(Keyword::Continue), tokenizer, {
+            let (slice, label) = Self::parse_target(tokenizer, start)?;
             return Ok(Some(Self {
-                slice: start,
-                kind: ControlKind::Continue,
+                slice,
+                kind: ControlKind::Continue(label),
             }));
         });
 
         if_next!(TokenKind::Keyword(Keyword::Break), tokenizer, {
+            let (slice, label) = Self::parse_target(tokenizer, start)?;
             return Ok(Some(Self {
-                slice: start,
-                kind: ControlKind::Break,
+                slice,
+                kind: ControlKind::Break(label),
             }));
         });
 
@@ -210,44 +254,136 @@ impl ControlStmt {
     }
 }
 
+impl ControlStmt {
+    /// `name:` in front of a loop
+    fn try_parse_label(
+        tokenizer: &mut Tokenizer,
+    ) -> Result<Option<(StringSlice, Arc<str>)>, ParserError> {
+        peek_nth!(TokenKind::Identifier(name), 0, tokenizer);
+        peek_nth!(TokenKind::Symbol(Symbol::Colon), 1, tokenizer);
+        peek_nth!(
+            TokenKind::Keyword(Keyword::While | Keyword::Until | Keyword::For),
+            2,
+            tokenizer
+        );
+
+        let start = tokenizer.next()?.slice;
+        tokenizer.next()?;
+
+        return Ok(Some((start, name)));
+    }
+
+    /// The label after `break` or `continue`, which has to be on the same line
+    fn parse_target(
+        tokenizer: &mut Tokenizer,
+        start: StringSlice,
+    ) -> Result<(StringSlice, Option<Arc<str>>), ParserError> {
+        let peek = tokenizer.peek(0)?;
+        let TokenKind::Identifier(label) = peek.kind else {
+            return Ok((start, None));
+        };
+
+        let between = start.src.get(start.end..peek.slice.start).unwrap_or("");
+        if between.contains('\n') {
+            return Ok((start, None));
+        }
+
+        tokenizer.next()?;
+
+        return Ok((start.merge(&peek.slice), Some(label)));
+    }
+}
+
 impl TryStmt {
     pub fn generate_bytecode(
         &self,
-        bytecode: &mut Vec<OpCode>,
+        bytecode: &mut BytecodeBuilder,
     ) -> Result<(), BytecodeGenerationError> {
-        let catch_update_index = bytecode.len();
+        let has_catch = !self.catches.is_empty();
 
-        bytecode.push(OpCode::PushCatch { location: 0 });
+        let finally_label = bytecode.new_label();
+        let catch_label = bytecode.new_label();
+        let exit_label = bytecode.new_label();
 
-        self.try_block.generate_bytecode(bytecode, false, false)?;
+        if self.finally_block.is_some() {
+            bytecode.push_jump(OpCode::PushFinally { location: 0 }, finally_label);
+            bytecode.enter_handler();
+        }
 
-        bytecode.push(OpCode::PopCatch);
+        if has_catch {
+            bytecode.push_jump(OpCode::PushCatch { location: 0 }, catch_label);
+            bytecode.enter_handler();
+        }
 
-        let jump_update_index = bytecode.len();
+        self.try_block.generate_bytecode(bytecode, false)?;
 
-        bytecode.push(OpCode::Jump { location: 0 });
+        if has_catch {
+            bytecode.push(OpCode::PopCatch);
+            bytecode.exit_handler();
+            bytecode.push_jump(OpCode::Jump { location: 0 }, exit_label);
 
-        bytecode[catch_update_index] = OpCode::PushCatch {
-            location: bytecode.len(),
-        };
+            bytecode.bind(catch_label);
+            self.generate_catches(bytecode, exit_label)?;
+        }
 
-        bytecode.push(OpCode::InitVariable {
-            name: self.catch_name.clone(),
-        });
-        bytecode.push(OpCode::PushException);
-        bytecode.push(OpCode::StoreVariable {
-            name: self.catch_name.clone(),
-        });
-        bytecode.push(OpCode::Pop);
-        bytecode.push(OpCode::MarkVariableConst {
-            name: self.catch_name.clone(),
-        });
+        bytecode.bind(exit_label);
 
-        self.catch_block.generate_bytecode(bytecode, false, false)?;
+        if let Some(finally_block) = &self.finally_block {
+            bytecode.push(OpCode::PopFinally);
+            bytecode.exit_handler();
 
-        bytecode[jump_update_index] = OpCode::Jump {
-            location: bytecode.len(),
-        };
+            bytecode.bind(finally_label);
+            finally_block.generate_bytecode(bytecode, false)?;
+
+            bytecode.push(OpCode::EndFinally);
+        }
+
+        return Ok(());
+    }
+
+    /// Tries each clause in order, throwing the exception again if none of them match
+    fn generate_catches(
+        &self,
+        bytecode: &mut BytecodeBuilder,
+        exit_label: Label,
+    ) -> Result<(), BytecodeGenerationError> {
+        for clause in self.catches.iter() {
+            bytecode.set_slice(&clause.slice);
+
+            let next_label = bytecode.new_label();
+
+            if let Some(ty) = &clause.ty {
+                bytecode.push(OpCode::PushException);
+                ty.generate_bytecode(bytecode)?;
+                bytecode.set_slice(&clause.slice);
+                bytecode.push(OpCode::ProtoEq);
+                bytecode.push_jump(OpCode::JumpFalse { location: 0 }, next_label);
+            }
+
+            // The exception gets a context of it's own, so it's name ends with the clause
+            bytecode.push_context();
+            bytecode.init_variable(&clause.name);
+            bytecode.push(OpCode::PushException);
+            bytecode.store_variable(&clause.name);
+            bytecode.push(OpCode::Pop);
+            bytecode.mark_variable_const(&clause.name);
+
+            clause.block.generate_bytecode(bytecode, false)?;
+            bytecode.pop_context();
+
+            bytecode.push_jump(OpCode::Jump { location: 0 }, exit_label);
+
+            if clause.ty.is_none() {
+                // The parser makes sure this is the last clause
+                return Ok(());
+            }
+
+            bytecode.bind(next_label);
+        }
+
+        bytecode.set_slice(&self.slice);
+        bytecode.push(OpCode::PushException);
+        bytecode.push(OpCode::Throw);
 
         return Ok(());
     }
@@ -258,65 +394,83 @@ impl TryStmt {
 
         require_parse!(try_block, Block, tokenizer);
 
-        require_next!(TokenKind::Keyword(Keyword::Catch), tokenizer);
+        let mut catches: Vec<CatchClause> = vec![];
+        while let Some(clause) = CatchClause::try_parse(tokenizer)? {
+            // A catch without a type has to be last
+            if catches.last().is_some_and(|it| it.ty.is_none()) {
+                return Err(ParserError::UnreachableCatch(clause.slice));
+            }
 
-        require_next!(TokenKind::Identifier(catch_name), tokenizer);
+            catches.push(clause);
+        }
 
-        require_parse!(catch_block, Block, tokenizer);
+        let finally_block = if_next_or_none!(TokenKind::Keyword(Keyword::Finally), tokenizer, {
+            require_parse!(block, Block, tokenizer);
+            Some(block)
+        });
 
         let end = tokenizer.peek(0)?.slice;
+        if catches.is_empty() && finally_block.is_none() {
+            return Err(ParserError::unexpected_token(tokenizer.peek(0)?));
+        }
         require_next!(TokenKind::Keyword(Keyword::End), tokenizer);
 
         return Ok(Some(Self {
             slice: start.merge(&end),
             try_block,
-            catch_name,
-            catch_block,
+            catches: catches.into_boxed_slice().into(),
+            finally_block,
         }));
     }
 }
 
-impl WhileStmt {
-    pub fn generate_bytecode(
-        &self,
-        bytecode: &mut Vec<OpCode>,
-    ) -> Result<(), BytecodeGenerationError> {
-        let condition_index = bytecode.len();
-        self.arm.condition.generate_bytecode(bytecode)?;
+impl CatchClause {
+    pub fn try_parse(tokenizer: &mut Tokenizer) -> Result<Option<Self>, ParserError> {
+        let start = tokenizer.peek(0)?.slice;
+        try_next!(TokenKind::Keyword(Keyword::Catch), tokenizer);
 
-        let jump_update_index = bytecode.len();
-        bytecode.push(OpCode::JumpTrue { location: 0 });
+        require_next!(TokenKind::Identifier(name), tokenizer);
+
+        let ty = if_next_or_none!(TokenKind::Keyword(Keyword::Is), tokenizer, {
+            require_parse_fn!(ty, Expr::try_parse_access, tokenizer);
+            Some(ty)
+        });
 
-        self.arm.block.generate_bytecode(bytecode, false, true)?;
+        require_parse!(block, Block, tokenizer);
 
-        let exit_index = bytecode.len();
+        return Ok(Some(Self {
+            slice: start.merge(&block.slice),
+            name,
+            ty,
+            block,
+        }));
+    }
+}
 
-        for i in (jump_update_index + 1)..exit_index {
-            if let OpCode::TempBreak = bytecode[i] {
-                bytecode[i] = OpCode::Jump {
-                    location: exit_index,
-                };
-                continue;
-            }
+impl WhileStmt {
+    pub fn generate_bytecode(
+        &self,
+        bytecode: &mut BytecodeBuilder,
+    ) -> Result<(), BytecodeGenerationError> {
+        let condition_label = bytecode.new_label();
+        let exit_label = bytecode.new_label();
 
-            if let OpCode::TempContinue = bytecode[i] {
-                bytecode[i] = OpCode::Jump {
-                    location: condition_index,
-                };
-                continue;
-            }
-        }
+        bytecode.bind(condition_label);
+        self.arm.condition.generate_bytecode(bytecode)?;
 
         if self.until {
-            bytecode[jump_update_index] = OpCode::JumpTrue {
-                location: exit_index,
-            };
+            bytecode.push_jump(OpCode::JumpTrue { location: 0 }, exit_label);
         } else {
-            bytecode[jump_update_index] = OpCode::JumpFalse {
-                location: exit_index,
-            };
+            bytecode.push_jump(OpCode::JumpFalse { location: 0 }, exit_label);
         }
 
+        bytecode.push_loop(self.label.clone(), exit_label, condition_label);
+        self.arm.block.generate_bytecode(bytecode, false)?;
+        bytecode.pop_loop();
+
+        bytecode.push_jump(OpCode::Jump { location: 0 }, condition_label);
+        bytecode.bind(exit_label);
+
         return Ok(());
     }
 
@@ -328,6 +482,7 @@ impl WhileStmt {
 
             return Ok(Some(Self {
                 slice: start.merge(&arm.slice),
+                label: None,
                 until: false,
                 arm,
             }));
@@ -338,6 +493,7 @@ impl WhileStmt {
 
             return Ok(Some(Self {
                 slice: start.merge(&arm.slice),
+                label: None,
                 until: true,
                 arm,
             }));
@@ -350,50 +506,27 @@ impl WhileStmt {
 impl IfStmt {
     pub fn generate_bytecode(
         &self,
-        bytecode: &mut Vec<OpCode>,
+        bytecode: &mut BytecodeBuilder,
     ) -> Result<(), BytecodeGenerationError> {
-        let mut jump_update_indices: Vec<usize> = vec![];
+        let exit_label = bytecode.new_label();
 
         for arm in self.arms.iter() {
-            arm.condition.generate_bytecode(bytecode)?;
-            jump_update_indices.push(bytecode.len());
-            bytecode.push(OpCode::JumpTrue { location: 0 });
-        }
-
-        let else_index = bytecode.len();
-        bytecode.push(OpCode::Jump { location: 0 });
+            let next_label = bytecode.new_label();
 
-        let mut block_indices: Vec<usize> = vec![];
+            arm.condition.generate_bytecode(bytecode)?;
+            bytecode.push_jump(OpCode::JumpFalse { location: 0 }, next_label);
 
-        let mut exit_indices: Vec<usize> = vec![];
+            arm.block.generate_bytecode(bytecode, false)?;
+            bytecode.push_jump(OpCode::Jump { location: 0 }, exit_label);
 
-        for arm in self.arms.iter() {
-            block_indices.push(bytecode.len());
-            arm.block.generate_bytecode(bytecode, false, false)?;
-            exit_indices.push(bytecode.len());
-            bytecode.push(OpCode::Jump { location: 0 });
+            bytecode.bind(next_label);
         }
 
-        for i in 0..self.arms.len() {
-            bytecode[jump_update_indices[i]] = OpCode::JumpTrue {
-                location: block_indices[i],
-            };
-        }
-
-        bytecode[else_index] = OpCode::Jump {
-            location: bytecode.len(),
-        };
-
         if let Some(else_arm) = &self.else_arm {
-            else_arm.generate_bytecode(bytecode, false, false)?;
+            else_arm.generate_bytecode(bytecode, false)?;
         }
 
-        let exit_index = bytecode.len();
-        for i in exit_indices {
-            bytecode[i] = OpCode::Jump {
-                location: exit_index,
-            };
-        }
+        bytecode.bind(exit_label);
 
         return Ok(());
     }
@@ -435,94 +568,300 @@ impl IfStmt {
 }
 
 impl ForStmt {
+    /// Every variable the loop declares in the enclosing context, in order,
+    /// including the hidden ones it keeps it's state in
+    pub fn locals(&self) -> Vec<Arc<str>> {
+        if self.is_range() {
+            return vec![
+                self.hidden_name("value"),
+                self.hidden_name("end"),
+                self.hidden_name("step"),
+                self.name.clone(),
+            ];
+        }
+
+        let mut locals = ["value", "index", "keys", "iter", "next"]
+            .map(|it| self.hidden_name(it))
+            .to_vec();
+        locals.push(self.name.clone());
+        locals.extend(self.key.clone());
+
+        return locals;
+    }
+
+    fn hidden_name(&self, name: &str) -> Arc<str> {
+        return format!("__each_{}_{}__", self.name, name).into();
+    }
+
+    /// A range directly in the loop, without a key
+    fn is_range(&self) -> bool {
+        return self.key.is_none() && matches!(self.expr.kind, ExprKind::Range(_));
+    }
+
+    /// Iterates using the first of these that applies to the value:
+    ///
+    /// - An `__iter__` method, returning an object with a `next` method. `next` returns an object with `done`, `value`, and optionally `key`
+    /// - A `length` property, and indexing from 0 until the length. The key is the index
+    /// - The keys of the object itself. Without a key name, the key is used as the value
+    ///
+    /// A range directly in the loop is turned into a counting loop instead
     pub fn generate_bytecode(
         &self,
-        bytecode: &mut Vec<OpCode>,
+        bytecode: &mut BytecodeBuilder,
     ) -> Result<(), BytecodeGenerationError> {
-        let value_name: Arc<str> = format!("__each_{}_value__", self.name).into();
-        let index_name: Arc<str> = format!("__each_{}_index__", self.name).into();
+        if self.key.is_none()
+            && let ExprKind::Range(range) = &self.expr.kind
+        {
+            return self.generate_range_bytecode(range, bytecode);
+        }
 
-        bytecode.push(OpCode::InitVariable {
-            name: value_name.clone(),
-        });
+        let value_name = self.hidden_name("value");
+        let index_name = self.hidden_name("index");
+        let keys_name = self.hidden_name("keys");
+        let iter_name = self.hidden_name("iter");
+        let next_name = self.hidden_name("next");
+
+        // The value is evaluated before the loop's variables exist, so it can't see them
         self.expr.generate_bytecode(bytecode)?;
-        bytecode.push(OpCode::StoreVariable {
-            name: value_name.clone(),
+        for name in self.locals() {
+            bytecode.init_variable(&name);
+        }
+        Self::store(bytecode, &value_name);
+
+        bytecode.push(OpCode::PushConstInt { value: -1 });
+        Self::store(bytecode, &index_name);
+        bytecode.push(OpCode::PushConstNone);
+        Self::store(bytecode, &keys_name);
+        bytecode.push(OpCode::PushConstNone);
+        Self::store(bytecode, &iter_name);
+
+        // Setup, checking for the protocol first, then the length
+        bytecode.push_variable(&value_name);
+        Self::push_property(bytecode, "__iter__");
+        let no_iter_label = bytecode.new_label();
+        bytecode.push_jump(OpCode::JumpNone { location: 0 }, no_iter_label);
+
+        bytecode.push_variable(&value_name);
+        bytecode.push(OpCode::Dupe);
+        Self::push_property(bytecode, "__iter__");
+        bytecode.push(OpCode::Invoke {
+            param_count: 0,
+            this_call: true,
         });
-        bytecode.push(OpCode::Pop);
+        Self::store(bytecode, &iter_name);
 
-        bytecode.push(OpCode::InitVariable {
-            name: index_name.clone(),
-        });
-        bytecode.push(OpCode::PushConstInt { value: 0 });
-        bytecode.push(OpCode::StoreVariable {
-            name: index_name.clone(),
-        });
-        bytecode.push(OpCode::Pop);
+        let advance_label = bytecode.new_label();
+        let body_label = bytecode.new_label();
+        let exit_label = bytecode.new_label();
 
-        bytecode.push(OpCode::Jump {
-            location: bytecode.len() + 6,
-        });
+        bytecode.push_jump(OpCode::Jump { location: 0 }, advance_label);
 
-        let increment_pos = bytecode.len();
+        bytecode.bind(no_iter_label);
+        bytecode.push_variable(&value_name);
+        Self::push_property(bytecode, "length");
+        bytecode.push_jump(OpCode::JumpNotNone { location: 0 }, advance_label);
 
-        bytecode.push(OpCode::PushVariable {
-            name: index_name.clone(),
+        bytecode.push_variable(&value_name);
+        bytecode.push(OpCode::PushKeys);
+        Self::store(bytecode, &keys_name);
+
+        bytecode.bind(advance_label);
+
+        // Iterator protocol
+        let indexed_label = bytecode.new_label();
+        bytecode.push_variable(&iter_name);
+        bytecode.push_jump(OpCode::JumpNone { location: 0 }, indexed_label);
+
+        bytecode.push_variable(&iter_name);
+        bytecode.push(OpCode::Dupe);
+        Self::push_property(bytecode, "next");
+        bytecode.push(OpCode::Invoke {
+            param_count: 0,
+            this_call: true,
         });
+        Self::store(bytecode, &next_name);
+
+        bytecode.push_variable(&next_name);
+        Self::push_property(bytecode, "done");
+        bytecode.push_jump(OpCode::JumpTrue { location: 0 }, exit_label);
+
+        if let Some(key) = &self.key {
+            bytecode.push_variable(&next_name);
+            Self::push_property(bytecode, "key");
+            Self::store(bytecode, key);
+        }
+        bytecode.push_variable(&next_name);
+        Self::push_property(bytecode, "value");
+        Self::store(bytecode, &self.name);
+
+        bytecode.push_jump(OpCode::Jump { location: 0 }, body_label);
+
+        // Indexing, either the value itself or it's keys
+        let keyed_label = bytecode.new_label();
+        bytecode.bind(indexed_label);
+        bytecode.push_variable(&index_name);
         bytecode.push(OpCode::PushConstInt { value: 1 });
         bytecode.push(OpCode::OpAdd);
-        bytecode.push(OpCode::StoreVariable {
-            name: index_name.clone(),
-        });
-        bytecode.push(OpCode::Pop);
+        Self::store(bytecode, &index_name);
 
+        bytecode.push_variable(&keys_name);
+        bytecode.push_jump(OpCode::JumpNotNone { location: 0 }, keyed_label);
+
+        Self::check_index(bytecode, &index_name, &value_name, exit_label);
+        if let Some(key) = &self.key {
+            bytecode.push_variable(&index_name);
+            Self::store(bytecode, key);
+        }
+        bytecode.push_variable(&value_name);
+        bytecode.push_variable(&index_name);
+        bytecode.push(OpCode::PushIndex);
+        Self::store(bytecode, &self.name);
+
+        bytecode.push_jump(OpCode::Jump { location: 0 }, body_label);
+
+        bytecode.bind(keyed_label);
+        Self::check_index(bytecode, &index_name, &keys_name, exit_label);
+        bytecode.push_variable(&keys_name);
+        bytecode.push_variable(&index_name);
+        bytecode.push(OpCode::PushIndex);
+
+        if let Some(key) = &self.key {
+            Self::store(bytecode, key);
+
+            bytecode.push_variable(&value_name);
+            bytecode.push_variable(key);
+            bytecode.push(OpCode::PushIndex);
+        }
+        Self::store(bytecode, &self.name);
+
+        bytecode.bind(body_label);
+        bytecode.push_loop(self.label.clone(), exit_label, advance_label);
+        self.block.generate_bytecode(bytecode, false)?;
+        bytecode.pop_loop();
+        bytecode.push_jump(OpCode::Jump { location: 0 }, advance_label);
+
+        bytecode.bind(exit_label);
+
+        return Ok(());
+    }
+
+    /// Counts from the start to the end, including the end. The step can be negative
+    fn generate_range_bytecode(
+        &self,
+        range: &RangeExpr,
+        bytecode: &mut BytecodeBuilder,
+    ) -> Result<(), BytecodeGenerationError> {
+        let value_name = self.hidden_name("value");
+        let end_name = self.hidden_name("end");
+        let step_name = self.hidden_name("step");
+
+        range.start.generate_bytecode(bytecode)?;
+        range.end.generate_bytecode(bytecode)?;
+        range.generate_step(bytecode)?;
+
+        for name in self.locals() {
+            bytecode.init_variable(&name);
+        }
+        Self::store(bytecode, &step_name);
+        Self::store(bytecode, &end_name);
+        Self::store(bytecode, &value_name);
+
+        // A step of 0 never reaches the end
+        let step_label = bytecode.new_label();
+        bytecode.set_slice(&range.slice);
+        bytecode.push_variable(&step_name);
+        bytecode.push(OpCode::PushConstInt { value: 0 });
+        bytecode.push(OpCode::OpNe);
+        bytecode.push_jump(OpCode::JumpTrue { location: 0 }, step_label);
         bytecode.push(OpCode::PushVariable {
-            name: index_name.clone(),
-        });
-        bytecode.push(OpCode::PushVariable {
-            name: value_name.clone(),
+            name: "RangeError".into(),
         });
+        bytecode.push(OpCode::Dupe);
+        Self::push_property(bytecode, "new");
         bytecode.push(OpCode::PushConstString {
-            value: "length".into(),
+            value: "A range can't have a step of 0".into(),
         });
-        bytecode.push(OpCode::PushIndex);
-        bytecode.push(OpCode::OpLt);
-
-        let update_exit_pos = bytecode.len();
-        bytecode.push(OpCode::Jump { location: 0 });
+        bytecode.push(OpCode::Invoke {
+            param_count: 1,
+            this_call: true,
+        });
+        bytecode.push(OpCode::Throw);
+        bytecode.bind(step_label);
 
-        self.block.generate_bytecode(bytecode, false, true)?;
+        let increment_label = bytecode.new_label();
+        let check_label = bytecode.new_label();
+        let exit_label = bytecode.new_label();
 
-        let exit_pos = bytecode.len();
+        bytecode.push_jump(OpCode::Jump { location: 0 }, check_label);
 
-        for pos in (update_exit_pos + 1)..exit_pos {
-            let value = &mut bytecode[pos];
+        bytecode.bind(increment_label);
+        bytecode.push_variable(&value_name);
+        bytecode.push_variable(&step_name);
+        bytecode.push(OpCode::OpAdd);
+        Self::store(bytecode, &value_name);
+
+        // (value - end) * step <= 0 holds for both directions
+        bytecode.bind(check_label);
+        bytecode.push_variable(&value_name);
+        bytecode.push_variable(&end_name);
+        bytecode.push(OpCode::OpSub);
+        bytecode.push_variable(&step_name);
+        bytecode.push(OpCode::OpMul);
+        bytecode.push(OpCode::PushConstInt { value: 0 });
+        bytecode.push(OpCode::OpLe);
+        bytecode.push_jump(OpCode::JumpFalse { location: 0 }, exit_label);
 
-            if let OpCode::TempBreak = value {
-                *value = OpCode::Jump { location: exit_pos };
-                continue;
-            }
+        bytecode.push_variable(&value_name);
+        Self::store(bytecode, &self.name);
 
-            if let OpCode::TempContinue = value {
-                *value = OpCode::Jump {
-                    location: increment_pos,
-                };
-                continue;
-            }
-        }
+        bytecode.push_loop(self.label.clone(), exit_label, increment_label);
+        self.block.generate_bytecode(bytecode, false)?;
+        bytecode.pop_loop();
+        bytecode.push_jump(OpCode::Jump { location: 0 }, increment_label);
 
-        bytecode[update_exit_pos] = OpCode::Jump { location: exit_pos };
+        bytecode.bind(exit_label);
 
         return Ok(());
     }
 
+    /// Exits the loop if the index isn't less than the length of the array
+    fn check_index(
+        bytecode: &mut BytecodeBuilder,
+        index_name: &Arc<str>,
+        array_name: &Arc<str>,
+        exit_label: Label,
+    ) {
+        bytecode.push_variable(index_name);
+        bytecode.push_variable(array_name);
+        Self::push_property(bytecode, "length");
+        bytecode.push(OpCode::OpLt);
+        bytecode.push_jump(OpCode::JumpFalse { location: 0 }, exit_label);
+    }
+
+    fn push_property(bytecode: &mut BytecodeBuilder, name: &str) {
+        bytecode.push(OpCode::PushConstString { value: name.into() });
+        bytecode.push(OpCode::PushIndex);
+    }
+
+    fn store(bytecode: &mut BytecodeBuilder, name: &Arc<str>) {
+        bytecode.store_variable(name);
+        bytecode.push(OpCode::Pop);
+    }
+
     pub fn try_parse(tokenizer: &mut Tokenizer) -> Result<Option<Self>, ParserError> {
         let start = tokenizer.peek(0)?.slice;
         try_next!(TokenKind::Keyword(Keyword::For), tokenizer);
 
         require_next!(TokenKind::Keyword(Keyword::Each), tokenizer);
 
-        require_next!(TokenKind::Identifier(name), tokenizer);
+        require_next!(TokenKind::Identifier(first), tokenizer);
+
+        let (key, name) = if is_next!(TokenKind::Symbol(Symbol::Comma), tokenizer) {
+            require_next!(TokenKind::Identifier(name), tokenizer);
+            (Some(first), name)
+        } else {
+            (None, first)
+        };
 
         require_next!(TokenKind::Keyword(Keyword::In), tokenizer);
 
@@ -536,6 +875,8 @@ impl ForStmt {
 
         return Ok(Some(Self {
             slice: start.merge(&block.slice),
+            label: None,
+            key,
             name,
             expr,
             block,
@@ -601,3 +942,345 @@ impl ConditionArm {
         )));
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bytecode::{op_code::OpCode, verifier::verify, BytecodeGenerationError, CompiledModule},
+        parse_tree::{tree::ParseTree, ParserError},
+        tokenizer::Tokenizer,
+    };
+
+    fn compile(src: &str) -> Result<CompiledModule, BytecodeGenerationError> {
+        let mut tokenizer = Tokenizer::new(src.into());
+        let tree = ParseTree::try_parse(&mut tokenizer).unwrap().unwrap();
+        let module = tree.compile()?;
+        assert_eq!(verify(&module), Ok(()));
+
+        return Ok(module);
+    }
+
+    fn init(src: &str) -> Vec<OpCode> {
+        return compile(src).unwrap().init.body.to_vec();
+    }
+
+    fn contains(body: &[OpCode], ops: &[OpCode]) -> bool {
+        return body.windows(ops.len()).any(|it| it == ops);
+    }
+
+    fn position(body: &[OpCode], op: OpCode) -> usize {
+        return body.iter().position(|it| *it == op).unwrap();
+    }
+
+    fn string(value: &str) -> OpCode {
+        return OpCode::PushConstString {
+            value: value.into(),
+        };
+    }
+
+    fn push(name: &str) -> OpCode {
+        return OpCode::PushVariable { name: name.into() };
+    }
+
+    fn store(name: &str) -> OpCode {
+        return OpCode::StoreVariablePop { name: name.into() };
+    }
+
+    #[test]
+    fn ranges() {
+        // A range directly in a loop counts instead of creating a range, and checks the step isn't 0 first
+        let body = init("let n = -1\nfor each i in 10 to 1 step n do print(i) end\n");
+        assert!(!body.contains(&OpCode::PushRange));
+        assert!(!body.contains(&string("__iter__")));
+        assert!(contains(
+            &body,
+            &[
+                push("__each_i_step__"),
+                OpCode::PushConstInt { value: 0 },
+                OpCode::OpNe,
+            ]
+        ));
+        assert!(
+            position(&body, push("RangeError")) < position(&body, store("i")),
+            "The step is checked before the loop starts"
+        );
+
+        assert!(init("let range = 1 to 5\n").contains(&OpCode::PushRange));
+
+        for step in ["0", "-0", "0.0"] {
+            assert!(matches!(
+                compile(&format!(
+                    "for each i in 1 to 10 step {} do print(i) end\n",
+                    step
+                )),
+                Err(BytecodeGenerationError::ZeroStep(_))
+            ));
+        }
+        assert!(matches!(
+            compile("let range = 1 to 10 step 0\n"),
+            Err(BytecodeGenerationError::ZeroStep(_))
+        ));
+
+        // `to` and `step` are only keywords inside of a range
+        let body = init(
+            "let to = 1
+let step = 2
+for each i in to to 10 step step do print(i) end
+to = 3
+step += 1
+print(to.x)
+",
+        );
+        assert!(contains(
+            &body,
+            &[push("to"), OpCode::PushConstInt { value: 10 }, push("step")]
+        ));
+        assert!(body.contains(&store("to")));
+        assert!(body.contains(&store("step")));
+
+        // Or on the line after what they'd follow, where they start the next statement
+        let body = init(
+            "function to(n) return n end
+function step(n) return n end
+let x = 1
+let y = x
+to(3)
+let range = 1 to 5
+step(4)
+",
+        );
+        assert!(contains(&body, &[push("x"), store("y")]));
+        assert!(contains(
+            &body,
+            &[
+                OpCode::PushConstInt { value: 1 },
+                OpCode::PushConstInt { value: 5 },
+                OpCode::PushConstInt { value: 1 },
+                OpCode::PushRange,
+                store("range"),
+            ]
+        ));
+        assert!(body.contains(&push("to")));
+        assert!(body.contains(&push("step")));
+    }
+
+    #[test]
+    fn iteration() {
+        let body = init("for each key, value in { a = 1 } do print(key, value) end\n");
+
+        // `__iter__` comes first, then indexing up to `length`, then the keys of the object
+        assert!(position(&body, string("__iter__")) < position(&body, string("length")));
+        assert!(position(&body, string("length")) < position(&body, OpCode::PushKeys));
+
+        // The protocol stops on `done`, and takes the key and value from what `next` returns
+        assert!(body.windows(3).any(|it| matches!(
+            it,
+            [OpCode::PushConstString { value }, OpCode::PushIndex, OpCode::JumpTrue { .. }]
+                if value.as_ref() == "done"
+        )));
+        assert!(contains(
+            &body,
+            &[string("key"), OpCode::PushIndex, store("key")]
+        ));
+        assert!(contains(
+            &body,
+            &[string("value"), OpCode::PushIndex, store("value")]
+        ));
+
+        // Values with a length are indexed directly, with the index as the key
+        assert!(contains(
+            &body,
+            &[
+                push("__each_value_index__"),
+                store("key"),
+                push("__each_value_value__"),
+                push("__each_value_index__"),
+                OpCode::PushIndex,
+                store("value"),
+            ]
+        ));
+
+        // Otherwise the key indexes the object
+        assert!(contains(
+            &body,
+            &[
+                store("key"),
+                push("__each_value_value__"),
+                push("key"),
+                OpCode::PushIndex,
+                store("value"),
+            ]
+        ));
+
+        // Without a key name, the keys themselves are the values
+        let body = init("for each name in { a = 1 } do print(name) end\n");
+        assert!(contains(
+            &body,
+            &[
+                push("__each_name_keys__"),
+                push("__each_name_index__"),
+                OpCode::PushIndex,
+                store("name"),
+            ]
+        ));
+    }
+
+    /// The instructions that push, pop and run catches and finally blocks, or leave the function
+    fn handlers(body: &[OpCode]) -> Vec<OpCode> {
+        return body
+            .iter()
+            .filter(|it| {
+                matches!(
+                    it,
+                    OpCode::PushCatch { .. }
+                        | OpCode::PopCatch
+                        | OpCode::PushFinally { .. }
+                        | OpCode::PopFinally
+                        | OpCode::EndFinally
+                        | OpCode::Leave { .. }
+                        | OpCode::Return
+                        | OpCode::Throw
+                )
+            })
+            .cloned()
+            .collect();
+    }
+
+    #[test]
+    fn try_statements() {
+        // Returning from the try block leaves through the finally block, which the return runs
+        let module = compile(
+            "function f()
+    try
+        return 1
+    finally
+        print(2)
+    end
+end
+",
+        )
+        .unwrap();
+        let body = &module.functions[0].body;
+        assert_eq!(
+            handlers(body),
+            [
+                OpCode::PushFinally { location: 5 },
+                OpCode::Return,
+                OpCode::EndFinally,
+                OpCode::Return,
+            ]
+        );
+        assert!(body[5..].starts_with(&[OpCode::PushContext, push("print")]));
+
+        // Breaking out of the try block leaves the finally block, running it
+        let body = init(
+            "while true do
+    try
+        break
+    finally
+        print(1)
+    end
+end
+",
+        );
+        assert_eq!(
+            handlers(&body),
+            [
+                OpCode::PushFinally { location: 4 },
+                OpCode::Leave {
+                    location: body.len(),
+                    handlers: 1,
+                    contexts: 2
+                },
+                OpCode::EndFinally,
+            ]
+        );
+
+        // An exception no clause matches is thrown again, after the finally block runs
+        let body = init(
+            "try
+    throw 1
+catch e is TypeError
+    print(e)
+finally
+    print(2)
+end
+",
+        );
+        assert!(matches!(
+            &handlers(&body)[..],
+            [
+                OpCode::PushFinally { .. },
+                OpCode::PushCatch { .. },
+                OpCode::Throw,
+                OpCode::Throw,
+                OpCode::PopFinally,
+                OpCode::EndFinally,
+            ]
+        ));
+        let Some(OpCode::JumpFalse { location }) = body
+            .iter()
+            .find(|it| matches!(it, OpCode::JumpFalse { .. }))
+        else {
+            panic!("The type of the catch isn't checked");
+        };
+        assert_eq!(
+            body[*location..*location + 2],
+            [OpCode::PushException, OpCode::Throw]
+        );
+
+        // Without a catch, only the finally block is pushed
+        let body = init(
+            "try
+    print(1)
+finally
+    print(2)
+end
+",
+        );
+        assert_eq!(
+            handlers(&body),
+            [
+                OpCode::PushFinally { location: 8 },
+                OpCode::PopFinally,
+                OpCode::EndFinally,
+            ]
+        );
+
+        // The exception's name ends with it's clause, after that it's a global again
+        let module = compile(
+            "function f()
+    try
+        g()
+    catch e
+        return e
+    end
+    return e
+end
+",
+        )
+        .unwrap();
+        let body = &module.functions[0].body;
+        assert!(contains(
+            body,
+            &[OpCode::PushLocal { depth: 1, slot: 0 }, OpCode::Return]
+        ));
+        assert!(contains(body, &[push("e"), OpCode::Return]));
+
+        let mut tokenizer = Tokenizer::new(
+            "try
+    throw 1
+catch e
+    print(e)
+catch e is TypeError
+    print(e)
+end
+"
+            .into(),
+        );
+        assert!(matches!(
+            ParseTree::try_parse(&mut tokenizer),
+            Err(ParserError::UnreachableCatch(_))
+        ));
+    }
+}
@@ -3,7 +3,7 @@ use std::sync::Arc;
 use control::ControlStmt;
 
 use crate::{
-    bytecode::{op_code::OpCode, BytecodeGenerationError},
+    bytecode::{builder::BytecodeBuilder, op_code::OpCode, BytecodeGenerationError},
     parse_tree::if_next,
     string::StringSlice,
     tokenizer::{
@@ -38,17 +38,12 @@ pub struct Block {
 impl Stmt {
     pub fn generate_bytecode(
         &self,
-        bytecode: &mut Vec<OpCode>,
+        bytecode: &mut BytecodeBuilder,
         allow_export: bool,
-        allow_break_continue: bool,
     ) -> Result<(), BytecodeGenerationError> {
-        bytecode.push(OpCode::SetSlice {
-            slice: self.slice.clone(),
-        });
+        bytecode.set_slice(&self.slice);
         return match &self.kind {
-            StmtKind::Control(control) => {
-                control.generate_bytecode(bytecode, allow_export, allow_break_continue)
-            }
+            StmtKind::Control(control) => control.generate_bytecode(bytecode, allow_export),
             StmtKind::Variable(variable) => variable.generate_bytecode(bytecode, allow_export),
             StmtKind::Expr(expr) => {
                 let value = expr.generate_bytecode(bytecode);
@@ -88,18 +83,15 @@ impl Stmt {
 impl Block {
     pub fn generate_bytecode(
         &self,
-        bytecode: &mut Vec<OpCode>,
+        bytecode: &mut BytecodeBuilder,
         allow_export: bool,
-        allow_break_continue: bool,
     ) -> Result<(), BytecodeGenerationError> {
-        bytecode.push(OpCode::SetSlice {
-            slice: self.slice.clone(),
-        });
-        bytecode.push(OpCode::PushContext);
+        bytecode.set_slice(&self.slice);
+        bytecode.push_context();
         for stmt in self.stmts.iter() {
-            stmt.generate_bytecode(bytecode, allow_export, allow_break_continue)?
+            stmt.generate_bytecode(bytecode, allow_export)?
         }
-        bytecode.push(OpCode::PopContext);
+        bytecode.pop_context();
 
         return Ok(());
     }
@@ -1,7 +1,10 @@
 use std::sync::Arc;
 
 use crate::{
-    bytecode::{op_code::OpCode, BytecodeGenerationError},
+    bytecode::{
+        builder::BytecodeBuilder, op_code::OpCode, optimizer, peephole, BytecodeGenerationError,
+        CompiledModule,
+    },
     parse_tree::decl::function::FunctionImpl,
     string::StringSlice,
     tokenizer::{token::TokenKind, Tokenizer},
@@ -24,6 +27,7 @@ pub struct ParseTree {
     pub imports: Arc<[ImportDecl]>,
     pub functions: Arc<[FunctionImpl]>,
     pub classes: Arc<[ClassDecl]>,
+    pub declares: Arc<[IdeDecl]>,
     pub stmts: Arc<[Stmt]>,
 }
 
@@ -34,14 +38,42 @@ pub struct TopLevelClass {
 }
 
 impl ParseTree {
+    /// Compiles the module initializer and the bodies of every function in it
+    pub fn compile(&self) -> Result<CompiledModule, BytecodeGenerationError> {
+        let module = optimizer::optimize_module(&self.generate_module()?);
+        return Ok(peephole::fuse_module(&module));
+    }
+
+    /// Resolves and compiles the module without optimizing it
+    pub fn generate_module(&self) -> Result<CompiledModule, BytecodeGenerationError> {
+        let resolution = self.resolve();
+        if let Some(error) = resolution.first_error() {
+            return Err(BytecodeGenerationError::Resolve(error.clone()));
+        }
+
+        let bindings = Arc::new(resolution.bindings);
+
+        let mut init = BytecodeBuilder::new().with_bindings(bindings.clone());
+        self.generate_init_bytecode(&mut init)?;
+
+        let functions = self
+            .functions
+            .iter()
+            .map(|it| it.generate_bytecode(&bindings))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        return Ok(CompiledModule {
+            functions: functions.into_boxed_slice().into(),
+            init: init.finish("<init>".into(), Arc::new([])),
+        });
+    }
+
     pub fn generate_init_bytecode(
         &self,
-        bytecode: &mut Vec<OpCode>,
+        bytecode: &mut BytecodeBuilder,
     ) -> Result<(), BytecodeGenerationError> {
         for import in self.imports.iter() {
-            bytecode.push(OpCode::SetSlice {
-                slice: import.slice.clone(),
-            });
+            bytecode.set_slice(&import.slice);
 
             match &import.kind {
                 ImportKind::Direct(DirectImport { slice: _, file }) => {
@@ -58,10 +90,10 @@ impl ParseTree {
                         match &value.kind {
                             FromImportKind::Everything { name } => {
                                 bytecode.push(OpCode::Dupe);
-                                bytecode.push(OpCode::InitVariable { name: name.clone() });
-                                bytecode.push(OpCode::StoreVariable { name: name.clone() });
+                                bytecode.init_variable(name);
+                                bytecode.store_variable(name);
                                 bytecode.push(OpCode::Pop);
-                                bytecode.push(OpCode::MarkVariableConst { name: name.clone() });
+                                bytecode.mark_variable_const(name);
                             }
                             FromImportKind::Single { name, rename } => {
                                 bytecode.push(OpCode::Dupe);
@@ -71,20 +103,14 @@ impl ParseTree {
                                     name
                                 };
 
-                                bytecode.push(OpCode::InitVariable {
-                                    name: value_name.clone(),
-                                });
+                                bytecode.init_variable(value_name);
                                 bytecode.push(OpCode::PushConstString {
                                     value: name.clone(),
                                 });
                                 bytecode.push(OpCode::PushIndex);
-                                bytecode.push(OpCode::StoreVariable {
-                                    name: value_name.clone(),
-                                });
+                                bytecode.store_variable(value_name);
                                 bytecode.push(OpCode::Pop);
-                                bytecode.push(OpCode::MarkVariableConst {
-                                    name: value_name.clone(),
-                                });
+                                bytecode.mark_variable_const(value_name);
                             }
                         }
                     }
@@ -94,28 +120,18 @@ impl ParseTree {
         }
 
         for class in self.classes.iter() {
-            bytecode.push(OpCode::SetSlice {
-                slice: class.slice.clone(),
-            });
+            bytecode.set_slice(&class.slice);
 
-            bytecode.push(OpCode::InitVariable {
-                name: class.name.clone(),
-            });
+            bytecode.init_variable(&class.name);
             bytecode.push(OpCode::PushNewObject);
             if let Some(extends) = &class.extends {
                 bytecode.push(OpCode::Dupe);
-                bytecode.push(OpCode::PushVariable {
-                    name: extends.clone(),
-                });
+                bytecode.push_variable(extends);
                 bytecode.push(OpCode::StoreProtorype);
             }
-            bytecode.push(OpCode::StoreVariable {
-                name: class.name.clone(),
-            });
+            bytecode.store_variable(&class.name);
             bytecode.push(OpCode::Pop);
-            bytecode.push(OpCode::MarkVariableConst {
-                name: class.name.clone(),
-            });
+            bytecode.mark_variable_const(&class.name);
 
             if class.export {
                 bytecode.push(OpCode::Export {
@@ -127,12 +143,10 @@ impl ParseTree {
         for i in 0..self.functions.len() {
             let func = &self.functions[i];
 
-            bytecode.push(OpCode::SetSlice {
-                slice: func.slice.clone(),
-            });
+            bytecode.set_slice(&func.slice);
 
             if let Some(base) = &func.decl.base {
-                bytecode.push(OpCode::PushVariable { name: base.clone() });
+                bytecode.push_variable(base);
                 bytecode.push(OpCode::PushConstString {
                     value: func.decl.name.clone(),
                 });
@@ -142,17 +156,11 @@ impl ParseTree {
                 continue;
             }
 
-            bytecode.push(OpCode::InitVariable {
-                name: func.decl.name.clone(),
-            });
+            bytecode.init_variable(&func.decl.name);
             bytecode.push(OpCode::PushFunction { index: i });
-            bytecode.push(OpCode::StoreVariable {
-                name: func.decl.name.clone(),
-            });
+            bytecode.store_variable(&func.decl.name);
             bytecode.push(OpCode::Pop);
-            bytecode.push(OpCode::MarkVariableConst {
-                name: func.decl.name.clone(),
-            });
+            bytecode.mark_variable_const(&func.decl.name);
 
             if func.export {
                 bytecode.push(OpCode::Export {
@@ -162,7 +170,7 @@ impl ParseTree {
         }
 
         for stmt in self.stmts.iter() {
-            stmt.generate_bytecode(bytecode, true, false)?;
+            stmt.generate_bytecode(bytecode, true)?;
         }
 
         return Ok(());
@@ -182,9 +190,11 @@ impl ParseTree {
         let mut stmts = vec![];
         let mut functions = vec![];
         let mut classes = vec![];
+        let mut declares = vec![];
 
         loop {
-            if let Some(_) = IdeDecl::try_parse(tokenizer)? {
+            if let Some(decl) = IdeDecl::try_parse(tokenizer)? {
+                declares.push(decl);
                 continue;
             }
 
@@ -213,6 +223,7 @@ impl ParseTree {
             imports: imports.into_boxed_slice().into(),
             functions: functions.into_boxed_slice().into(),
             classes: classes.into_boxed_slice().into(),
+            declares: declares.into_boxed_slice().into(),
             stmts: stmts.into_boxed_slice().into(),
         }));
     }
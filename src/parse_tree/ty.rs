@@ -1,4 +1,7 @@
-use std::sync::Arc;
+use std::{
+    fmt::{Display, Formatter},
+    sync::Arc,
+};
 
 use crate::{
     parse_tree::{
@@ -68,7 +71,7 @@ impl Type {
 
         return Ok(Some(Self {
             slice: tys.first().unwrap().slice.merge(&tys.last().unwrap().slice),
-            kind: TypeKind::Or(tys.into_boxed_slice().into()),
+            kind: TypeKind::And(tys.into_boxed_slice().into()),
         }));
     }
 
@@ -267,3 +270,97 @@ impl FunctionType {
         }));
     }
 }
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match &self.kind {
+            TypeKind::Value(value) => Display::fmt(value, f),
+            TypeKind::Function(func) => Display::fmt(func, f),
+            TypeKind::Or(tys) => {
+                for (i, ty) in tys.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(" or ")?;
+                    }
+                    // `and` binds looser than `or`, so it needs parenthesis to round trip
+                    if let TypeKind::And(_) = ty.kind {
+                        write!(f, "({})", ty)?;
+                    } else {
+                        Display::fmt(ty, f)?;
+                    }
+                }
+                Ok(())
+            }
+            TypeKind::And(tys) => {
+                for (i, ty) in tys.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(" and ")?;
+                    }
+                    Display::fmt(ty, f)?;
+                }
+                Ok(())
+            }
+            TypeKind::Prototype(ty) => write!(f, "prototype[{}]", ty),
+            TypeKind::Class => f.write_str("class"),
+            TypeKind::This => f.write_str("this"),
+            TypeKind::None => f.write_str("none"),
+        };
+    }
+}
+
+impl Display for ValueType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+
+        if self.generics.is_empty() {
+            return Ok(());
+        }
+
+        f.write_str("[")?;
+        for (i, ty) in self.generics.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            Display::fmt(ty, f)?;
+        }
+        return f.write_str("]");
+    }
+}
+
+impl Display for FunctionType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("function(")?;
+        for (i, ty) in self.params.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            Display::fmt(ty, f)?;
+        }
+        f.write_str(")")?;
+
+        if let Some(ret) = &self.ret {
+            write!(f, " {}", ret)?;
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tokenizer::Tokenizer;
+
+    use super::{Type, TypeKind};
+
+    #[test]
+    fn and_binds_looser_than_or() {
+        let mut tokenizer = Tokenizer::new("Readable and string or none".into());
+        let ty = Type::try_parse(&mut tokenizer).unwrap().unwrap();
+
+        let TypeKind::And(tys) = &ty.kind else {
+            panic!("Expected an and type, got {}", ty);
+        };
+        assert_eq!(tys.len(), 2);
+        assert!(matches!(&tys[1].kind, TypeKind::Or(tys) if tys.len() == 2));
+        assert_eq!(ty.to_string(), "Readable and string or none");
+    }
+}
@@ -0,0 +1,86 @@
+use std::{
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Where the runtime gets the time from, so embedders can replace it with a fake clock
+pub trait Clock {
+    /// The time since a fixed point, which never goes backwards
+    fn monotonic(&self) -> Duration;
+
+    /// The wall-clock time since the unix epoch, in UTC
+    fn wall(&self) -> Duration;
+
+    fn sleep(&self, duration: Duration);
+}
+
+/// The clock of the operating system
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        return Self {
+            start: Instant::now(),
+        };
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+impl Clock for SystemClock {
+    fn monotonic(&self) -> Duration {
+        return self.start.elapsed();
+    }
+
+    fn wall(&self) -> Duration {
+        // A system clock set before 1970 is treated as the epoch
+        return SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// A clock that only moves when it's told to, sleeping advances it instead of waiting
+pub struct ManualClock {
+    /// The monotonic and wall-clock times
+    times: Mutex<(Duration, Duration)>,
+}
+
+impl ManualClock {
+    pub fn new(wall: Duration) -> Self {
+        return Self {
+            times: Mutex::new((Duration::ZERO, wall)),
+        };
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        let mut times = self.times.lock().unwrap();
+        times.0 += duration;
+        times.1 += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn monotonic(&self) -> Duration {
+        return self.times.lock().unwrap().0;
+    }
+
+    fn wall(&self) -> Duration {
+        return self.times.lock().unwrap().1;
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
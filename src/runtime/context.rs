@@ -0,0 +1,251 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use super::{
+    error::ErrorKind,
+    value::{object_pool::Property, string_pool::StrReference, Value},
+    Runtime,
+};
+
+/// The variables of a block while it runs, chained to the context it was pushed in
+pub struct Context {
+    pub parent: Option<Arc<Context>>,
+    /// Variables looked up by name, from `InitVariable`
+    variables: RwLock<HashMap<StrReference, Variable>>,
+    /// Locals resolved to slots when compiling, from `InitLocal`
+    slots: RwLock<Vec<Variable>>,
+}
+
+#[derive(Clone)]
+pub struct Variable {
+    pub value: Value,
+    /// Set by `MarkVariableConst` once the variable has it's value
+    pub is_const: bool,
+}
+
+impl Variable {
+    fn uninitialized() -> Self {
+        return Self {
+            value: Value::Uninitialized,
+            is_const: false,
+        };
+    }
+}
+
+impl Context {
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new(parent: Option<Arc<Context>>) -> Arc<Self> {
+        return Arc::new(Self {
+            parent,
+            variables: RwLock::new(HashMap::new()),
+            slots: RwLock::new(vec![]),
+        });
+    }
+
+    /// The context `depth` contexts up the chain
+    fn ancestor(&self, runtime: &Runtime, depth: usize) -> Result<&Context, Value> {
+        let mut context = self;
+        for _ in 0..depth {
+            let Some(parent) = context.parent.as_deref() else {
+                return Err(runtime.new_error(
+                    ErrorKind::ReferenceError,
+                    "A local was resolved past the outermost context",
+                ));
+            };
+            context = parent;
+        }
+
+        return Ok(context);
+    }
+
+    pub fn init_variable(&self, name: StrReference) {
+        self.variables
+            .write()
+            .unwrap()
+            .insert(name, Variable::uninitialized());
+    }
+
+    pub fn mark_variable_const(&self, name: &StrReference) {
+        if let Some(variable) = self.variables.write().unwrap().get_mut(name) {
+            variable.is_const = true;
+        }
+    }
+
+    /// Finds the variable in this context or the ones it's chained to, then in the runtime's globals
+    pub fn get_variable(&self, runtime: &Runtime, name: &StrReference) -> Result<Value, Value> {
+        let mut context = Some(self);
+        while let Some(current) = context {
+            if let Some(variable) = current.variables.read().unwrap().get(name) {
+                return Ok(variable.value.clone());
+            }
+            context = current.parent.as_deref();
+        }
+
+        if let Some(prop) = runtime.globals.get().values.read().unwrap().get(name)
+            && let Property::Value(value) = &*prop.read().unwrap()
+        {
+            return Ok(value.clone());
+        }
+
+        return Err(runtime.new_error(
+            ErrorKind::ReferenceError,
+            &format!("{} is not defined", name),
+        ));
+    }
+
+    /// Stores to the innermost variable with the name, which fails if it's const
+    pub fn store_variable(
+        &self,
+        runtime: &Runtime,
+        name: &StrReference,
+        value: Value,
+    ) -> Result<(), Value> {
+        let mut context = Some(self);
+        while let Some(current) = context {
+            if let Some(variable) = current.variables.write().unwrap().get_mut(name) {
+                return Self::store(runtime, &name.get(), variable, value);
+            }
+            context = current.parent.as_deref();
+        }
+
+        return Err(runtime.new_error(
+            ErrorKind::ReferenceError,
+            &format!("{} is not defined", name),
+        ));
+    }
+
+    /// Resets the slot in this context, growing it if needed
+    pub fn init_local(&self, slot: usize) {
+        let mut slots = self.slots.write().unwrap();
+        if slots.len() <= slot {
+            slots.resize(slot + 1, Variable::uninitialized());
+        }
+        slots[slot] = Variable::uninitialized();
+    }
+
+    pub fn mark_local_const(&self, slot: usize) {
+        if let Some(variable) = self.slots.write().unwrap().get_mut(slot) {
+            variable.is_const = true;
+        }
+    }
+
+    /// Gets the local, which fails if there's no slot for it or it hasn't been given a value yet.
+    /// The name is only used for the error
+    pub fn get_local(
+        &self,
+        runtime: &Runtime,
+        depth: usize,
+        slot: usize,
+        name: &str,
+    ) -> Result<Value, Value> {
+        let context = self.ancestor(runtime, depth)?;
+        let slots = context.slots.read().unwrap();
+
+        return match slots.get(slot) {
+            Some(Variable {
+                value: Value::Uninitialized,
+                ..
+            }) => Err(runtime.new_error(
+                ErrorKind::ReferenceError,
+                &format!("{} is used before it's initialized", name),
+            )),
+            Some(variable) => Ok(variable.value.clone()),
+            None => Err(Self::undefined_local(runtime, name)),
+        };
+    }
+
+    /// Stores to the local, which fails if it's const or there's no slot for it. The name is only used for the error
+    pub fn store_local(
+        &self,
+        runtime: &Runtime,
+        depth: usize,
+        slot: usize,
+        name: &str,
+        value: Value,
+    ) -> Result<(), Value> {
+        let context = self.ancestor(runtime, depth)?;
+        let mut slots = context.slots.write().unwrap();
+        let Some(variable) = slots.get_mut(slot) else {
+            return Err(Self::undefined_local(runtime, name));
+        };
+
+        return Self::store(runtime, name, variable, value);
+    }
+
+    fn undefined_local(runtime: &Runtime, name: &str) -> Value {
+        return runtime.new_error(
+            ErrorKind::ReferenceError,
+            &format!("{} is not defined", name),
+        );
+    }
+
+    fn store(runtime: &Runtime, name: &str, variable: &mut Variable, value: Value) -> Result<(), Value> {
+        if variable.is_const {
+            return Err(runtime.new_error(
+                ErrorKind::TypeError,
+                &format!("Assignment to constant variable {}", name),
+            ));
+        }
+
+        variable.value = value;
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::runtime::{value::Value, Runtime};
+
+    use super::Context;
+
+    #[test]
+    fn const_store() {
+        let runtime = Runtime::new();
+        let name = runtime.string_pool.acquire("answer".into()).unwrap();
+
+        let outer = Context::new(None);
+        outer.init_variable(name.clone());
+        assert!(outer
+            .store_variable(&runtime, &name, Value::Integer(41))
+            .is_ok());
+        outer.mark_variable_const(&name);
+
+        let inner = Context::new(Some(outer.clone()));
+        inner.init_local(1);
+        assert!(inner
+            .store_local(&runtime, 0, 1, "local", Value::Integer(1))
+            .is_ok());
+        inner.mark_local_const(1);
+
+        assert!(matches!(
+            inner.get_variable(&runtime, &name),
+            Ok(Value::Integer(41))
+        ));
+        assert!(matches!(
+            inner.get_local(&runtime, 0, 1, "local"),
+            Ok(Value::Integer(1))
+        ));
+
+        let Err(error) = inner.store_variable(&runtime, &name, Value::Integer(42)) else {
+            panic!("Stored to a const");
+        };
+        assert!(runtime.is_error(&error));
+        assert!(inner
+            .store_local(&runtime, 0, 1, "local", Value::Integer(2))
+            .is_err());
+
+        // Slots without a value can't be read, and slots and contexts past the last one don't exist
+        assert!(inner.get_local(&runtime, 0, 0, "local").is_err());
+        assert!(inner.get_local(&runtime, 0, 2, "missing").is_err());
+        assert!(inner
+            .store_local(&runtime, 0, 2, "missing", Value::None)
+            .is_err());
+        assert!(inner.get_local(&runtime, 2, 0, "missing").is_err());
+
+        // Declaring it again in an inner context shadows the const
+        inner.init_variable(name.clone());
+        assert!(inner.store_variable(&runtime, &name, Value::None).is_ok());
+    }
+}
@@ -0,0 +1,448 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    fmt::{self, Display},
+    sync::{Arc, RwLock},
+};
+
+use crate::string::StringSlice;
+
+use super::{
+    value::{
+        object_pool::{MarkChildren, ObjectPool, ObjectReference, Property},
+        string_pool::StringPool,
+        NativeValue, Value,
+    },
+    Runtime,
+};
+
+/// The built in error prototypes, every one of them extends `Error`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    Error,
+    TypeError,
+    ReferenceError,
+    /// Thrown when an index or count is outside of what a native function accepts
+    RangeError,
+    /// Thrown when text given to a native function can't be parsed, with the `line` and `column` if it has them
+    SyntaxError,
+    ImportError,
+    /// Thrown by native modules when the system fails, with a `code` naming the failure
+    IOError,
+}
+
+impl ErrorKind {
+    pub const ALL: [ErrorKind; 7] = [
+        ErrorKind::Error,
+        ErrorKind::TypeError,
+        ErrorKind::ReferenceError,
+        ErrorKind::RangeError,
+        ErrorKind::SyntaxError,
+        ErrorKind::ImportError,
+        ErrorKind::IOError,
+    ];
+
+    pub fn name(self) -> &'static str {
+        return match self {
+            ErrorKind::Error => "Error",
+            ErrorKind::TypeError => "TypeError",
+            ErrorKind::ReferenceError => "ReferenceError",
+            ErrorKind::RangeError => "RangeError",
+            ErrorKind::SyntaxError => "SyntaxError",
+            ErrorKind::ImportError => "ImportError",
+            ErrorKind::IOError => "IOError",
+        };
+    }
+}
+
+/// A single source position in a stack trace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackFrame {
+    pub file: Arc<str>,
+    pub function: Arc<str>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl StackFrame {
+    /// Creates a frame from the source map position of a call frame
+    pub fn from_slice(file: Arc<str>, function: Arc<str>, slice: &StringSlice) -> Self {
+        let (line, column) = slice.line_column();
+
+        return Self {
+            file,
+            function,
+            line,
+            column,
+        };
+    }
+}
+
+impl Display for StackFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "at {} ({}:{}:{})",
+            self.function, self.file, self.line, self.column
+        );
+    }
+}
+
+/// The native value of an error object once it has been thrown, holding the captured frames
+struct ErrorStack(Arc<[StackFrame]>);
+
+impl NativeValue for ErrorStack {
+    fn mark_children(&self, _marker: &mut MarkChildren) {}
+}
+
+/// The native value of what `#os`'s `exit` throws, holding the code to end the process with
+struct Exit(i32);
+
+impl NativeValue for Exit {
+    fn mark_children(&self, _marker: &mut MarkChildren) {}
+}
+
+pub struct ErrorPrototypes {
+    prototypes: HashMap<ErrorKind, ObjectReference>,
+}
+
+impl ErrorPrototypes {
+    pub(super) fn new(string_pool: &Arc<StringPool>, object_pool: &Arc<ObjectPool>) -> Self {
+        let error = object_pool.new_object().unwrap();
+
+        let constructor = Value::Object(object_pool.new_native_object(Arc::new(construct)).unwrap());
+        insert_value(string_pool, &error, "new", constructor);
+
+        let mut prototypes = HashMap::new();
+
+        for kind in ErrorKind::ALL {
+            let prototype = match kind {
+                ErrorKind::Error => error.clone(),
+                _ => object_pool.new_object_proto(error.clone()).unwrap(),
+            };
+
+            insert_value(
+                string_pool,
+                &prototype,
+                "name",
+                Value::String(string_pool.acquire(kind.name().into()).unwrap()),
+            );
+
+            prototypes.insert(kind, prototype);
+        }
+
+        return Self { prototypes };
+    }
+
+    pub fn get(&self, kind: ErrorKind) -> &ObjectReference {
+        return &self.prototypes[&kind];
+    }
+}
+
+/// `Error.new(message, cause)`, creates an error using `this` as the prototype
+fn construct(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let Value::Object(prototype) = this_obj else {
+        return Err(runtime.new_error(ErrorKind::TypeError, "Error.new must be called on an error prototype"));
+    };
+
+    let message = params.first().cloned().unwrap_or(Value::None);
+    let cause = params.get(1).cloned().unwrap_or(Value::None);
+
+    return Ok(runtime.new_error_from(prototype, message, cause));
+}
+
+pub(super) fn insert_value(string_pool: &Arc<StringPool>, obj: &ObjectReference, name: &str, value: Value) {
+    obj.get().values.write().unwrap().insert(
+        string_pool.acquire(name.into()).unwrap(),
+        RwLock::new(Property::Value(value)),
+    );
+}
+
+/// Finds a plain value property on the object or one of it's prototypes, without invoking anything
+fn find_value(runtime: &Runtime, obj: &ObjectReference, name: &str) -> Option<Value> {
+    let key = runtime.string_pool.acquire(name.into()).unwrap();
+    let mut current = obj.clone();
+
+    loop {
+        let object = current.get();
+
+        if let Some(prop) = object.values.read().unwrap().get(&key)
+            && let Property::Value(value) = &*prop.read().unwrap()
+        {
+            return Some(value.clone());
+        }
+
+        let next = object.prototype.read().unwrap().clone();
+        current = next?;
+    }
+}
+
+impl Runtime {
+    /// Creates an error of one of the built in kinds, without a stack
+    pub fn new_error(&self, kind: ErrorKind, message: &str) -> Value {
+        let message = Value::String(self.string_pool.acquire(message.into()).unwrap());
+
+        return self.new_error_from(self.errors.get(kind), message, Value::None);
+    }
+
+    /// Creates an error with any prototype, which should extend `Error`
+    pub fn new_error_from(&self, prototype: &ObjectReference, message: Value, cause: Value) -> Value {
+        let error = self.object_pool.new_object_proto(prototype.clone()).unwrap();
+
+        insert_value(&self.string_pool, &error, "message", message);
+        insert_value(&self.string_pool, &error, "cause", cause);
+        insert_value(&self.string_pool, &error, "stack", Value::None);
+
+        return Value::Object(error);
+    }
+
+    /// Fills in the stack of an error when it's thrown, the innermost frame first.
+    /// Errors that were already thrown once keep their original stack.
+    pub fn capture_stack(&self, error: &Value, frames: &[StackFrame]) {
+        let Value::Object(obj) = error else {
+            return;
+        };
+
+        if !self.is_error(error) {
+            return;
+        }
+
+        let object = obj.get();
+
+        {
+            let mut native_value = object.native_value.write().unwrap();
+            if native_value.is_some() {
+                return;
+            }
+
+            *native_value = Some(Arc::new(ErrorStack(frames.to_vec().into_boxed_slice().into())));
+        }
+
+        let stack = frames
+            .iter()
+            .map(|frame| format!("    {}", frame))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        insert_value(
+            &self.string_pool,
+            obj,
+            "stack",
+            Value::String(self.string_pool.acquire(stack.into()).unwrap()),
+        );
+    }
+
+    /// Creates the value `exit` throws to end the script. Catch blocks don't handle it, but finally blocks still run,
+    /// and the host ends the process with the code once it reaches the top
+    pub fn new_exit(&self, code: i32) -> Value {
+        let exit = self.object_pool.new_object().unwrap();
+        *exit.get().native_value.write().unwrap() = Some(Arc::new(Exit(code)));
+
+        return Value::Object(exit);
+    }
+
+    /// The code of a value created by `new_exit`, or none for anything else
+    pub fn exit_code(&self, value: &Value) -> Option<i32> {
+        let Value::Object(obj) = value else {
+            return None;
+        };
+
+        let native_value = obj.get().native_value.read().unwrap().clone()?;
+        let Exit(code) = (native_value.as_ref() as &dyn Any).downcast_ref::<Exit>()?;
+
+        return Some(*code);
+    }
+
+    /// Checks if the value has `Error` somewhere in it's prototype chain
+    pub fn is_error(&self, value: &Value) -> bool {
+        let Value::Object(obj) = value else {
+            return false;
+        };
+
+        let error = self.errors.get(ErrorKind::Error);
+        let mut current = obj.get().prototype.read().unwrap().clone();
+
+        while let Some(proto) = current {
+            if proto == *error {
+                return true;
+            }
+
+            current = proto.get().prototype.read().unwrap().clone();
+        }
+
+        return false;
+    }
+}
+
+/// How many errors deep `BurrowError::from_value` follows causes
+const MAX_CAUSES: usize = 64;
+
+/// A thrown value converted for use outside of the runtime
+#[derive(Debug, Clone, PartialEq)]
+pub struct BurrowError {
+    pub name: Arc<str>,
+    pub message: String,
+    pub stack: Arc<[StackFrame]>,
+    pub cause: Option<Box<BurrowError>>,
+    /// Set when the script called `exit`, the host should end the process with the code instead of reporting an error
+    pub exit_code: Option<i32>,
+}
+
+impl BurrowError {
+    /// Converts a thrown value, values that aren't errors become an `Error` with the value as the message
+    pub fn from_value(runtime: &Runtime, value: &Value) -> Self {
+        return Self::from_chain(runtime, value, &mut vec![]);
+    }
+
+    /// Converts the value, with the errors it's the cause of in `chain`. A cause that's already in the chain,
+    /// or past `MAX_CAUSES`, is left out, since a script can make the chain a cycle or as long as it wants
+    fn from_chain(runtime: &Runtime, value: &Value, chain: &mut Vec<ObjectReference>) -> Self {
+        if let Some(code) = runtime.exit_code(value) {
+            return Self {
+                name: "Exit".into(),
+                message: format!("The script exited with code {}", code),
+                stack: Arc::new([]),
+                cause: None,
+                exit_code: Some(code),
+            };
+        }
+
+        let Value::Object(obj) = value else {
+            return Self {
+                name: ErrorKind::Error.name().into(),
+                message: value.to_string(),
+                stack: Arc::new([]),
+                cause: None,
+                exit_code: None,
+            };
+        };
+
+        if !runtime.is_error(value) {
+            return Self {
+                name: ErrorKind::Error.name().into(),
+                message: value.to_string(),
+                stack: Arc::new([]),
+                cause: None,
+                exit_code: None,
+            };
+        }
+
+        let name = match find_value(runtime, obj, "name") {
+            Some(Value::String(name)) => name.get(),
+            _ => ErrorKind::Error.name().into(),
+        };
+
+        let message = match find_value(runtime, obj, "message") {
+            Some(Value::None) | None => String::new(),
+            Some(message) => message.to_string(),
+        };
+
+        chain.push(obj.clone());
+        let cause = match find_value(runtime, obj, "cause") {
+            Some(Value::None) | Some(Value::Uninitialized) | None => None,
+            Some(Value::Object(cause)) if chain.contains(&cause) => None,
+            Some(_) if chain.len() >= MAX_CAUSES => None,
+            Some(cause) => Some(Box::new(Self::from_chain(runtime, &cause, chain))),
+        };
+
+        let stack = obj.get().native_value.read().unwrap().clone();
+        let stack = match stack.as_deref().and_then(|it| (it as &dyn Any).downcast_ref::<ErrorStack>()) {
+            Some(ErrorStack(frames)) => frames.clone(),
+            None => Arc::new([]),
+        };
+
+        return Self {
+            name,
+            message,
+            stack,
+            cause,
+            exit_code: None,
+        };
+    }
+}
+
+impl Display for BurrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.message.is_empty() {
+            write!(f, "{}", self.name)?;
+        } else {
+            write!(f, "{}: {}", self.name, self.message)?;
+        }
+
+        for frame in self.stack.iter() {
+            write!(f, "\n    {}", frame)?;
+        }
+
+        return Ok(());
+    }
+}
+
+impl std::error::Error for BurrowError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        return self
+            .cause
+            .as_ref()
+            .map(|cause| cause.as_ref() as &(dyn std::error::Error + 'static));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        runtime::{value::Value, Runtime},
+        string::ToStringSlice,
+    };
+
+    use super::{insert_value, BurrowError, ErrorKind, StackFrame, MAX_CAUSES};
+
+    #[test]
+    fn error_roundtrip() {
+        let runtime = Runtime::new();
+
+        let src: Arc<str> = "function f()\n    throw x\nend".into();
+        let frame = StackFrame::from_slice("main.bur".into(), "f".into(), &src.slice(17, 22));
+
+        let error = runtime.new_error(ErrorKind::TypeError, "Cannot invoke value");
+        assert!(runtime.is_error(&error));
+
+        runtime.capture_stack(&error, std::slice::from_ref(&frame));
+
+        let error = BurrowError::from_value(&runtime, &error);
+        assert_eq!(error.name.as_ref(), "TypeError");
+        assert_eq!(error.message, "Cannot invoke value");
+        assert_eq!(error.stack.as_ref(), &[frame]);
+        assert_eq!(
+            error.to_string(),
+            "TypeError: Cannot invoke value\n    at f (main.bur:2:5)"
+        );
+        assert_eq!(error.exit_code, None);
+
+        // A cause that leads back to the error ends the chain
+        let looped = runtime.new_error(ErrorKind::Error, "loop");
+        let Value::Object(obj) = &looped else {
+            panic!("Expected an object");
+        };
+        insert_value(&runtime.string_pool, obj, "cause", looped.clone());
+        let error = BurrowError::from_value(&runtime, &looped);
+        assert_eq!(error.cause, None);
+
+        let mut chain = runtime.new_error(ErrorKind::Error, "root");
+        for _ in 0..1000 {
+            let prototype = runtime.errors.get(ErrorKind::Error);
+            chain = runtime.new_error_from(prototype, Value::None, chain);
+        }
+        let mut depth = 0;
+        let mut error = &BurrowError::from_value(&runtime, &chain);
+        while let Some(cause) = &error.cause {
+            error = cause;
+            depth += 1;
+        }
+        assert_eq!(depth, MAX_CAUSES - 1);
+
+        let exit = runtime.new_exit(3);
+        assert!(!runtime.is_error(&exit));
+        assert_eq!(BurrowError::from_value(&runtime, &exit).exit_code, Some(3));
+    }
+}
@@ -3,20 +3,45 @@ use std::{
     sync::{Arc, RwLock},
 };
 
+use clock::{Clock, SystemClock};
+use error::{ErrorKind, ErrorPrototypes};
+use sandbox::Sandbox;
 use value::{
-    object_pool::ObjectPool,
+    array,
+    object_pool::{ObjectPool, ObjectReference, Property},
+    string,
     string_pool::{StrReference, StringPool},
     Value,
 };
 
 use crate::bytecode::CompiledModule;
 
+pub mod clock;
+pub mod context;
+pub mod error;
+pub mod modules;
+pub mod sandbox;
 pub mod value;
 
 pub struct Runtime {
     pub string_pool: Arc<StringPool>,
     pub object_pool: Arc<ObjectPool>,
     pub module_cache: RwLock<HashMap<StrReference, Arc<Module>>>,
+    /// The built in values every module can see, like the error prototypes
+    pub globals: ObjectReference,
+    pub errors: ErrorPrototypes,
+    /// The prototype of every array, which provides indexing, `length` and the array methods
+    pub array_prototype: ObjectReference,
+    /// The prototype strings get their properties from, since they aren't objects
+    pub string_prototype: ObjectReference,
+    pub sandbox: Sandbox,
+    /// Where `#time` gets the time from
+    pub clock: Box<dyn Clock>,
+    /// The arguments given to the script, which `#os` provides
+    pub args: Vec<String>,
+    /// Environment variables set by scripts, or none where removed. They're kept here instead of changing the
+    /// environment of the whole process, and apply to processes spawned by `#process`
+    pub env_overrides: RwLock<HashMap<String, Option<String>>>,
 }
 
 pub struct Module {
@@ -31,27 +56,87 @@ pub struct BytecodeModule {
 
 impl Runtime {
     pub fn new() -> Self {
+        return Self::with_sandbox(Sandbox::default());
+    }
+
+    pub fn with_sandbox(sandbox: Sandbox) -> Self {
         let string_pool = StringPool::new();
         let reference_pool = ObjectPool::new();
 
-        return Self {
+        let errors = ErrorPrototypes::new(&string_pool, &reference_pool);
+
+        let globals = reference_pool.new_object().unwrap();
+        {
+            let globals = globals.get();
+            let mut values = globals.values.write().unwrap();
+
+            for kind in ErrorKind::ALL {
+                values.insert(
+                    string_pool.acquire(kind.name().into()).unwrap(),
+                    RwLock::new(Property::Value(Value::Object(errors.get(kind).clone()))),
+                );
+            }
+        }
+
+        let array_prototype = reference_pool.new_object().unwrap();
+        let string_prototype = reference_pool.new_object().unwrap();
+
+        let runtime = Self {
             string_pool,
             object_pool: reference_pool,
             module_cache: RwLock::new(HashMap::new()),
+            globals,
+            errors,
+            array_prototype,
+            string_prototype,
+            sandbox,
+            clock: Box::new(SystemClock::new()),
+            args: vec![],
+            env_overrides: RwLock::new(HashMap::new()),
+        };
+
+        array::install_prototype(&runtime);
+        string::install_prototype(&runtime);
+        modules::install(&runtime);
+
+        return runtime;
+    }
+
+    /// Replaces the clock, like with a `ManualClock` to control the time scripts see
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        return self;
+    }
+
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        return self;
+    }
+
+    /// The environment variable as scripts see it, with their own changes
+    pub fn env_var(&self, name: &str) -> Option<String> {
+        return match self.env_overrides.read().unwrap().get(name) {
+            Some(value) => value.clone(),
+            None => std::env::var(name).ok(),
         };
     }
 
     pub fn create_native_module(&self, name: Arc<str>, value: Value) -> Arc<Module> {
+        let name = self.string_pool.acquire(name).unwrap();
         let mut modules = self.module_cache.write().unwrap();
 
-        return modules
-            .insert(
-                self.string_pool.acquire(name).unwrap(),
-                Arc::new(Module {
-                    bytecode: None,
-                    export: value,
-                }),
-            )
-            .unwrap();
+        modules.insert(
+            name.clone(),
+            Arc::new(Module {
+                bytecode: None,
+                export: value,
+            }),
+        );
+
+        return modules[&name].clone();
+    }
+
+    pub fn new_string(&self, value: &str) -> Value {
+        return Value::String(self.string_pool.acquire(value.into()).unwrap());
     }
 }
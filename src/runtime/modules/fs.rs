@@ -0,0 +1,692 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::UNIX_EPOCH,
+};
+
+use crate::runtime::{
+    error::{insert_value, ErrorKind},
+    value::{object_pool::MarkChildren, NativeValue, Value},
+    Runtime,
+};
+
+use super::{
+    insert_function, integer_param, new_object, optional_bool_param, param_error, string_param,
+    with_native,
+};
+
+/// Creates the export of `#fs`. Every path goes through the sandbox, so it can't leave the root directory if one is set
+pub fn create(runtime: &Runtime) -> Value {
+    let module = runtime.object_pool.new_object().unwrap();
+
+    insert_function(runtime, &module, "readToString", read_to_string);
+    insert_function(runtime, &module, "readBytes", read_bytes);
+    insert_function(runtime, &module, "writeString", write_string);
+    insert_function(runtime, &module, "writeBytes", write_bytes);
+    insert_function(runtime, &module, "appendString", append_string);
+    insert_function(runtime, &module, "exists", exists);
+    insert_function(runtime, &module, "stat", stat);
+    insert_function(runtime, &module, "readDir", read_dir);
+    insert_function(runtime, &module, "createDir", create_dir);
+    insert_function(runtime, &module, "removeDir", remove_dir);
+    insert_function(runtime, &module, "removeFile", remove_file);
+    insert_function(runtime, &module, "rename", rename);
+
+    let handle_prototype = runtime.object_pool.new_object().unwrap();
+    insert_function(runtime, &handle_prototype, "readLine", handle_read_line);
+    insert_function(
+        runtime,
+        &handle_prototype,
+        "readToString",
+        handle_read_to_string,
+    );
+    insert_function(runtime, &handle_prototype, "readBytes", handle_read_bytes);
+    insert_function(runtime, &handle_prototype, "write", handle_write);
+    insert_function(runtime, &handle_prototype, "flush", handle_flush);
+    insert_function(runtime, &handle_prototype, "close", handle_close);
+
+    insert_function(
+        runtime,
+        &module,
+        "open",
+        move |runtime, _this_obj, params| {
+            let (path, resolved) = resolve(&runtime, params, 0, "open")?;
+
+            let mut options = OpenOptions::new();
+            match params.get(1) {
+                None | Some(Value::None) => options.read(true),
+                Some(Value::String(mode)) => match mode.get().as_ref() {
+                    "r" => options.read(true),
+                    "r+" => options.read(true).write(true),
+                    "w" => options.write(true).create(true).truncate(true),
+                    "w+" => options.read(true).write(true).create(true).truncate(true),
+                    "a" => options.append(true).create(true),
+                    "a+" => options.read(true).append(true).create(true),
+                    _ => {
+                        return Err(param_error(
+                            &runtime,
+                            1,
+                            "open",
+                            "a mode of r, r+, w, w+, a or a+",
+                        ))
+                    }
+                },
+                _ => return Err(param_error(&runtime, 1, "open", "a mode string")),
+            };
+
+            let file = options
+                .open(&resolved)
+                .map_err(|err| io_error(&runtime, &path, err))?;
+
+            let handle = FileHandle {
+                path: path.clone(),
+                file: Mutex::new(Some(BufReader::new(file))),
+            };
+
+            let obj = runtime
+                .object_pool
+                .new_native_object_prototype(Arc::new(handle), handle_prototype.clone())
+                .unwrap();
+            insert_value(
+                &runtime.string_pool,
+                &obj,
+                "path",
+                runtime.new_string(&path),
+            );
+
+            return Ok(Value::Object(obj));
+        },
+    );
+
+    return Value::Object(module);
+}
+
+/// An `IOError` with the path and a `code` like `NotFound`, so scripts can tell failures apart
+pub(super) fn fs_error(runtime: &Runtime, path: &str, code: &str, message: &str) -> Value {
+    let error = runtime.new_error(ErrorKind::IOError, &format!("{}: {}", path, message));
+
+    if let Value::Object(obj) = &error {
+        insert_value(&runtime.string_pool, obj, "code", runtime.new_string(code));
+        insert_value(&runtime.string_pool, obj, "path", runtime.new_string(path));
+    }
+
+    return error;
+}
+
+pub(super) fn io_error(runtime: &Runtime, path: &str, err: io::Error) -> Value {
+    return fs_error(
+        runtime,
+        path,
+        &format!("{:?}", err.kind()),
+        &err.to_string(),
+    );
+}
+
+/// The path parameter at the index as given, and where it is on disk
+fn resolve(
+    runtime: &Runtime,
+    params: &[Value],
+    index: usize,
+    function: &str,
+) -> Result<(Arc<str>, PathBuf), Value> {
+    let path = string_param(runtime, params, index, function)?;
+
+    return match runtime.sandbox.resolve_path(&path) {
+        Some(resolved) => Ok((path, resolved)),
+        None => Err(fs_error(
+            runtime,
+            &path,
+            "PermissionDenied",
+            "outside of the sandbox",
+        )),
+    };
+}
+
+/// Like `resolve`, but refuses the sandbox root itself, which a script mustn't remove or move
+fn resolve_below_root(
+    runtime: &Runtime,
+    params: &[Value],
+    index: usize,
+    function: &str,
+) -> Result<(Arc<str>, PathBuf), Value> {
+    let (path, resolved) = resolve(runtime, params, index, function)?;
+
+    if let Some(root) = &runtime.sandbox.fs_root
+        && resolved == *root
+    {
+        return Err(fs_error(
+            runtime,
+            &path,
+            "PermissionDenied",
+            "the root of the sandbox",
+        ));
+    }
+
+    return Ok((path, resolved));
+}
+
+fn bytes_to_array(runtime: &Runtime, bytes: Vec<u8>) -> Value {
+    return runtime.new_array(
+        bytes
+            .into_iter()
+            .map(|it| Value::Integer(it as isize))
+            .collect(),
+    );
+}
+
+fn array_to_bytes(
+    runtime: &Runtime,
+    params: &[Value],
+    index: usize,
+    function: &str,
+) -> Result<Vec<u8>, Value> {
+    let error = || param_error(runtime, index, function, "an array of bytes");
+
+    let values = params
+        .get(index)
+        .and_then(|it| runtime.array_values(it))
+        .ok_or_else(error)?;
+
+    return values
+        .iter()
+        .map(|it| match it {
+            Value::Integer(byte) => u8::try_from(*byte).map_err(|_| error()),
+            _ => Err(error()),
+        })
+        .collect();
+}
+
+fn read_to_string(
+    runtime: Arc<Runtime>,
+    _this_obj: &Value,
+    params: &[Value],
+) -> Result<Value, Value> {
+    let (path, resolved) = resolve(&runtime, params, 0, "readToString")?;
+
+    let text = fs::read_to_string(resolved).map_err(|err| io_error(&runtime, &path, err))?;
+
+    return Ok(runtime.new_string(&text));
+}
+
+fn read_bytes(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let (path, resolved) = resolve(&runtime, params, 0, "readBytes")?;
+
+    let bytes = fs::read(resolved).map_err(|err| io_error(&runtime, &path, err))?;
+
+    return Ok(bytes_to_array(&runtime, bytes));
+}
+
+fn write_string(
+    runtime: Arc<Runtime>,
+    _this_obj: &Value,
+    params: &[Value],
+) -> Result<Value, Value> {
+    let (path, resolved) = resolve(&runtime, params, 0, "writeString")?;
+    let text = string_param(&runtime, params, 1, "writeString")?;
+
+    fs::write(resolved, text.as_bytes()).map_err(|err| io_error(&runtime, &path, err))?;
+
+    return Ok(Value::None);
+}
+
+fn write_bytes(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let (path, resolved) = resolve(&runtime, params, 0, "writeBytes")?;
+    let bytes = array_to_bytes(&runtime, params, 1, "writeBytes")?;
+
+    fs::write(resolved, bytes).map_err(|err| io_error(&runtime, &path, err))?;
+
+    return Ok(Value::None);
+}
+
+fn append_string(
+    runtime: Arc<Runtime>,
+    _this_obj: &Value,
+    params: &[Value],
+) -> Result<Value, Value> {
+    let (path, resolved) = resolve(&runtime, params, 0, "appendString")?;
+    let text = string_param(&runtime, params, 1, "appendString")?;
+
+    OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(resolved)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|err| io_error(&runtime, &path, err))?;
+
+    return Ok(Value::None);
+}
+
+fn exists(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let (path, resolved) = resolve(&runtime, params, 0, "exists")?;
+
+    let exists = resolved
+        .try_exists()
+        .map_err(|err| io_error(&runtime, &path, err))?;
+
+    return Ok(Value::Boolean(exists));
+}
+
+/// `stat(path)`, an object with the `size`, `isFile`, `isDirectory`, `isSymlink`, `readonly`
+/// and `modified` milliseconds since the unix epoch, or none if the system doesn't know
+fn stat(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let (path, resolved) = resolve(&runtime, params, 0, "stat")?;
+
+    let metadata = fs::metadata(&resolved).map_err(|err| io_error(&runtime, &path, err))?;
+    let is_symlink = fs::symlink_metadata(&resolved).is_ok_and(|it| it.is_symlink());
+
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|it| it.duration_since(UNIX_EPOCH).ok())
+        .map_or(Value::None, |it| Value::Integer(it.as_millis() as isize));
+
+    return Ok(new_object(
+        &runtime,
+        vec![
+            ("size", Value::Integer(metadata.len() as isize)),
+            ("isFile", Value::Boolean(metadata.is_file())),
+            ("isDirectory", Value::Boolean(metadata.is_dir())),
+            ("isSymlink", Value::Boolean(is_symlink)),
+            (
+                "readonly",
+                Value::Boolean(metadata.permissions().readonly()),
+            ),
+            ("modified", modified),
+        ],
+    ));
+}
+
+/// `readDir(path)`, the names in the directory in sorted order
+fn read_dir(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let (path, resolved) = resolve(&runtime, params, 0, "readDir")?;
+
+    let mut names = fs::read_dir(resolved)
+        .and_then(|entries| {
+            entries
+                .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+                .collect::<io::Result<Vec<_>>>()
+        })
+        .map_err(|err| io_error(&runtime, &path, err))?;
+    names.sort();
+
+    return Ok(runtime.new_array(names.iter().map(|it| runtime.new_string(it)).collect()));
+}
+
+/// `createDir(path, recursive)`, which also creates the missing parents if `recursive` is true
+fn create_dir(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let (path, resolved) = resolve(&runtime, params, 0, "createDir")?;
+    let recursive = optional_bool_param(&runtime, params, 1, "createDir", false)?;
+
+    let result = match recursive {
+        true => fs::create_dir_all(resolved),
+        false => fs::create_dir(resolved),
+    };
+    result.map_err(|err| io_error(&runtime, &path, err))?;
+
+    return Ok(Value::None);
+}
+
+/// `removeDir(path, recursive)`, which only removes empty directories unless `recursive` is true
+fn remove_dir(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let (path, resolved) = resolve_below_root(&runtime, params, 0, "removeDir")?;
+    let recursive = optional_bool_param(&runtime, params, 1, "removeDir", false)?;
+
+    let result = match recursive {
+        true => fs::remove_dir_all(resolved),
+        false => fs::remove_dir(resolved),
+    };
+    result.map_err(|err| io_error(&runtime, &path, err))?;
+
+    return Ok(Value::None);
+}
+
+fn remove_file(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let (path, resolved) = resolve(&runtime, params, 0, "removeFile")?;
+
+    fs::remove_file(resolved).map_err(|err| io_error(&runtime, &path, err))?;
+
+    return Ok(Value::None);
+}
+
+fn rename(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let (from, resolved_from) = resolve_below_root(&runtime, params, 0, "rename")?;
+    let (_, resolved_to) = resolve_below_root(&runtime, params, 1, "rename")?;
+
+    fs::rename(resolved_from, resolved_to).map_err(|err| io_error(&runtime, &from, err))?;
+
+    return Ok(Value::None);
+}
+
+/// A file opened with `open`, closed by `close` or once the object is collected
+struct FileHandle {
+    path: Arc<str>,
+    file: Mutex<Option<BufReader<File>>>,
+}
+
+impl NativeValue for FileHandle {
+    fn mark_children(&self, _marker: &mut MarkChildren) {}
+
+    fn cleanup(&self) {
+        self.file.lock().unwrap().take();
+    }
+}
+
+impl FileHandle {
+    fn with_file<R>(
+        &self,
+        runtime: &Runtime,
+        f: impl FnOnce(&mut BufReader<File>) -> io::Result<R>,
+    ) -> Result<R, Value> {
+        let mut file = self.file.lock().unwrap();
+
+        let Some(file) = file.as_mut() else {
+            return Err(fs_error(
+                runtime,
+                &self.path,
+                "Closed",
+                "the file is closed",
+            ));
+        };
+
+        return f(file).map_err(|err| io_error(runtime, &self.path, err));
+    }
+}
+
+/// `readLine()`, the next line without it's line ending, or none at the end of the file
+fn handle_read_line(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    _params: &[Value],
+) -> Result<Value, Value> {
+    let line = with_native(&runtime, this_obj, "file", |handle: &FileHandle| {
+        handle.with_file(&runtime, |file| {
+            let mut line = String::new();
+            let read = file.read_line(&mut line)?;
+
+            return Ok((read > 0).then_some(line));
+        })
+    })?;
+
+    let Some(line) = line else {
+        return Ok(Value::None);
+    };
+
+    let line = line.strip_suffix('\n').unwrap_or(&line);
+    let line = line.strip_suffix('\r').unwrap_or(line);
+
+    return Ok(runtime.new_string(line));
+}
+
+/// `readToString()`, everything left in the file
+fn handle_read_to_string(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    _params: &[Value],
+) -> Result<Value, Value> {
+    let text = with_native(&runtime, this_obj, "file", |handle: &FileHandle| {
+        handle.with_file(&runtime, |file| {
+            let mut text = String::new();
+            file.read_to_string(&mut text)?;
+
+            return Ok(text);
+        })
+    })?;
+
+    return Ok(runtime.new_string(&text));
+}
+
+/// `readBytes(count)`, at most `count` bytes, which is less only at the end of the file
+fn handle_read_bytes(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    params: &[Value],
+) -> Result<Value, Value> {
+    let count = integer_param(&runtime, params, 0, "readBytes")?;
+
+    let bytes = with_native(&runtime, this_obj, "file", |handle: &FileHandle| {
+        handle.with_file(&runtime, |file| {
+            let mut bytes = vec![];
+            file.take(count.max(0) as u64).read_to_end(&mut bytes)?;
+
+            return Ok(bytes);
+        })
+    })?;
+
+    return Ok(bytes_to_array(&runtime, bytes));
+}
+
+/// `write(value)`, with either a string or an array of bytes
+fn handle_write(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let bytes = match params.first() {
+        Some(Value::String(text)) => text.get().as_bytes().to_vec(),
+        _ => array_to_bytes(&runtime, params, 0, "write")?,
+    };
+
+    with_native(&runtime, this_obj, "file", |handle: &FileHandle| {
+        handle.with_file(&runtime, |file| {
+            // Seeking drops what was read ahead, so the write lands where the script expects
+            let position = file.stream_position()?;
+            file.seek(SeekFrom::Start(position))?;
+            return file.get_mut().write_all(&bytes);
+        })
+    })?;
+
+    return Ok(Value::None);
+}
+
+fn handle_flush(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    _params: &[Value],
+) -> Result<Value, Value> {
+    with_native(&runtime, this_obj, "file", |handle: &FileHandle| {
+        handle.with_file(&runtime, |file| file.get_mut().flush())
+    })?;
+
+    return Ok(Value::None);
+}
+
+/// `close()`, which does nothing if the file is already closed
+fn handle_close(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    _params: &[Value],
+) -> Result<Value, Value> {
+    with_native(&runtime, this_obj, "file", |handle: &FileHandle| {
+        handle.cleanup();
+        return Ok(());
+    })?;
+
+    return Ok(Value::None);
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::runtime::{
+        modules::{call, property, shared},
+        sandbox::Sandbox,
+        value::Value,
+        Runtime,
+    };
+
+    #[test]
+    fn files_in_sandbox() {
+        let root = std::env::temp_dir().join(format!("burrow-fs-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+
+        let runtime = shared(Runtime::with_sandbox(Sandbox {
+            fs_root: Some(root.clone()),
+            ..Default::default()
+        }));
+        let fs = runtime.native_module("#fs").unwrap();
+        assert!(runtime.native_module("#process").is_none());
+        assert!(runtime.native_module("#os").is_none());
+
+        let path = runtime.new_string("notes/a.txt");
+        let line = |text: &str| runtime.new_string(text);
+
+        let Err(error) = call(
+            &runtime,
+            &fs,
+            "writeString",
+            &[path.clone(), line("first\n")],
+        ) else {
+            panic!("Wrote to a missing directory");
+        };
+        assert!(runtime.is_error(&error));
+        assert_eq!(property(&runtime, &error, "name"), "IOError");
+        assert_eq!(property(&runtime, &error, "code"), "NotFound");
+
+        assert!(call(&runtime, &fs, "createDir", &[line("/notes")]).is_ok());
+        assert!(call(
+            &runtime,
+            &fs,
+            "writeString",
+            &[path.clone(), line("first\n")]
+        )
+        .is_ok());
+        assert!(call(
+            &runtime,
+            &fs,
+            "appendString",
+            &[path.clone(), line("second\r\n")]
+        )
+        .is_ok());
+
+        let text = call(&runtime, &fs, "readToString", std::slice::from_ref(&path))
+            .ok()
+            .unwrap();
+        assert_eq!(text.to_string(), "first\nsecond\r\n");
+        assert_eq!(
+            fs::read_to_string(root.join("notes/a.txt")).unwrap(),
+            text.to_string()
+        );
+
+        let stat = call(&runtime, &fs, "stat", std::slice::from_ref(&path))
+            .ok()
+            .unwrap();
+        assert_eq!(property(&runtime, &stat, "size"), "14");
+        assert_eq!(property(&runtime, &stat, "isFile"), "true");
+
+        let names = call(&runtime, &fs, "readDir", &[line("notes")])
+            .ok()
+            .unwrap();
+        let names = runtime.array_values(&names).unwrap();
+        assert_eq!(
+            names.iter().map(|it| it.to_string()).collect::<Vec<_>>(),
+            ["a.txt"]
+        );
+
+        // Handles read line by line, and throw once they're closed
+        let handle = call(&runtime, &fs, "open", std::slice::from_ref(&path))
+            .ok()
+            .unwrap();
+        for expected in ["first", "second", "none"] {
+            let line = call(&runtime, &handle, "readLine", &[]).ok().unwrap();
+            assert_eq!(line.to_string(), expected);
+        }
+        assert!(call(&runtime, &handle, "close", &[]).is_ok());
+        assert!(call(&runtime, &handle, "readLine", &[]).is_err());
+
+        let bytes = runtime.new_array(vec![Value::Integer(0), Value::Integer(255)]);
+        assert!(call(&runtime, &fs, "writeBytes", &[line("notes/b.bin"), bytes]).is_ok());
+        let bytes = call(&runtime, &fs, "readBytes", &[line("notes/b.bin")])
+            .ok()
+            .unwrap();
+        assert!(matches!(
+            runtime.array_values(&bytes).unwrap().as_slice(),
+            [Value::Integer(0), Value::Integer(255)]
+        ));
+
+        assert!(call(
+            &runtime,
+            &fs,
+            "rename",
+            &[path.clone(), line("notes/c.txt")]
+        )
+        .is_ok());
+        let exists = call(&runtime, &fs, "exists", std::slice::from_ref(&path))
+            .ok()
+            .unwrap();
+        assert!(matches!(exists, Value::Boolean(false)));
+
+        // Nothing outside of the root can be reached
+        let Err(error) = call(&runtime, &fs, "readToString", &[line("notes/../../secret")]) else {
+            panic!("Read outside of the sandbox");
+        };
+        assert_eq!(property(&runtime, &error, "code"), "PermissionDenied");
+
+        assert!(call(&runtime, &fs, "removeDir", &[line("notes")]).is_err());
+        for root_path in ["", ".", "/", "notes/.."] {
+            let Err(error) = call(
+                &runtime,
+                &fs,
+                "removeDir",
+                &[line(root_path), Value::Boolean(true)],
+            ) else {
+                panic!("Removed the root as {:?}", root_path);
+            };
+            assert_eq!(property(&runtime, &error, "code"), "PermissionDenied");
+        }
+        assert!(call(&runtime, &fs, "rename", &[line("."), line("moved")]).is_err());
+        assert!(call(&runtime, &fs, "rename", &[line("notes"), line("/")]).is_err());
+        assert!(root.join("notes/c.txt").exists());
+        assert!(call(
+            &runtime,
+            &fs,
+            "removeDir",
+            &[line("notes"), Value::Boolean(true)]
+        )
+        .is_ok());
+        assert!(!root.join("notes").exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dangling_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let dir = std::env::temp_dir().join(format!("burrow-links-{}", std::process::id()));
+        let root = dir.join("root");
+        fs::create_dir_all(&root).unwrap();
+
+        // Neither target exists yet, so only following the links shows where they go
+        symlink(dir.join("outside.txt"), root.join("escape")).unwrap();
+        symlink("../root/inside.txt", root.join("stay")).unwrap();
+
+        let runtime = shared(Runtime::with_sandbox(Sandbox {
+            fs_root: Some(root.clone()),
+            ..Default::default()
+        }));
+        let fs = runtime.native_module("#fs").unwrap();
+
+        let text = runtime.new_string("text");
+        let Err(error) = call(
+            &runtime,
+            &fs,
+            "writeString",
+            &[runtime.new_string("escape"), text.clone()],
+        ) else {
+            panic!("Wrote through a symlink out of the sandbox");
+        };
+        assert_eq!(property(&runtime, &error, "code"), "PermissionDenied");
+        assert!(!dir.join("outside.txt").exists());
+
+        assert!(call(
+            &runtime,
+            &fs,
+            "writeString",
+            &[runtime.new_string("stay"), text]
+        )
+        .is_ok());
+        assert!(root.join("inside.txt").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,728 @@
+use std::sync::Arc;
+
+use crate::runtime::{
+    error::{insert_value, ErrorKind},
+    value::{object_pool::ObjectReference, Value},
+    Runtime,
+};
+
+use super::{insert_function, own_values, param_error, string_param};
+
+/// How deep arrays and objects can nest, so a hostile document can't overflow the stack
+const MAX_DEPTH: usize = 256;
+
+/// Creates the export of `#json`
+pub fn create(runtime: &Runtime) -> Value {
+    let module = runtime.object_pool.new_object().unwrap();
+
+    insert_function(runtime, &module, "parse", parse);
+    insert_function(runtime, &module, "stringify", stringify);
+
+    return Value::Object(module);
+}
+
+/// `parse(text, reviver)`, converts JSON text to values. Integers stay integers when they fit.
+/// The reviver is called as `reviver(key, value)` from the innermost values out, and what it returns replaces the value
+fn parse(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let text = string_param(&runtime, params, 0, "parse")?;
+
+    let mut parser = Parser {
+        runtime: &runtime,
+        text: &text,
+        offset: 0,
+    };
+
+    parser.skip_whitespace();
+    let value = parser.parse_value(0)?;
+    parser.skip_whitespace();
+
+    if parser.offset < text.len() {
+        return Err(parser.error("Unexpected text after the value"));
+    }
+
+    return match params.get(1) {
+        None | Some(Value::None) => Ok(value),
+        Some(reviver) => revive(&runtime, reviver, runtime.new_string(""), value, 0),
+    };
+}
+
+fn revive(
+    runtime: &Arc<Runtime>,
+    reviver: &Value,
+    key: Value,
+    value: Value,
+    depth: usize,
+) -> Result<Value, Value> {
+    if depth > MAX_DEPTH {
+        return Err(too_deep(runtime));
+    }
+
+    if let Some(values) = runtime.array_values(&value) {
+        let mut revived = vec![];
+        for (index, child) in values.into_iter().enumerate() {
+            revived.push(revive(
+                runtime,
+                reviver,
+                Value::Integer(index as isize),
+                child,
+                depth + 1,
+            )?);
+        }
+        let value = runtime.new_array(revived);
+
+        return reviver.invoke(runtime.clone(), &Value::None, &[key, value]);
+    }
+
+    if let Value::Object(obj) = &value {
+        let entries = own_values(obj);
+        for (name, child) in entries {
+            let revived = revive(
+                runtime,
+                reviver,
+                runtime.new_string(&name),
+                child,
+                depth + 1,
+            )?;
+            insert_value(&runtime.string_pool, obj, &name, revived);
+        }
+    }
+
+    return reviver.invoke(runtime.clone(), &Value::None, &[key, value]);
+}
+
+/// A `RangeError` for values nested deeper than `MAX_DEPTH`, which would overflow the stack
+fn too_deep(runtime: &Runtime) -> Value {
+    return runtime.new_error(ErrorKind::RangeError, "The value is nested too deeply");
+}
+
+struct Parser<'a> {
+    runtime: &'a Runtime,
+    text: &'a str,
+    offset: usize,
+}
+
+impl Parser<'_> {
+    /// A `SyntaxError` with the `line` and `column` the parser stopped at
+    fn error(&self, message: &str) -> Value {
+        let before = &self.text[..self.offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|it| *it != '\n').count() + 1;
+
+        let error = self.runtime.new_error(
+            ErrorKind::SyntaxError,
+            &format!("{} at {}:{}", message, line, column),
+        );
+
+        if let Value::Object(obj) = &error {
+            insert_value(
+                &self.runtime.string_pool,
+                obj,
+                "line",
+                Value::Integer(line as isize),
+            );
+            insert_value(
+                &self.runtime.string_pool,
+                obj,
+                "column",
+                Value::Integer(column as isize),
+            );
+        }
+
+        return error;
+    }
+
+    fn peek(&self) -> Option<char> {
+        return self.text[self.offset..].chars().next();
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let char = self.peek()?;
+        self.offset += char.len_utf8();
+
+        return Some(char);
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Value> {
+        if self.peek() != Some(expected) {
+            return Err(self.unexpected());
+        }
+        self.offset += expected.len_utf8();
+
+        return Ok(());
+    }
+
+    fn unexpected(&self) -> Value {
+        return match self.peek() {
+            Some(char) => self.error(&format!("Unexpected character {:?}", char)),
+            None => self.error("Unexpected end of the text"),
+        };
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.offset += 1;
+        }
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<Value, Value> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("The value is nested too deeply"));
+        }
+
+        return match self.peek() {
+            Some('{') => self.parse_object(depth),
+            Some('[') => self.parse_array(depth),
+            Some('"') => Ok(self.runtime.new_string(&self.parse_string()?)),
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some('t') => self.parse_keyword("true", Value::Boolean(true)),
+            Some('f') => self.parse_keyword("false", Value::Boolean(false)),
+            Some('n') => self.parse_keyword("null", Value::None),
+            _ => Err(self.unexpected()),
+        };
+    }
+
+    fn parse_keyword(&mut self, keyword: &str, value: Value) -> Result<Value, Value> {
+        if !self.text[self.offset..].starts_with(keyword) {
+            return Err(self.unexpected());
+        }
+        self.offset += keyword.len();
+
+        return Ok(value);
+    }
+
+    fn parse_object(&mut self, depth: usize) -> Result<Value, Value> {
+        self.expect('{')?;
+        let obj = self.runtime.object_pool.new_object().unwrap();
+
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.offset += 1;
+            return Ok(Value::Object(obj));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.unexpected());
+            }
+            let key = self.parse_string()?;
+
+            self.skip_whitespace();
+            self.expect(':')?;
+            self.skip_whitespace();
+
+            let value = self.parse_value(depth + 1)?;
+            insert_value(&self.runtime.string_pool, &obj, &key, value);
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.offset += 1,
+                Some('}') => {
+                    self.offset += 1;
+                    return Ok(Value::Object(obj));
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn parse_array(&mut self, depth: usize) -> Result<Value, Value> {
+        self.expect('[')?;
+        let mut values = vec![];
+
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.offset += 1;
+            return Ok(self.runtime.new_array(values));
+        }
+
+        loop {
+            self.skip_whitespace();
+            values.push(self.parse_value(depth + 1)?);
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.offset += 1,
+                Some(']') => {
+                    self.offset += 1;
+                    return Ok(self.runtime.new_array(values));
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, Value> {
+        self.expect('"')?;
+        let mut str = String::new();
+
+        loop {
+            let start = self.offset;
+            match self.next() {
+                Some('"') => return Ok(str),
+                Some('\\') => {
+                    let escaped = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.parse_unicode_escape(start)?,
+                        _ => {
+                            self.offset = start;
+                            return Err(self.error("Invalid escape"));
+                        }
+                    };
+                    str.push(escaped);
+                }
+                Some(char) if char < ' ' => {
+                    self.offset = start;
+                    return Err(self.error("Control characters must be escaped in strings"));
+                }
+                Some(char) => str.push(char),
+                None => return Err(self.error("Unterminated string")),
+            }
+        }
+    }
+
+    /// The character of a `\u` escape, which combines surrogate pairs
+    fn parse_unicode_escape(&mut self, start: usize) -> Result<char, Value> {
+        let high = self.parse_hex(start)?;
+
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.text[self.offset..].starts_with("\\u") {
+                self.offset = start;
+                return Err(self.error("Unpaired surrogate"));
+            }
+            self.offset += 2;
+
+            let low = self.parse_hex(start)?;
+            if !(0xDC00..0xE000).contains(&low) {
+                self.offset = start;
+                return Err(self.error("Unpaired surrogate"));
+            }
+
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+
+        return char::from_u32(code).ok_or_else(|| {
+            self.offset = start;
+            self.error("Unpaired surrogate")
+        });
+    }
+
+    fn parse_hex(&mut self, start: usize) -> Result<u32, Value> {
+        let digits = self.text.get(self.offset..self.offset + 4).unwrap_or("");
+
+        // from_str_radix would also take a sign
+        let Some(code) = u32::from_str_radix(digits, 16)
+            .ok()
+            .filter(|_| digits.bytes().all(|it| it.is_ascii_hexdigit()))
+        else {
+            self.offset = start;
+            return Err(self.error("Invalid unicode escape"));
+        };
+        self.offset += 4;
+
+        return Ok(code);
+    }
+
+    fn parse_number(&mut self) -> Result<Value, Value> {
+        let start = self.offset;
+        let digits = |parser: &mut Self| {
+            let from = parser.offset;
+            while let Some('0'..='9') = parser.peek() {
+                parser.offset += 1;
+            }
+
+            return parser.offset > from;
+        };
+
+        if self.peek() == Some('-') {
+            self.offset += 1;
+        }
+
+        if self.peek() == Some('0') {
+            self.offset += 1;
+        } else if !digits(self) {
+            return Err(self.unexpected());
+        }
+
+        let mut is_float = false;
+
+        if self.peek() == Some('.') {
+            self.offset += 1;
+            is_float = true;
+            if !digits(self) {
+                return Err(self.unexpected());
+            }
+        }
+
+        if let Some('e' | 'E') = self.peek() {
+            self.offset += 1;
+            is_float = true;
+            if let Some('+' | '-') = self.peek() {
+                self.offset += 1;
+            }
+            if !digits(self) {
+                return Err(self.unexpected());
+            }
+        }
+
+        let number = &self.text[start..self.offset];
+
+        // Integers too big for an integer become floats, like they would in most other languages
+        if !is_float && let Ok(value) = number.parse::<isize>() {
+            return Ok(Value::Integer(value));
+        }
+
+        return Ok(Value::Float(number.parse::<f32>().unwrap()));
+    }
+}
+
+/// `stringify(value, options)`, converts the value to JSON text. The options can have
+/// - `indent`, a number of spaces or a string to pretty print with
+/// - `replacer`, called as `replacer(key, value)` before each value is converted, and what it returns is used instead
+///
+/// Functions and accessors are left out of objects, and become null in arrays. Cycles throw a `TypeError`
+fn stringify(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let value = params.first().cloned().unwrap_or(Value::None);
+
+    let mut indent = String::new();
+    let mut replacer = None;
+
+    match params.get(1) {
+        None | Some(Value::None) => {}
+        Some(options @ Value::Object(_)) => {
+            let get = |name: &str| options.get_property(runtime.clone(), &runtime.new_string(name));
+
+            indent = match get("indent")? {
+                Value::Uninitialized | Value::None => String::new(),
+                Value::Integer(spaces) => " ".repeat(spaces.clamp(0, 10) as usize),
+                Value::String(indent) => indent.to_string(),
+                _ => {
+                    return Err(param_error(
+                        &runtime,
+                        1,
+                        "stringify",
+                        "an indent of a number or string",
+                    ))
+                }
+            };
+
+            replacer = match get("replacer")? {
+                Value::Uninitialized | Value::None => None,
+                replacer => Some(replacer),
+            };
+        }
+        _ => {
+            return Err(param_error(
+                &runtime,
+                1,
+                "stringify",
+                "an object of options",
+            ))
+        }
+    }
+
+    let mut writer = Writer {
+        runtime: &runtime,
+        indent,
+        replacer,
+        visiting: vec![],
+        out: String::new(),
+    };
+
+    let value = writer.replace(runtime.new_string(""), value)?;
+    writer.write(&value, 0)?;
+
+    return Ok(runtime.new_string(&writer.out));
+}
+
+struct Writer<'a> {
+    runtime: &'a Arc<Runtime>,
+    indent: String,
+    replacer: Option<Value>,
+    /// The objects and arrays being written, to find cycles
+    visiting: Vec<ObjectReference>,
+    out: String,
+}
+
+impl Writer<'_> {
+    fn replace(&self, key: Value, value: Value) -> Result<Value, Value> {
+        return match &self.replacer {
+            Some(replacer) => replacer.invoke(self.runtime.clone(), &Value::None, &[key, value]),
+            None => Ok(value),
+        };
+    }
+
+    fn is_function(&self, value: &Value) -> bool {
+        let Value::Object(obj) = value else {
+            return false;
+        };
+
+        let native_value = obj.get().native_value.read().unwrap().clone();
+        return native_value.is_some_and(|it| it.has_invoker(self.runtime.clone()));
+    }
+
+    fn newline(&mut self, depth: usize) {
+        if !self.indent.is_empty() {
+            self.out.push('\n');
+            self.out.push_str(&self.indent.repeat(depth));
+        }
+    }
+
+    fn write(&mut self, value: &Value, depth: usize) -> Result<(), Value> {
+        match value {
+            Value::String(str) => write_string(&mut self.out, &str.get()),
+            Value::Integer(value) => self.out.push_str(&value.to_string()),
+            Value::Float(value) if !value.is_finite() => self.out.push_str("null"),
+            Value::Float(value) => {
+                let number = value.to_string();
+                self.out.push_str(&number);
+                // Keeps it a float when it's parsed again
+                if !number.contains(['.', 'e']) {
+                    self.out.push_str(".0");
+                }
+            }
+            Value::Boolean(value) => self.out.push_str(&value.to_string()),
+            Value::None | Value::Uninitialized => self.out.push_str("null"),
+            Value::Object(_) if self.is_function(value) => self.out.push_str("null"),
+            Value::Object(_) if depth > MAX_DEPTH => return Err(too_deep(self.runtime)),
+            Value::Object(obj) => {
+                if self.visiting.contains(obj) {
+                    return Err(self.runtime.new_error(
+                        ErrorKind::TypeError,
+                        "Cannot convert a value that contains itself to JSON",
+                    ));
+                }
+                self.visiting.push(obj.clone());
+
+                match self.runtime.array_values(value) {
+                    Some(values) => self.write_array(values, depth)?,
+                    None => self.write_object(obj, depth)?,
+                }
+
+                self.visiting.pop();
+            }
+        }
+
+        return Ok(());
+    }
+
+    fn write_array(&mut self, values: Vec<Value>, depth: usize) -> Result<(), Value> {
+        if values.is_empty() {
+            self.out.push_str("[]");
+            return Ok(());
+        }
+
+        self.out.push('[');
+        for (index, value) in values.into_iter().enumerate() {
+            if index > 0 {
+                self.out.push(',');
+            }
+            self.newline(depth + 1);
+
+            let value = self.replace(Value::Integer(index as isize), value)?;
+            self.write(&value, depth + 1)?;
+        }
+        self.newline(depth);
+        self.out.push(']');
+
+        return Ok(());
+    }
+
+    fn write_object(&mut self, obj: &ObjectReference, depth: usize) -> Result<(), Value> {
+        let mut first = true;
+
+        self.out.push('{');
+        for (name, value) in own_values(obj) {
+            let value = self.replace(self.runtime.new_string(&name), value)?;
+            if self.is_function(&value) {
+                continue;
+            }
+
+            if !first {
+                self.out.push(',');
+            }
+            first = false;
+            self.newline(depth + 1);
+
+            write_string(&mut self.out, &name);
+            self.out.push(':');
+            if !self.indent.is_empty() {
+                self.out.push(' ');
+            }
+            self.write(&value, depth + 1)?;
+        }
+
+        if !first {
+            self.newline(depth);
+        }
+        self.out.push('}');
+
+        return Ok(());
+    }
+}
+
+fn write_string(out: &mut String, str: &str) {
+    out.push('"');
+    for char in str.chars() {
+        match char {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            char if char < ' ' => out.push_str(&format!("\\u{:04x}", char as u32)),
+            char => out.push(char),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::runtime::{
+        modules::{call, insert_function, new_object, property, shared},
+        value::Value,
+        Runtime,
+    };
+
+    #[test]
+    fn roundtrip() {
+        let runtime = shared(Runtime::new());
+        let json = runtime.native_module("#json").unwrap();
+
+        let text = runtime.new_string(
+            r#"{"name": "caf\u00e9 \ud83d\ude00", "count": 3, "ratio": 0.5, "big": 1e3, "tags": ["a", null, true], "empty": {}}"#,
+        );
+        let value = call(&runtime, &json, "parse", &[text]).ok().unwrap();
+        assert_eq!(property(&runtime, &value, "name"), "café 😀");
+        assert!(matches!(
+            value.get_property(runtime.clone(), &runtime.new_string("count")),
+            Ok(Value::Integer(3))
+        ));
+
+        let options = new_object(&runtime, vec![("indent", Value::Integer(2))]);
+        let pretty = call(&runtime, &json, "stringify", &[value.clone(), options])
+            .ok()
+            .unwrap();
+        assert_eq!(
+            pretty.to_string(),
+            "{\n  \"name\": \"café 😀\",\n  \"count\": 3,\n  \"ratio\": 0.5,\n  \"big\": 1000.0,\n  \"tags\": [\n    \"a\",\n    null,\n    true\n  ],\n  \"empty\": {}\n}"
+        );
+
+        // The replacer sees every key, and what it returns is written instead
+        let options = runtime.object_pool.new_object().unwrap();
+        insert_function(
+            &runtime,
+            &options,
+            "replacer",
+            |runtime, _, params| match params {
+                [Value::String(key), value] if key.get().as_ref() == "tags" => {
+                    Ok(runtime.new_string(&value.to_string()))
+                }
+                [_, value] => Ok(value.clone()),
+                _ => Ok(Value::None),
+            },
+        );
+        let compact = call(
+            &runtime,
+            &json,
+            "stringify",
+            &[value.clone(), Value::Object(options)],
+        )
+        .ok()
+        .unwrap();
+        assert_eq!(
+            compact.to_string(),
+            r#"{"name":"café 😀","count":3,"ratio":0.5,"big":1000.0,"tags":"[object]","empty":{}}"#
+        );
+
+        // The reviver sees the innermost values first
+        let double = Value::Object(
+            runtime
+                .object_pool
+                .new_native_object(Arc::new(|_: Arc<Runtime>, _: &Value, params: &[Value]| {
+                    return match params {
+                        [_, Value::Integer(value)] => Ok(Value::Integer(value * 2)),
+                        [_, value] => Ok(value.clone()),
+                        _ => Ok(Value::None),
+                    };
+                }))
+                .unwrap(),
+        );
+        let revived = call(
+            &runtime,
+            &json,
+            "parse",
+            &[runtime.new_string("[1, [2]]"), double],
+        )
+        .ok()
+        .unwrap();
+        let revived = call(&runtime, &json, "stringify", &[revived]).ok().unwrap();
+        assert_eq!(revived.to_string(), "[2,[4]]");
+
+        let Value::Object(obj) = &value else {
+            panic!("Expected an object");
+        };
+        assert!(obj
+            .set_property(
+                runtime.clone(),
+                &value,
+                &runtime.new_string("empty"),
+                &value
+            )
+            .is_ok());
+        let Err(error) = call(&runtime, &json, "stringify", std::slice::from_ref(&value)) else {
+            panic!("Converted a cycle");
+        };
+        assert_eq!(property(&runtime, &error, "name"), "TypeError");
+
+        let mut nested = Value::None;
+        for _ in 0..300 {
+            nested = runtime.new_array(vec![nested]);
+        }
+        let Err(error) = call(&runtime, &json, "stringify", &[nested]) else {
+            panic!("Converted a value nested too deeply");
+        };
+        assert_eq!(property(&runtime, &error, "name"), "RangeError");
+    }
+
+    #[test]
+    fn parse_errors() {
+        let runtime = shared(Runtime::new());
+        let json = runtime.native_module("#json").unwrap();
+
+        for (text, line, column) in [
+            ("{\n  \"a\": 1,\n  \"b\" 2\n}", "3", "7"),
+            ("[1, 2", "1", "6"),
+            ("\"\\x\"", "1", "2"),
+            ("01", "1", "2"),
+            ("\"\\u+041\"", "1", "2"),
+        ] {
+            let Err(error) = call(&runtime, &json, "parse", &[runtime.new_string(text)]) else {
+                panic!("Parsed {:?}", text);
+            };
+
+            assert_eq!(property(&runtime, &error, "name"), "SyntaxError");
+            assert_eq!(
+                (
+                    property(&runtime, &error, "line").as_str(),
+                    property(&runtime, &error, "column").as_str()
+                ),
+                (line, column),
+                "{}",
+                text
+            );
+        }
+    }
+}
@@ -0,0 +1,601 @@
+use std::{cmp::Ordering, sync::Arc};
+
+use crate::runtime::{
+    error::{insert_value, ErrorKind},
+    value::{object_pool::ObjectReference, Value},
+    Runtime,
+};
+
+use super::{insert_function, integer_param, param_error, string_param};
+
+/// Creates the export of `#math`.
+///
+/// Integers and floats mix by the same rules everywhere:
+/// - Functions that keep the type, like `abs`, `min` and `clamp`, return an integer only if every number given is one
+/// - Rounding functions return integers, unless the float is NaN, infinite or too big for an integer
+/// - Everything else works on floats, and integers given to it are converted
+/// - Integer helpers like `div` and the bit operations only take integers, and throw a `RangeError` instead of overflowing
+pub fn create(runtime: &Runtime) -> Value {
+    let module = runtime.object_pool.new_object().unwrap();
+
+    // The same values the `Infinity` and `NaN` keywords push
+    for (name, value) in [
+        ("pi", std::f32::consts::PI),
+        ("tau", std::f32::consts::TAU),
+        ("e", std::f32::consts::E),
+        ("Infinity", f32::INFINITY),
+        ("NaN", f32::NAN),
+    ] {
+        insert_value(&runtime.string_pool, &module, name, Value::Float(value));
+    }
+    insert_value(
+        &runtime.string_pool,
+        &module,
+        "max_int",
+        Value::Integer(isize::MAX),
+    );
+    insert_value(
+        &runtime.string_pool,
+        &module,
+        "min_int",
+        Value::Integer(isize::MIN),
+    );
+
+    insert_float(runtime, &module, "sqrt", f32::sqrt);
+    insert_float(runtime, &module, "cbrt", f32::cbrt);
+    insert_float(runtime, &module, "exp", f32::exp);
+    insert_float(runtime, &module, "ln", f32::ln);
+    insert_float(runtime, &module, "log2", f32::log2);
+    insert_float(runtime, &module, "log10", f32::log10);
+    insert_float(runtime, &module, "sin", f32::sin);
+    insert_float(runtime, &module, "cos", f32::cos);
+    insert_float(runtime, &module, "tan", f32::tan);
+    insert_float(runtime, &module, "asin", f32::asin);
+    insert_float(runtime, &module, "acos", f32::acos);
+    insert_float(runtime, &module, "atan", f32::atan);
+    insert_float(runtime, &module, "sinh", f32::sinh);
+    insert_float(runtime, &module, "cosh", f32::cosh);
+    insert_float(runtime, &module, "tanh", f32::tanh);
+    insert_function(runtime, &module, "pow", pow);
+    insert_function(runtime, &module, "log", log);
+    insert_function(runtime, &module, "atan2", atan2);
+    insert_function(runtime, &module, "hypot", hypot);
+
+    insert_rounding(runtime, &module, "floor", f32::floor);
+    insert_rounding(runtime, &module, "ceil", f32::ceil);
+    insert_rounding(runtime, &module, "round", f32::round);
+    insert_rounding(runtime, &module, "trunc", f32::trunc);
+
+    insert_function(runtime, &module, "abs", abs);
+    insert_function(runtime, &module, "sign", sign);
+    insert_function(runtime, &module, "min", min);
+    insert_function(runtime, &module, "max", max);
+    insert_function(runtime, &module, "clamp", clamp);
+
+    insert_function(runtime, &module, "div", div);
+    insert_function(runtime, &module, "mod", modulo);
+    insert_function(runtime, &module, "gcd", gcd);
+    insert_function(runtime, &module, "band", |runtime, _, params| {
+        let (lhs, rhs) = integer_pair(&runtime, params, "band")?;
+        return Ok(Value::Integer(lhs & rhs));
+    });
+    insert_function(runtime, &module, "bor", |runtime, _, params| {
+        let (lhs, rhs) = integer_pair(&runtime, params, "bor")?;
+        return Ok(Value::Integer(lhs | rhs));
+    });
+    insert_function(runtime, &module, "bxor", |runtime, _, params| {
+        let (lhs, rhs) = integer_pair(&runtime, params, "bxor")?;
+        return Ok(Value::Integer(lhs ^ rhs));
+    });
+    insert_function(runtime, &module, "bnot", |runtime, _, params| {
+        return Ok(Value::Integer(!integer_param(&runtime, params, 0, "bnot")?));
+    });
+    insert_function(runtime, &module, "shl", shl);
+    insert_function(runtime, &module, "shr", shr);
+
+    insert_function(runtime, &module, "to_int", to_int);
+    insert_function(runtime, &module, "to_float", to_float);
+    insert_function(runtime, &module, "parse_number", parse_number);
+
+    return Value::Object(module);
+}
+
+/// An integer or float parameter as a float
+fn number_param(
+    runtime: &Runtime,
+    params: &[Value],
+    index: usize,
+    function: &str,
+) -> Result<f32, Value> {
+    return match params.get(index) {
+        Some(Value::Integer(value)) => Ok(*value as f32),
+        Some(Value::Float(value)) => Ok(*value),
+        _ => Err(param_error(runtime, index, function, "a number")),
+    };
+}
+
+fn integer_pair(
+    runtime: &Runtime,
+    params: &[Value],
+    function: &str,
+) -> Result<(isize, isize), Value> {
+    return Ok((
+        integer_param(runtime, params, 0, function)?,
+        integer_param(runtime, params, 1, function)?,
+    ));
+}
+
+fn range_error(runtime: &Runtime, message: &str) -> Value {
+    return runtime.new_error(ErrorKind::RangeError, message);
+}
+
+/// The float as an integer if it's whole and fits
+fn float_to_integer(value: f32) -> Option<isize> {
+    // isize::MAX rounds up to 2^63 as a float, which doesn't fit
+    if value.fract() != 0.0 || !(isize::MIN as f32..isize::MAX as f32).contains(&value) {
+        return None;
+    }
+
+    return Some(value as isize);
+}
+
+/// Adds a function of one float
+fn insert_float(
+    runtime: &Runtime,
+    module: &ObjectReference,
+    name: &'static str,
+    f: fn(f32) -> f32,
+) {
+    insert_function(runtime, module, name, move |runtime, _, params| {
+        return Ok(Value::Float(f(number_param(&runtime, params, 0, name)?)));
+    });
+}
+
+/// Adds a rounding function, integers are already whole so they're returned as they are
+fn insert_rounding(
+    runtime: &Runtime,
+    module: &ObjectReference,
+    name: &'static str,
+    f: fn(f32) -> f32,
+) {
+    insert_function(runtime, module, name, move |runtime, _, params| {
+        return match params.first() {
+            Some(Value::Integer(value)) => Ok(Value::Integer(*value)),
+            Some(Value::Float(value)) => {
+                let rounded = f(*value);
+                Ok(float_to_integer(rounded).map_or(Value::Float(rounded), Value::Integer))
+            }
+            _ => Err(param_error(&runtime, 0, name, "a number")),
+        };
+    });
+}
+
+/// `pow(base, exponent)`, stays an integer for integers with a positive exponent, unless it overflows
+fn pow(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    if let [Value::Integer(base), Value::Integer(exponent), ..] = params
+        && let Ok(exponent) = u32::try_from(*exponent)
+        && let Some(value) = base.checked_pow(exponent)
+    {
+        return Ok(Value::Integer(value));
+    }
+
+    let base = number_param(&runtime, params, 0, "pow")?;
+    let exponent = number_param(&runtime, params, 1, "pow")?;
+
+    return Ok(Value::Float(base.powf(exponent)));
+}
+
+/// `log(value, base)`, the natural logarithm without a base
+fn log(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let value = number_param(&runtime, params, 0, "log")?;
+
+    return match params.get(1) {
+        None | Some(Value::None) => Ok(Value::Float(value.ln())),
+        _ => Ok(Value::Float(
+            value.log(number_param(&runtime, params, 1, "log")?),
+        )),
+    };
+}
+
+fn atan2(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let y = number_param(&runtime, params, 0, "atan2")?;
+    let x = number_param(&runtime, params, 1, "atan2")?;
+
+    return Ok(Value::Float(y.atan2(x)));
+}
+
+fn hypot(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let x = number_param(&runtime, params, 0, "hypot")?;
+    let y = number_param(&runtime, params, 1, "hypot")?;
+
+    return Ok(Value::Float(x.hypot(y)));
+}
+
+fn abs(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    return match params.first() {
+        Some(Value::Integer(value)) => value.checked_abs().map(Value::Integer).ok_or_else(|| {
+            range_error(
+                &runtime,
+                "abs of the smallest integer doesn't fit in an integer",
+            )
+        }),
+        Some(Value::Float(value)) => Ok(Value::Float(value.abs())),
+        _ => Err(param_error(&runtime, 0, "abs", "a number")),
+    };
+}
+
+/// `sign(value)`, -1, 0 or 1 of the same type. NaN stays NaN
+fn sign(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    return match params.first() {
+        Some(Value::Integer(value)) => Ok(Value::Integer(value.signum())),
+        Some(Value::Float(value)) if *value == 0.0 || value.is_nan() => Ok(Value::Float(*value)),
+        Some(Value::Float(value)) => Ok(Value::Float(value.signum())),
+        _ => Err(param_error(&runtime, 0, "sign", "a number")),
+    };
+}
+
+/// The numbers `min` and `max` pick from, either the parameters or a single array of them
+fn numbers(runtime: &Runtime, params: &[Value], function: &str) -> Result<Vec<Value>, Value> {
+    let values = match params {
+        [single] => runtime
+            .array_values(single)
+            .unwrap_or_else(|| params.to_vec()),
+        _ => params.to_vec(),
+    };
+
+    if values.is_empty() {
+        return Err(param_error(runtime, 0, function, "at least one number"));
+    }
+
+    for (index, value) in values.iter().enumerate() {
+        if !matches!(value, Value::Integer(_) | Value::Float(_)) {
+            return Err(param_error(runtime, index, function, "a number"));
+        }
+    }
+
+    return Ok(values);
+}
+
+/// Picks the number that compares as `wanted` to all others, as an integer only if every number is one. NaN wins over everything
+fn pick_number(values: Vec<Value>, wanted: Ordering) -> Value {
+    let integers = values
+        .iter()
+        .map(|it| match it {
+            Value::Integer(value) => Some(*value),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>();
+
+    if let Some(integers) = integers {
+        let mut best = integers[0];
+        for value in integers {
+            if value.cmp(&best) == wanted {
+                best = value;
+            }
+        }
+        return Value::Integer(best);
+    }
+
+    let mut best = f32::NAN;
+    for (index, value) in values.iter().enumerate() {
+        let value = match value {
+            Value::Integer(value) => *value as f32,
+            Value::Float(value) => *value,
+            _ => unreachable!(),
+        };
+
+        if value.is_nan() {
+            return Value::Float(f32::NAN);
+        }
+        if index == 0 || value.partial_cmp(&best) == Some(wanted) {
+            best = value;
+        }
+    }
+
+    return Value::Float(best);
+}
+
+fn min(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    return Ok(pick_number(
+        numbers(&runtime, params, "min")?,
+        Ordering::Less,
+    ));
+}
+
+fn max(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    return Ok(pick_number(
+        numbers(&runtime, params, "max")?,
+        Ordering::Greater,
+    ));
+}
+
+/// `clamp(value, min, max)`, which throws a `RangeError` if `min` is greater than `max`
+fn clamp(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    if let [Value::Integer(value), Value::Integer(min), Value::Integer(max), ..] = params {
+        if min > max {
+            return Err(range_error(&runtime, "clamp expects min to be at most max"));
+        }
+        return Ok(Value::Integer(*value.clamp(min, max)));
+    }
+
+    let value = number_param(&runtime, params, 0, "clamp")?;
+    let min = number_param(&runtime, params, 1, "clamp")?;
+    let max = number_param(&runtime, params, 2, "clamp")?;
+
+    if min > max || min.is_nan() || max.is_nan() {
+        return Err(range_error(&runtime, "clamp expects min to be at most max"));
+    }
+
+    return Ok(Value::Float(value.clamp(min, max)));
+}
+
+/// `div(lhs, rhs)`, integer division rounding down, so `div(-7, 2)` is -4
+fn div(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let (lhs, rhs) = integer_pair(&runtime, params, "div")?;
+
+    if rhs == 0 {
+        return Err(range_error(&runtime, "Division by zero"));
+    }
+
+    let Some(quotient) = lhs.checked_div(rhs) else {
+        return Err(range_error(
+            &runtime,
+            "The quotient doesn't fit in an integer",
+        ));
+    };
+
+    let rounds_down = lhs % rhs != 0 && (lhs < 0) != (rhs < 0);
+    return Ok(Value::Integer(quotient - rounds_down as isize));
+}
+
+/// `mod(lhs, rhs)`, the remainder of `div`, which has the sign of `rhs`
+fn modulo(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let (lhs, rhs) = integer_pair(&runtime, params, "mod")?;
+
+    if rhs == 0 {
+        return Err(range_error(&runtime, "Division by zero"));
+    }
+
+    let remainder = lhs.checked_rem(rhs).unwrap_or(0);
+    if remainder != 0 && (remainder < 0) != (rhs < 0) {
+        return Ok(Value::Integer(remainder + rhs));
+    }
+
+    return Ok(Value::Integer(remainder));
+}
+
+fn gcd(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let (lhs, rhs) = integer_pair(&runtime, params, "gcd")?;
+
+    let (mut lhs, mut rhs) = (lhs.unsigned_abs(), rhs.unsigned_abs());
+    while rhs != 0 {
+        (lhs, rhs) = (rhs, lhs % rhs);
+    }
+
+    return isize::try_from(lhs)
+        .map(Value::Integer)
+        .map_err(|_| range_error(&runtime, "The divisor doesn't fit in an integer"));
+}
+
+fn shift_param(runtime: &Runtime, params: &[Value], function: &str) -> Result<(isize, u32), Value> {
+    let (value, shift) = integer_pair(runtime, params, function)?;
+
+    return match u32::try_from(shift) {
+        Ok(shift) if shift < isize::BITS => Ok((value, shift)),
+        _ => Err(range_error(
+            runtime,
+            &format!("{} expects a shift from 0 to {}", function, isize::BITS - 1),
+        )),
+    };
+}
+
+/// `shl(value, shift)`, which throws a `RangeError` if bits that matter are shifted out
+fn shl(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let (value, shift) = shift_param(&runtime, params, "shl")?;
+
+    let shifted = value << shift;
+    if shifted >> shift != value {
+        return Err(range_error(
+            &runtime,
+            "The shifted value doesn't fit in an integer",
+        ));
+    }
+
+    return Ok(Value::Integer(shifted));
+}
+
+/// `shr(value, shift)`, an arithmetic shift, so negative values stay negative
+fn shr(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let (value, shift) = shift_param(&runtime, params, "shr")?;
+
+    return Ok(Value::Integer(value >> shift));
+}
+
+/// Parses an integer, or a float if it isn't one. `Infinity` and `NaN` are spelled like the keywords
+fn parse(str: &str) -> Option<Value> {
+    let str = str.trim();
+
+    if let Ok(value) = str.parse::<isize>() {
+        return Some(Value::Integer(value));
+    }
+
+    return match str {
+        "Infinity" | "+Infinity" => Some(Value::Float(f32::INFINITY)),
+        "-Infinity" => Some(Value::Float(f32::NEG_INFINITY)),
+        "NaN" => Some(Value::Float(f32::NAN)),
+        // Rust would also take spellings like `inf`, which the language doesn't have
+        _ if str.contains(|it: char| it.is_ascii_alphabetic() && it != 'e' && it != 'E') => None,
+        _ => str.parse::<f32>().ok().map(Value::Float),
+    };
+}
+
+/// `parse_number(str)`, throws a `SyntaxError` if it isn't a number
+fn parse_number(
+    runtime: Arc<Runtime>,
+    _this_obj: &Value,
+    params: &[Value],
+) -> Result<Value, Value> {
+    let str = string_param(&runtime, params, 0, "parse_number")?;
+
+    return parse(&str).ok_or_else(|| {
+        runtime.new_error(
+            ErrorKind::SyntaxError,
+            &format!("{:?} is not a number", str),
+        )
+    });
+}
+
+/// `to_int(value)`, truncates floats and parses strings, throwing a `RangeError` if the result isn't a whole integer
+fn to_int(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let value = match params.first() {
+        Some(Value::String(str)) => {
+            parse_number(runtime.clone(), &Value::None, &[Value::String(str.clone())])?
+        }
+        Some(value) => value.clone(),
+        None => Value::None,
+    };
+
+    return match value {
+        Value::Integer(value) => Ok(Value::Integer(value)),
+        Value::Boolean(value) => Ok(Value::Integer(value as isize)),
+        Value::Float(value) => float_to_integer(value.trunc())
+            .map(Value::Integer)
+            .ok_or_else(|| range_error(&runtime, &format!("{} doesn't fit in an integer", value))),
+        _ => Err(param_error(
+            &runtime,
+            0,
+            "to_int",
+            "a number, string or boolean",
+        )),
+    };
+}
+
+fn to_float(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let value = match params.first() {
+        Some(Value::String(str)) => {
+            parse_number(runtime.clone(), &Value::None, &[Value::String(str.clone())])?
+        }
+        Some(value) => value.clone(),
+        None => Value::None,
+    };
+
+    return match value {
+        Value::Integer(value) => Ok(Value::Float(value as f32)),
+        Value::Float(value) => Ok(Value::Float(value)),
+        Value::Boolean(value) => Ok(Value::Float(value as u8 as f32)),
+        _ => Err(param_error(
+            &runtime,
+            0,
+            "to_float",
+            "a number, string or boolean",
+        )),
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use crate::runtime::{
+        modules::{self, shared},
+        value::Value,
+        Runtime,
+    };
+
+    #[test]
+    fn number_mixing() {
+        let runtime = shared(Runtime::new());
+        let math = runtime.native_module("#math").unwrap();
+
+        let call = |name: &str, params: &[Value]| modules::call(&runtime, &math, name, params);
+        let int = Value::Integer;
+        let float = Value::Float;
+
+        assert!(matches!(call("sqrt", &[int(16)]), Ok(Value::Float(4.0))));
+        assert!(matches!(
+            call("floor", &[float(-2.5)]),
+            Ok(Value::Integer(-3))
+        ));
+        assert!(matches!(
+            call("round", &[float(f32::INFINITY)]),
+            Ok(Value::Float(f32::INFINITY))
+        ));
+        assert!(matches!(
+            call("pow", &[int(2), int(10)]),
+            Ok(Value::Integer(1024))
+        ));
+        assert!(matches!(
+            call("pow", &[int(2), int(-1)]),
+            Ok(Value::Float(0.5))
+        ));
+
+        assert!(matches!(
+            call("min", &[int(3), int(1)]),
+            Ok(Value::Integer(1))
+        ));
+        assert!(matches!(
+            call("min", &[int(3), float(1.5)]),
+            Ok(Value::Float(1.5))
+        ));
+        let values = runtime.new_array(vec![int(4), int(9), int(2)]);
+        assert!(matches!(call("max", &[values]), Ok(Value::Integer(9))));
+        assert!(
+            matches!(call("max", &[int(1), float(f32::NAN)]), Ok(Value::Float(value)) if value.is_nan())
+        );
+        assert!(matches!(
+            call("clamp", &[int(12), int(0), int(10)]),
+            Ok(Value::Integer(10))
+        ));
+        assert!(call("clamp", &[int(1), int(10), int(0)]).is_err());
+
+        assert!(matches!(
+            call("div", &[int(-7), int(2)]),
+            Ok(Value::Integer(-4))
+        ));
+        assert!(matches!(
+            call("mod", &[int(-7), int(2)]),
+            Ok(Value::Integer(1))
+        ));
+        assert!(call("div", &[int(1), int(0)]).is_err());
+        assert!(call("div", &[int(isize::MIN), int(-1)]).is_err());
+        assert!(call("abs", &[int(isize::MIN)]).is_err());
+        assert!(matches!(
+            call("gcd", &[int(-12), int(18)]),
+            Ok(Value::Integer(6))
+        ));
+        assert!(matches!(
+            call("shr", &[int(-16), int(2)]),
+            Ok(Value::Integer(-4))
+        ));
+        assert!(call("shl", &[int(1), int(64)]).is_err());
+        assert!(matches!(
+            call("shl", &[int(-1), int(63)]),
+            Ok(Value::Integer(isize::MIN))
+        ));
+        assert!(call("shl", &[int(1), int(63)]).is_err());
+        assert!(call("shl", &[int(3), int(62)]).is_err());
+
+        assert!(matches!(
+            call("parse_number", &[runtime.new_string(" 42 ")]),
+            Ok(Value::Integer(42))
+        ));
+        assert!(matches!(
+            call("parse_number", &[runtime.new_string("2.5e1")]),
+            Ok(Value::Float(25.0))
+        ));
+        assert!(call("parse_number", &[runtime.new_string("inf")]).is_err());
+        assert!(matches!(
+            call("to_int", &[float(-3.9)]),
+            Ok(Value::Integer(-3))
+        ));
+        assert!(call("to_int", &[float(f32::NAN)]).is_err());
+        assert!(matches!(
+            call("to_float", &[runtime.new_string("7")]),
+            Ok(Value::Float(7.0))
+        ));
+
+        let infinity = math
+            .get_property(runtime.clone(), &runtime.new_string("Infinity"))
+            .ok()
+            .unwrap();
+        assert!(matches!(infinity, Value::Float(f32::INFINITY)));
+    }
+}
@@ -0,0 +1,197 @@
+use std::{any::Any, sync::Arc};
+
+use super::{
+    error::{insert_value, ErrorKind},
+    value::{
+        object_pool::{ObjectReference, Property},
+        NativeValue, Value,
+    },
+    Runtime,
+};
+
+pub mod fs;
+pub mod json;
+pub mod math;
+pub mod os;
+pub mod path;
+pub mod process;
+pub mod random;
+pub mod regex;
+pub mod time;
+
+/// Creates the export of a native module
+type CreateModule = fn(&Runtime) -> Value;
+
+/// Registers the native modules scripts can import with `from "#name"`, except the ones the sandbox denies
+pub(super) fn install(runtime: &Runtime) {
+    let modules: [(&str, CreateModule); 9] = [
+        ("#fs", fs::create),
+        ("#json", json::create),
+        ("#math", math::create),
+        ("#random", random::create),
+        ("#time", time::create),
+        ("#os", os::create),
+        ("#process", process::create),
+        ("#path", path::create),
+        ("#regex", regex::create),
+    ];
+
+    for (name, create) in modules {
+        if runtime.sandbox.allows_module(name) {
+            runtime.create_native_module(name.into(), create(runtime));
+        }
+    }
+}
+
+impl Runtime {
+    /// The export of a native module, or none if the sandbox denied it
+    pub fn native_module(&self, name: &str) -> Option<Value> {
+        let name = self.string_pool.acquire(name.into()).unwrap();
+
+        return self
+            .module_cache
+            .read()
+            .unwrap()
+            .get(&name)
+            .map(|it| it.export.clone());
+    }
+}
+
+/// The plain value properties of the object in order, accessors aren't data so they're left out
+pub fn own_values(obj: &ObjectReference) -> Vec<(Arc<str>, Value)> {
+    return obj
+        .get()
+        .values
+        .read()
+        .unwrap()
+        .iter()
+        .filter_map(|(name, prop)| match &*prop.read().unwrap() {
+            Property::Value(Value::Uninitialized) | Property::GetSet { .. } => None,
+            Property::Value(value) => Some((name.get(), value.clone())),
+        })
+        .collect();
+}
+
+/// Adds a native function as a property of the object
+pub fn insert_function<TFn>(runtime: &Runtime, obj: &ObjectReference, name: &str, f: TFn)
+where
+    TFn: Fn(Arc<Runtime>, &Value, &[Value]) -> Result<Value, Value> + 'static,
+{
+    let function = runtime.object_pool.new_native_object(Arc::new(f)).unwrap();
+    insert_value(&runtime.string_pool, obj, name, Value::Object(function));
+}
+
+/// Creates a plain object with the properties in order
+pub fn new_object(runtime: &Runtime, properties: Vec<(&str, Value)>) -> Value {
+    let obj = runtime.object_pool.new_object().unwrap();
+    for (name, value) in properties {
+        insert_value(&runtime.string_pool, &obj, name, value);
+    }
+
+    return Value::Object(obj);
+}
+
+/// The string parameter at the index, throwing a `TypeError` naming the function otherwise
+pub fn string_param(
+    runtime: &Runtime,
+    params: &[Value],
+    index: usize,
+    function: &str,
+) -> Result<Arc<str>, Value> {
+    return match params.get(index) {
+        Some(Value::String(value)) => Ok(value.get()),
+        _ => Err(param_error(runtime, index, function, "a string")),
+    };
+}
+
+pub fn integer_param(
+    runtime: &Runtime,
+    params: &[Value],
+    index: usize,
+    function: &str,
+) -> Result<isize, Value> {
+    return match params.get(index) {
+        Some(Value::Integer(value)) => Ok(*value),
+        _ => Err(param_error(runtime, index, function, "an integer")),
+    };
+}
+
+/// A boolean parameter that can be left out or none
+pub fn optional_bool_param(
+    runtime: &Runtime,
+    params: &[Value],
+    index: usize,
+    function: &str,
+    default: bool,
+) -> Result<bool, Value> {
+    return match params.get(index) {
+        None | Some(Value::None) => Ok(default),
+        Some(Value::Boolean(value)) => Ok(*value),
+        _ => Err(param_error(runtime, index, function, "a boolean")),
+    };
+}
+
+pub fn param_error(runtime: &Runtime, index: usize, function: &str, expected: &str) -> Value {
+    return runtime.new_error(
+        ErrorKind::TypeError,
+        &format!(
+            "{} expects {} as parameter {}",
+            function,
+            expected,
+            index + 1
+        ),
+    );
+}
+
+/// Runs the function with the native value of `this`, throwing a `TypeError` if it isn't a `T`
+pub fn with_native<T: NativeValue, R>(
+    runtime: &Runtime,
+    this_obj: &Value,
+    type_name: &str,
+    f: impl FnOnce(&T) -> Result<R, Value>,
+) -> Result<R, Value> {
+    let native_value = match this_obj {
+        Value::Object(obj) => obj.get().native_value.read().unwrap().clone(),
+        _ => None,
+    };
+
+    return match native_value
+        .as_deref()
+        .and_then(|it| (it as &dyn Any).downcast_ref::<T>())
+    {
+        Some(value) => f(value),
+        None => Err(runtime.new_error(
+            ErrorKind::TypeError,
+            &format!("Expected this to be a {}", type_name),
+        )),
+    };
+}
+
+/// Shares the runtime like embedders do, so native functions can be called with it
+#[cfg(test)]
+#[allow(clippy::arc_with_non_send_sync)]
+pub fn shared(runtime: Runtime) -> Arc<Runtime> {
+    return Arc::new(runtime);
+}
+
+/// Calls the method of the value, the way `value.name(params)` would
+#[cfg(test)]
+pub fn call(
+    runtime: &Arc<Runtime>,
+    value: &Value,
+    name: &str,
+    params: &[Value],
+) -> Result<Value, Value> {
+    let function = value.get_property(runtime.clone(), &runtime.new_string(name))?;
+    return function.invoke(runtime.clone(), value, params);
+}
+
+/// The property of the value as a string
+#[cfg(test)]
+pub fn property(runtime: &Arc<Runtime>, value: &Value, name: &str) -> String {
+    let Ok(property) = value.get_property(runtime.clone(), &runtime.new_string(name)) else {
+        panic!("Couldn't get {}", name);
+    };
+
+    return property.to_string();
+}
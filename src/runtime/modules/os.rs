@@ -0,0 +1,192 @@
+use std::{env, sync::Arc};
+
+use crate::runtime::{
+    error::{insert_value, ErrorKind},
+    value::Value,
+    Runtime,
+};
+
+use super::{fs::io_error, insert_function, new_object, param_error, string_param};
+
+/// Creates the export of `#os`. Environment variables set by scripts stay in the runtime, see `Runtime::env_overrides`
+pub fn create(runtime: &Runtime) -> Value {
+    let module = runtime.object_pool.new_object().unwrap();
+
+    insert_value(
+        &runtime.string_pool,
+        &module,
+        "platform",
+        runtime.new_string(env::consts::OS),
+    );
+    insert_value(
+        &runtime.string_pool,
+        &module,
+        "arch",
+        runtime.new_string(env::consts::ARCH),
+    );
+
+    insert_function(runtime, &module, "args", args);
+    insert_function(runtime, &module, "get_env", get_env);
+    insert_function(runtime, &module, "set_env", set_env);
+    insert_function(runtime, &module, "env", env_vars);
+    insert_function(runtime, &module, "cwd", cwd);
+    insert_function(runtime, &module, "exit", exit);
+
+    return Value::Object(module);
+}
+
+/// `args()`, the arguments given to the script
+fn args(runtime: Arc<Runtime>, _this_obj: &Value, _params: &[Value]) -> Result<Value, Value> {
+    let args = runtime
+        .args
+        .iter()
+        .map(|it| runtime.new_string(it))
+        .collect();
+
+    return Ok(runtime.new_array(args));
+}
+
+/// `get_env(name)`, the value of the environment variable, or none if it isn't set
+fn get_env(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let name = string_param(&runtime, params, 0, "get_env")?;
+
+    return Ok(match runtime.env_var(&name) {
+        Some(value) => runtime.new_string(&value),
+        None => Value::None,
+    });
+}
+
+/// `set_env(name, value)`, where a value of none removes the variable
+fn set_env(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let name = string_param(&runtime, params, 0, "set_env")?;
+    let value = match params.get(1) {
+        None | Some(Value::None) => None,
+        _ => Some(string_param(&runtime, params, 1, "set_env")?.to_string()),
+    };
+
+    if name.is_empty() || name.contains(['=', '\0']) {
+        return Err(runtime.new_error(
+            ErrorKind::TypeError,
+            &format!("{:?} isn't a valid environment variable name", name),
+        ));
+    }
+
+    runtime
+        .env_overrides
+        .write()
+        .unwrap()
+        .insert(name.to_string(), value);
+
+    return Ok(Value::None);
+}
+
+/// `env()`, every environment variable as an object
+fn env_vars(runtime: Arc<Runtime>, _this_obj: &Value, _params: &[Value]) -> Result<Value, Value> {
+    let mut vars = env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .collect::<Vec<_>>();
+
+    for (name, value) in runtime.env_overrides.read().unwrap().iter() {
+        vars.retain(|(existing, _)| existing != name);
+        if let Some(value) = value {
+            vars.push((name.clone(), value.clone()));
+        }
+    }
+    vars.sort();
+
+    let vars = vars
+        .iter()
+        .map(|(name, value)| (name.as_str(), runtime.new_string(value)))
+        .collect();
+
+    return Ok(new_object(&runtime, vars));
+}
+
+/// `cwd()`, the working directory of the process
+fn cwd(runtime: Arc<Runtime>, _this_obj: &Value, _params: &[Value]) -> Result<Value, Value> {
+    return match env::current_dir() {
+        Ok(path) => Ok(runtime.new_string(&path.to_string_lossy())),
+        Err(err) => Err(io_error(&runtime, ".", err)),
+    };
+}
+
+/// `exit(code)`, ends the script with the code, 0 if it's left out. It throws a value catch blocks can't handle,
+/// see `Runtime::new_exit`, so finally blocks run and the host decides how to end the process
+fn exit(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let code = match params.first() {
+        None | Some(Value::None) => 0,
+        Some(Value::Integer(code @ 0..=255)) => *code as i32,
+        Some(Value::Integer(_)) => {
+            return Err(
+                runtime.new_error(ErrorKind::RangeError, "exit expects a code from 0 to 255")
+            );
+        }
+        _ => return Err(param_error(&runtime, 0, "exit", "an integer")),
+    };
+
+    return Err(runtime.new_exit(code));
+}
+
+#[cfg(test)]
+mod test {
+    use crate::runtime::{
+        modules::{self, shared},
+        sandbox::Sandbox,
+        value::Value,
+        Runtime,
+    };
+
+    #[test]
+    fn args_and_env() {
+        let runtime = shared(
+            Runtime::with_sandbox(Sandbox {
+                denied_modules: ["#fs".to_string()].into(),
+                allowed_modules: ["#os".to_string()].into(),
+                ..Default::default()
+            })
+            .with_args(vec!["build".into(), "--release".into()]),
+        );
+
+        assert!(runtime.native_module("#fs").is_none());
+        assert!(runtime.native_module("#process").is_none());
+        let os = runtime.native_module("#os").unwrap();
+
+        let call = |name: &str, params: &[Value]| modules::call(&runtime, &os, name, params);
+
+        let args = call("args", &[]).ok().unwrap();
+        let args = runtime.array_values(&args).unwrap();
+        assert!(
+            matches!(&args[..], [Value::String(first), Value::String(_)] if first.get().as_ref() == "build")
+        );
+
+        let name = runtime.new_string("BURROW_OS_TEST");
+        assert!(matches!(
+            call("get_env", std::slice::from_ref(&name)),
+            Ok(Value::None)
+        ));
+        call("set_env", &[name.clone(), runtime.new_string("yes")])
+            .ok()
+            .unwrap();
+        assert!(
+            matches!(call("get_env", std::slice::from_ref(&name)), Ok(Value::String(value)) if value.get().as_ref() == "yes")
+        );
+        assert!(std::env::var("BURROW_OS_TEST").is_err());
+
+        let vars = call("env", &[]).ok().unwrap();
+        assert!(matches!(
+            vars.get_property(runtime.clone(), &name),
+            Ok(Value::String(_))
+        ));
+        assert!(call("set_env", &[runtime.new_string("A=B"), Value::None]).is_err());
+
+        let Err(exit) = call("exit", &[Value::Integer(2)]) else {
+            panic!("exit returned");
+        };
+        assert_eq!(runtime.exit_code(&exit), Some(2));
+        let Err(error) = call("exit", &[Value::Integer(256)]) else {
+            panic!("exit returned");
+        };
+        assert!(runtime.is_error(&error));
+        assert_eq!(runtime.exit_code(&error), None);
+    }
+}
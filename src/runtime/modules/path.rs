@@ -0,0 +1,422 @@
+use std::{
+    path::{self, Component, Path, PathBuf, MAIN_SEPARATOR_STR},
+    sync::Arc,
+};
+
+use crate::runtime::{
+    error::{insert_value, ErrorKind},
+    value::Value,
+    Runtime,
+};
+
+use super::{insert_function, param_error, string_param};
+
+/// Creates the export of `#path`. It only works on the text of paths and never touches the file system,
+/// so it's there even when `#fs` is denied. Paths follow the rules of the platform, like `\` being a separator on Windows
+pub fn create(runtime: &Runtime) -> Value {
+    let module = runtime.object_pool.new_object().unwrap();
+
+    insert_value(
+        &runtime.string_pool,
+        &module,
+        "separator",
+        runtime.new_string(MAIN_SEPARATOR_STR),
+    );
+
+    insert_function(runtime, &module, "join", join);
+    insert_function(runtime, &module, "normalize", normalize);
+    insert_function(runtime, &module, "dirname", dirname);
+    insert_function(runtime, &module, "basename", basename);
+    insert_function(runtime, &module, "extension", extension);
+    insert_function(runtime, &module, "with_extension", with_extension);
+    insert_function(runtime, &module, "is_absolute", is_absolute);
+    insert_function(runtime, &module, "relative", relative);
+    insert_function(runtime, &module, "matches", matches);
+
+    return Value::Object(module);
+}
+
+fn path_value(runtime: &Runtime, path: &Path) -> Value {
+    return runtime.new_string(&path.to_string_lossy());
+}
+
+/// `join(paths...)`, joins the paths in order, where an absolute path starts over
+fn join(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let mut path = PathBuf::new();
+    for index in 0..params.len() {
+        path.push(string_param(&runtime, params, index, "join")?.as_ref());
+    }
+
+    return Ok(path_value(&runtime, &path));
+}
+
+/// Removes `.` and resolves `..` without looking at the file system. `..` at the root stays at the root,
+/// and relative paths keep the `..` they start with
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    let mut depth = 0;
+
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => normalized.push(component),
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => {
+                normalized.pop();
+                depth -= 1;
+            }
+            Component::ParentDir if normalized.has_root() => {}
+            Component::ParentDir => normalized.push(component),
+            Component::Normal(part) => {
+                normalized.push(part);
+                depth += 1;
+            }
+        }
+    }
+
+    if normalized.as_os_str().is_empty() {
+        normalized.push(".");
+    }
+
+    return normalized;
+}
+
+/// `normalize(path)`, the shortest path to the same place, like `a/c` for `./a/b/../c/`
+fn normalize(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let path = string_param(&runtime, params, 0, "normalize")?;
+
+    return Ok(path_value(
+        &runtime,
+        &normalize_path(Path::new(path.as_ref())),
+    ));
+}
+
+/// `dirname(path)`, the path without its last part, `.` if that leaves nothing
+fn dirname(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let path = string_param(&runtime, params, 0, "dirname")?;
+    let path = Path::new(path.as_ref());
+
+    return Ok(match path.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => runtime.new_string("."),
+        Some(parent) => path_value(&runtime, parent),
+        // The root is its own parent
+        None if path.has_root() => path_value(&runtime, path),
+        None => runtime.new_string("."),
+    });
+}
+
+/// `basename(path)`, the last part of the path, or none if it ends in the root or `..`
+fn basename(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let path = string_param(&runtime, params, 0, "basename")?;
+
+    return Ok(match Path::new(path.as_ref()).file_name() {
+        Some(name) => runtime.new_string(&name.to_string_lossy()),
+        None => Value::None,
+    });
+}
+
+/// `extension(path)`, the extension without the dot, or none if there isn't one. Names like `.bashrc` don't have one
+fn extension(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let path = string_param(&runtime, params, 0, "extension")?;
+
+    return Ok(match Path::new(path.as_ref()).extension() {
+        Some(extension) => runtime.new_string(&extension.to_string_lossy()),
+        None => Value::None,
+    });
+}
+
+/// `with_extension(path, extension)`, replaces or adds the extension, an empty one removes it
+fn with_extension(
+    runtime: Arc<Runtime>,
+    _this_obj: &Value,
+    params: &[Value],
+) -> Result<Value, Value> {
+    let path = string_param(&runtime, params, 0, "with_extension")?;
+    let extension = string_param(&runtime, params, 1, "with_extension")?;
+
+    let extension = extension.strip_prefix('.').unwrap_or(&extension);
+
+    return Ok(path_value(
+        &runtime,
+        &Path::new(path.as_ref()).with_extension(extension),
+    ));
+}
+
+fn is_absolute(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let path = string_param(&runtime, params, 0, "is_absolute")?;
+
+    return Ok(Value::Boolean(Path::new(path.as_ref()).is_absolute()));
+}
+
+/// `relative(from, to)`, the path that leads from `from` to `to`. Both have to be absolute or both relative
+fn relative(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let from = string_param(&runtime, params, 0, "relative")?;
+    let to = string_param(&runtime, params, 1, "relative")?;
+
+    let from = normalize_path(Path::new(from.as_ref()));
+    let to = normalize_path(Path::new(to.as_ref()));
+
+    if from.is_absolute() != to.is_absolute() {
+        return Err(runtime.new_error(
+            ErrorKind::TypeError,
+            "relative expects both paths to be absolute or both to be relative",
+        ));
+    }
+
+    let from = from
+        .components()
+        .filter(|it| *it != Component::CurDir)
+        .collect::<Vec<_>>();
+    let to = to
+        .components()
+        .filter(|it| *it != Component::CurDir)
+        .collect::<Vec<_>>();
+
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    // Absolute paths on different drives on Windows, or going up past where a relative path starts
+    if (from.first() != to.first()
+        && from
+            .first()
+            .is_some_and(|it| matches!(it, Component::Prefix(_))))
+        || from[common..].contains(&Component::ParentDir)
+    {
+        return Err(runtime.new_error(
+            ErrorKind::Error,
+            &format!(
+                "There's no relative path from {} to {}",
+                from.iter().collect::<PathBuf>().display(),
+                to.iter().collect::<PathBuf>().display()
+            ),
+        ));
+    }
+
+    let mut path = PathBuf::new();
+    for _ in common..from.len() {
+        path.push("..");
+    }
+    for component in &to[common..] {
+        path.push(component);
+    }
+
+    if path.as_os_str().is_empty() {
+        path.push(".");
+    }
+
+    return Ok(path_value(&runtime, &path));
+}
+
+/// The parts of a path between the separators, where an absolute path starts with an empty part
+fn split(path: &str) -> Vec<&str> {
+    return path
+        .split(path::is_separator)
+        .enumerate()
+        .filter(|(index, part)| *index == 0 || !part.is_empty())
+        .map(|(_, part)| part)
+        .collect();
+}
+
+/// Matches a glob pattern against a path, see `matches`. Works like `component_match`, with `**` matching
+/// whole parts the way `*` matches characters
+fn glob_match(pattern: &[&str], path: &[&str]) -> bool {
+    let chars = |parts: &[&str]| {
+        parts
+            .iter()
+            .map(|it| it.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>()
+    };
+    let (pattern, path) = (chars(pattern), chars(path));
+
+    let mut rest = &pattern[..];
+    let mut index = 0;
+    let mut star = None;
+
+    while index < path.len() {
+        match rest {
+            [part, after @ ..] if part[..] == ['*', '*'] => {
+                rest = after;
+                star = Some((after, index));
+                continue;
+            }
+            [part, after @ ..] if component_match(part, &path[index]) => {
+                rest = after;
+                index += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        let Some((after, start)) = star else {
+            return false;
+        };
+        // The last `**` takes one more part, and the pattern after it is tried from there
+        star = Some((after, start + 1));
+        rest = after;
+        index = start + 1;
+    }
+
+    return rest.iter().all(|part| part[..] == ['*', '*']);
+}
+
+/// Matches one part of a pattern against one part of a path. A `*` first matches nothing, and only takes
+/// another character when the rest of the pattern fails, so it never backtracks further than the last `*`
+fn component_match(pattern: &[char], name: &[char]) -> bool {
+    let mut rest = pattern;
+    let mut index = 0;
+    let mut star = None;
+
+    while index < name.len() {
+        if let ['*', after @ ..] = rest {
+            rest = after;
+            star = Some((after, index));
+            continue;
+        }
+
+        if let Some(after) = char_match(rest, name[index]) {
+            rest = after;
+            index += 1;
+            continue;
+        }
+
+        let Some((after, start)) = star else {
+            return false;
+        };
+        star = Some((after, start + 1));
+        rest = after;
+        index = start + 1;
+    }
+
+    return rest.iter().all(|it| *it == '*');
+}
+
+/// Matches a character against the start of a pattern that isn't a `*`, returning the rest of the pattern
+fn char_match(pattern: &[char], char: char) -> Option<&[char]> {
+    return match pattern.split_first()? {
+        ('?', rest) => Some(rest),
+        ('[', rest) => match class_match(rest, Some(&char)) {
+            Some((matched, rest)) => matched.then_some(rest),
+            // Without a closing `]` it's just a character
+            None => (char == '[').then_some(rest),
+        },
+        (expected, rest) => (*expected == char).then_some(rest),
+    };
+}
+
+/// Matches a class like `[a-z]` or `[!0-9]`, starting after the `[`. Returns whether the character matched and
+/// the rest of the pattern, or none if the class isn't closed
+fn class_match<'a>(pattern: &'a [char], char: Option<&char>) -> Option<(bool, &'a [char])> {
+    let (negated, mut pattern) = match pattern.split_first() {
+        Some(('!' | '^', rest)) => (true, rest),
+        _ => (false, pattern),
+    };
+
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        match pattern {
+            // A `]` right at the start is part of the class
+            [']', rest @ ..] if !first => {
+                return Some((char.is_some() && matched != negated, rest));
+            }
+            [start, '-', end, rest @ ..] if *end != ']' => {
+                matched |= char.is_some_and(|it| (start..=end).contains(&it));
+                pattern = rest;
+            }
+            [expected, rest @ ..] => {
+                matched |= char == Some(expected);
+                pattern = rest;
+            }
+            [] => return None,
+        }
+        first = false;
+    }
+}
+
+/// `matches(pattern, path)`, whether the path matches the glob pattern. `*` matches anything within a part
+/// of the path, `?` one character, `[a-z]` and `[!a-z]` one character of a class, and `**` as a part matches any
+/// number of parts, like `src/**/*.rs`
+fn matches(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let pattern = string_param(&runtime, params, 0, "matches")?;
+    let path = match params.get(1) {
+        Some(Value::String(path)) => path.get(),
+        _ => return Err(param_error(&runtime, 1, "matches", "a path string")),
+    };
+
+    return Ok(Value::Boolean(glob_match(&split(&pattern), &split(&path))));
+}
+
+#[cfg(test)]
+mod test {
+    use crate::runtime::{
+        modules::{self, shared},
+        value::Value,
+        Runtime,
+    };
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_paths() {
+        let runtime = shared(Runtime::new());
+        let path = runtime.native_module("#path").unwrap();
+
+        let call = |name: &str, params: &[&str]| {
+            let params = params
+                .iter()
+                .map(|it| runtime.new_string(it))
+                .collect::<Vec<_>>();
+            return modules::call(&runtime, &path, name, &params);
+        };
+        let string = |name: &str, params: &[&str]| match call(name, params) {
+            Ok(Value::String(str)) => str.get().to_string(),
+            Ok(Value::None) => "none".into(),
+            _ => panic!("Expected a string"),
+        };
+
+        assert_eq!(string("join", &["a", "b/", "c.txt"]), "a/b/c.txt");
+        assert_eq!(string("join", &["a", "/etc", "hosts"]), "/etc/hosts");
+        assert_eq!(string("normalize", &["./a/b/../c/"]), "a/c");
+        assert_eq!(string("normalize", &["../../a/.."]), "../..");
+        assert_eq!(string("normalize", &["/.."]), "/");
+        assert_eq!(string("normalize", &["a/.."]), ".");
+        assert_eq!(string("dirname", &["/usr/lib"]), "/usr");
+        assert_eq!(string("dirname", &["file"]), ".");
+        assert_eq!(string("dirname", &["/"]), "/");
+        assert_eq!(string("basename", &["/usr/lib/"]), "lib");
+        assert_eq!(string("basename", &["/"]), "none");
+        assert_eq!(string("extension", &["archive.tar.gz"]), "gz");
+        assert_eq!(string("extension", &[".bashrc"]), "none");
+        assert_eq!(string("with_extension", &["notes.txt", ".md"]), "notes.md");
+        assert_eq!(string("with_extension", &["notes.txt", ""]), "notes");
+        assert!(matches!(
+            call("is_absolute", &["/a"]),
+            Ok(Value::Boolean(true))
+        ));
+
+        assert_eq!(string("relative", &["/a/b/c", "/a/d"]), "../../d");
+        assert_eq!(string("relative", &["a", "a/b/../c"]), "c");
+        assert_eq!(string("relative", &["/a", "/a"]), ".");
+        assert!(call("relative", &["/a", "b"]).is_err());
+        assert!(call("relative", &["../a", "b"]).is_err());
+
+        let glob = |pattern: &str, path: &str| match call("matches", &[pattern, path]) {
+            Ok(Value::Boolean(matched)) => matched,
+            _ => panic!("Expected a boolean"),
+        };
+        assert!(glob("src/**/*.rs", "src/main.rs"));
+        assert!(glob("src/**/*.rs", "src/runtime/modules/path.rs"));
+        assert!(!glob("src/*.rs", "src/runtime/mod.rs"));
+        assert!(glob("file?.[a-c]", "file1.b"));
+        assert!(!glob("file?.[!a-c]", "file1.b"));
+        assert!(glob("[]]x", "]x"));
+        assert!(!glob("/tmp/*", "tmp/a"));
+        assert!(glob("**", "a/b"));
+        assert!(glob("a/**/b/**/c", "a/b/x/b/y/c"));
+        assert!(!glob("a/**/b", "a/x/c"));
+        assert!(glob("*a*b", "xaab"));
+
+        // These would backtrack exponentially if every `*` and `**` was tried at every length
+        let name = "a".repeat(40);
+        assert!(!glob(&format!("{}b", "*a".repeat(20)), &name));
+        let path = vec!["a"; 40].join("/");
+        assert!(!glob(&format!("{}b", "**/a/".repeat(20)), &path));
+    }
+}
@@ -0,0 +1,576 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+};
+
+use crate::runtime::{
+    error::insert_value,
+    value::{object_pool::MarkChildren, NativeValue, Value},
+    Runtime,
+};
+
+use super::{
+    fs::{fs_error, io_error},
+    insert_function, new_object, own_values, param_error, string_param, with_native,
+};
+
+/// Creates the export of `#process`, which runs other programs.
+/// Children get the environment scripts see, with the variables set through `#os`
+pub fn create(runtime: &Runtime) -> Value {
+    let module = runtime.object_pool.new_object().unwrap();
+
+    let stream_prototype = runtime.object_pool.new_object().unwrap();
+    insert_function(runtime, &stream_prototype, "readLine", stream_read_line);
+    insert_function(
+        runtime,
+        &stream_prototype,
+        "readToString",
+        stream_read_to_string,
+    );
+    insert_function(runtime, &stream_prototype, "write", stream_write);
+    insert_function(runtime, &stream_prototype, "close", stream_close);
+
+    let child_prototype = runtime.object_pool.new_object().unwrap();
+    insert_function(runtime, &child_prototype, "wait", child_wait);
+    insert_function(runtime, &child_prototype, "kill", child_kill);
+
+    insert_function(runtime, &module, "run", run);
+    insert_function(
+        runtime,
+        &module,
+        "spawn",
+        move |runtime, _this_obj, params| {
+            let (program, mut command) = command(&runtime, params, "spawn", ["pipe"; 3])?;
+            let mut child = command
+                .spawn()
+                .map_err(|err| io_error(&runtime, &program, err))?;
+
+            let new_stream = |name, pipe| Stream {
+                name,
+                program: program.clone(),
+                pipe: Mutex::new(pipe),
+            };
+            let stdin = Arc::new(new_stream("stdin", child.stdin.take().map(Pipe::Stdin)));
+            let stdout = new_stream(
+                "stdout",
+                child
+                    .stdout
+                    .take()
+                    .map(|it| Pipe::Stdout(BufReader::new(it))),
+            );
+            let stderr = new_stream(
+                "stderr",
+                child
+                    .stderr
+                    .take()
+                    .map(|it| Pipe::Stderr(BufReader::new(it))),
+            );
+
+            let pid = Value::Integer(child.id() as isize);
+            let process = ChildProcess {
+                program: program.clone(),
+                child: Mutex::new(child),
+                stdin: stdin.clone(),
+            };
+
+            let obj = runtime
+                .object_pool
+                .new_native_object_prototype(Arc::new(process), child_prototype.clone())
+                .unwrap();
+
+            let stream_object = |stream: Arc<Stream>| {
+                let obj = runtime
+                    .object_pool
+                    .new_native_object_prototype(stream, stream_prototype.clone())
+                    .unwrap();
+                return Value::Object(obj);
+            };
+            insert_value(&runtime.string_pool, &obj, "pid", pid);
+            insert_value(&runtime.string_pool, &obj, "stdin", stream_object(stdin));
+            insert_value(
+                &runtime.string_pool,
+                &obj,
+                "stdout",
+                stream_object(Arc::new(stdout)),
+            );
+            insert_value(
+                &runtime.string_pool,
+                &obj,
+                "stderr",
+                stream_object(Arc::new(stderr)),
+            );
+
+            return Ok(Value::Object(obj));
+        },
+    );
+
+    return Value::Object(module);
+}
+
+/// An option from the options object, with missing ones as none
+fn option(runtime: &Arc<Runtime>, options: Option<&Value>, name: &str) -> Result<Value, Value> {
+    let Some(options) = options else {
+        return Ok(Value::None);
+    };
+
+    return match options.get_property(runtime.clone(), &runtime.new_string(name))? {
+        Value::Uninitialized => Ok(Value::None),
+        value => Ok(value),
+    };
+}
+
+/// Builds the command of `(program, options)`. The options are `args`, `env` with variables to set or none to remove,
+/// `cwd`, and `stdin`, `stdout` and `stderr` as `pipe`, `inherit` or `null`, which default to `stdio`
+fn command(
+    runtime: &Arc<Runtime>,
+    params: &[Value],
+    function: &str,
+    stdio: [&str; 3],
+) -> Result<(Arc<str>, Command), Value> {
+    let program = string_param(runtime, params, 0, function)?;
+    let mut command = Command::new(program.as_ref());
+
+    for (name, value) in runtime.env_overrides.read().unwrap().iter() {
+        match value {
+            Some(value) => command.env(name, value),
+            None => command.env_remove(name),
+        };
+    }
+
+    let options = match params.get(1) {
+        None | Some(Value::None) => None,
+        Some(options @ Value::Object(_)) => Some(options),
+        _ => return Err(param_error(runtime, 1, function, "an object of options")),
+    };
+
+    match option(runtime, options, "args")? {
+        Value::None => {}
+        args => {
+            let args = runtime
+                .array_values(&args)
+                .and_then(|args| {
+                    args.iter()
+                        .map(|it| match it {
+                            Value::String(arg) => Some(arg.get()),
+                            _ => None,
+                        })
+                        .collect::<Option<Vec<_>>>()
+                })
+                .ok_or_else(|| {
+                    param_error(runtime, 1, function, "args to be an array of strings")
+                })?;
+            command.args(args.iter().map(|it| it.as_ref()));
+        }
+    }
+
+    match option(runtime, options, "env")? {
+        Value::None => {}
+        Value::Object(env) => {
+            for (name, value) in own_values(&env) {
+                match value {
+                    Value::String(value) => command.env(name.as_ref(), value.get().as_ref()),
+                    Value::None => command.env_remove(name.as_ref()),
+                    _ => {
+                        return Err(param_error(
+                            runtime,
+                            1,
+                            function,
+                            "env to have strings or none",
+                        ))
+                    }
+                };
+            }
+        }
+        _ => return Err(param_error(runtime, 1, function, "env to be an object")),
+    }
+
+    match option(runtime, options, "cwd")? {
+        Value::None => {}
+        Value::String(cwd) => {
+            command.current_dir(cwd.get().as_ref());
+        }
+        _ => return Err(param_error(runtime, 1, function, "cwd to be a string")),
+    }
+
+    let mut modes = vec![];
+    for (name, default) in ["stdin", "stdout", "stderr"].into_iter().zip(stdio) {
+        let mode = match option(runtime, options, name)? {
+            Value::None => default.into(),
+            Value::String(mode) => mode.get(),
+            _ => {
+                return Err(param_error(
+                    runtime,
+                    1,
+                    function,
+                    &format!("{} to be a string", name),
+                ))
+            }
+        };
+
+        modes.push(match mode.as_ref() {
+            "pipe" => Stdio::piped(),
+            "inherit" => Stdio::inherit(),
+            "null" => Stdio::null(),
+            _ => {
+                return Err(param_error(
+                    runtime,
+                    1,
+                    function,
+                    &format!("{} to be pipe, inherit or null", name),
+                ))
+            }
+        });
+    }
+    let [stdin, stdout, stderr] = <[Stdio; 3]>::try_from(modes).ok().unwrap();
+    command.stdin(stdin).stdout(stdout).stderr(stderr);
+
+    return Ok((program, command));
+}
+
+/// The exit code, or none if the process was ended by a signal
+fn exit_code(status: ExitStatus) -> Value {
+    return match status.code() {
+        Some(code) => Value::Integer(code as isize),
+        None => Value::None,
+    };
+}
+
+/// `run(program, options)`, runs the program to the end and returns its `status`, `stdout` and `stderr`.
+/// The output is captured and there's no input unless the options say otherwise
+fn run(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let (program, mut command) = command(&runtime, params, "run", ["null", "pipe", "pipe"])?;
+
+    let output = command
+        .output()
+        .map_err(|err| io_error(&runtime, &program, err))?;
+
+    return Ok(new_object(
+        &runtime,
+        vec![
+            ("status", exit_code(output.status)),
+            (
+                "stdout",
+                runtime.new_string(&String::from_utf8_lossy(&output.stdout)),
+            ),
+            (
+                "stderr",
+                runtime.new_string(&String::from_utf8_lossy(&output.stderr)),
+            ),
+        ],
+    ));
+}
+
+enum Pipe {
+    Stdin(ChildStdin),
+    Stdout(BufReader<ChildStdout>),
+    Stderr(BufReader<ChildStderr>),
+}
+
+/// One of the pipes of a child process, which is none once closed or if it wasn't piped
+struct Stream {
+    name: &'static str,
+    program: Arc<str>,
+    pipe: Mutex<Option<Pipe>>,
+}
+
+impl NativeValue for Stream {
+    fn mark_children(&self, _marker: &mut MarkChildren) {}
+
+    fn cleanup(&self) {
+        self.pipe.lock().unwrap().take();
+    }
+}
+
+impl Stream {
+    fn error(&self, runtime: &Runtime, code: &str, message: &str) -> Value {
+        return fs_error(
+            runtime,
+            &self.program,
+            code,
+            &format!("{} {}", self.name, message),
+        );
+    }
+
+    fn with_reader<R>(
+        &self,
+        runtime: &Runtime,
+        f: impl FnOnce(&mut dyn BufRead) -> io::Result<R>,
+    ) -> Result<R, Value> {
+        let result = match self.pipe.lock().unwrap().as_mut() {
+            Some(Pipe::Stdout(reader)) => f(reader),
+            Some(Pipe::Stderr(reader)) => f(reader),
+            Some(Pipe::Stdin(_)) => {
+                return Err(self.error(runtime, "Unsupported", "can't be read from"))
+            }
+            None => return Err(self.error(runtime, "Closed", "is closed or not piped")),
+        };
+
+        return result.map_err(|err| io_error(runtime, &self.program, err));
+    }
+}
+
+/// `readLine()`, the next line without it's line ending, or none once the process closes the pipe
+fn stream_read_line(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    _params: &[Value],
+) -> Result<Value, Value> {
+    let line = with_native(&runtime, this_obj, "stream", |stream: &Stream| {
+        stream.with_reader(&runtime, |reader| {
+            let mut line = String::new();
+            let read = reader.read_line(&mut line)?;
+
+            return Ok((read > 0).then_some(line));
+        })
+    })?;
+
+    let Some(line) = line else {
+        return Ok(Value::None);
+    };
+
+    let line = line.strip_suffix('\n').unwrap_or(&line);
+    let line = line.strip_suffix('\r').unwrap_or(line);
+
+    return Ok(runtime.new_string(line));
+}
+
+/// `readToString()`, everything until the process closes the pipe
+fn stream_read_to_string(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    _params: &[Value],
+) -> Result<Value, Value> {
+    let text = with_native(&runtime, this_obj, "stream", |stream: &Stream| {
+        stream.with_reader(&runtime, |reader| {
+            let mut text = String::new();
+            reader.read_to_string(&mut text)?;
+
+            return Ok(text);
+        })
+    })?;
+
+    return Ok(runtime.new_string(&text));
+}
+
+/// `write(text)`, writes to the input of the process
+fn stream_write(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let text = string_param(&runtime, params, 0, "write")?;
+
+    return with_native(&runtime, this_obj, "stream", |stream: &Stream| {
+        let result = match stream.pipe.lock().unwrap().as_mut() {
+            Some(Pipe::Stdin(stdin)) => stdin.write_all(text.as_bytes()),
+            Some(_) => return Err(stream.error(&runtime, "Unsupported", "can't be written to")),
+            None => return Err(stream.error(&runtime, "Closed", "is closed or not piped")),
+        };
+
+        return result
+            .map(|_| Value::None)
+            .map_err(|err| io_error(&runtime, &stream.program, err));
+    });
+}
+
+/// `close()`, closing the input lets the process know there's nothing more to read
+fn stream_close(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    _params: &[Value],
+) -> Result<Value, Value> {
+    return with_native(&runtime, this_obj, "stream", |stream: &Stream| {
+        stream.pipe.lock().unwrap().take();
+        return Ok(Value::None);
+    });
+}
+
+struct ChildProcess {
+    program: Arc<str>,
+    child: Mutex<Child>,
+    stdin: Arc<Stream>,
+}
+
+impl NativeValue for ChildProcess {
+    fn mark_children(&self, _marker: &mut MarkChildren) {}
+
+    /// Nothing can wait for the process once it's collected, so it's ended and waited for here instead of being left running
+    fn cleanup(&self) {
+        self.stdin.pipe.lock().unwrap().take();
+
+        let mut child = self.child.lock().unwrap();
+        if let Ok(None) = child.try_wait() {
+            let _ = child.kill();
+        }
+        let _ = child.wait();
+    }
+}
+
+/// `wait()`, waits for the process to end and returns its exit code, or none if it was ended by a signal.
+/// The input is closed first, so a process reading it doesn't wait forever
+fn child_wait(runtime: Arc<Runtime>, this_obj: &Value, _params: &[Value]) -> Result<Value, Value> {
+    return with_native(
+        &runtime,
+        this_obj,
+        "child process",
+        |process: &ChildProcess| {
+            process.stdin.pipe.lock().unwrap().take();
+
+            return match process.child.lock().unwrap().wait() {
+                Ok(status) => Ok(exit_code(status)),
+                Err(err) => Err(io_error(&runtime, &process.program, err)),
+            };
+        },
+    );
+}
+
+/// `kill()`, ends the process, doing nothing if it already has
+fn child_kill(runtime: Arc<Runtime>, this_obj: &Value, _params: &[Value]) -> Result<Value, Value> {
+    return with_native(
+        &runtime,
+        this_obj,
+        "child process",
+        |process: &ChildProcess| {
+            let mut child = process.child.lock().unwrap();
+
+            if let Ok(Some(_)) = child.try_wait() {
+                return Ok(Value::None);
+            }
+
+            return match child.kill() {
+                Ok(()) => Ok(Value::None),
+                Err(err) => Err(io_error(&runtime, &process.program, err)),
+            };
+        },
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use crate::runtime::{
+        modules::{call, new_object, shared, with_native},
+        value::{NativeValue, Value},
+        Runtime,
+    };
+
+    use super::ChildProcess;
+
+    fn string(value: Value) -> String {
+        return match value {
+            Value::String(str) => str.get().to_string(),
+            _ => panic!("Expected a string"),
+        };
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_and_spawn() {
+        let runtime = shared(Runtime::new());
+        let process = runtime.native_module("#process").unwrap();
+
+        let options = new_object(
+            &runtime,
+            vec![
+                (
+                    "args",
+                    runtime.new_array(vec![
+                        runtime.new_string("-c"),
+                        runtime.new_string("echo $GREETING; echo oops >&2; exit 3"),
+                    ]),
+                ),
+                (
+                    "env",
+                    new_object(&runtime, vec![("GREETING", runtime.new_string("hello"))]),
+                ),
+            ],
+        );
+        let output = call(
+            &runtime,
+            &process,
+            "run",
+            &[runtime.new_string("sh"), options],
+        )
+        .ok()
+        .unwrap();
+        let property = |name: &str| {
+            output
+                .get_property(runtime.clone(), &runtime.new_string(name))
+                .ok()
+                .unwrap()
+        };
+        assert!(matches!(property("status"), Value::Integer(3)));
+        assert_eq!(string(property("stdout")), "hello\n");
+        assert_eq!(string(property("stderr")), "oops\n");
+
+        let child = call(&runtime, &process, "spawn", &[runtime.new_string("cat")])
+            .ok()
+            .unwrap();
+        let stream = |name: &str| {
+            child
+                .get_property(runtime.clone(), &runtime.new_string(name))
+                .ok()
+                .unwrap()
+        };
+        call(
+            &runtime,
+            &stream("stdin"),
+            "write",
+            &[runtime.new_string("one\ntwo\n")],
+        )
+        .ok()
+        .unwrap();
+        call(&runtime, &stream("stdin"), "close", &[]).ok().unwrap();
+        let line = call(&runtime, &stream("stdout"), "readLine", &[])
+            .ok()
+            .unwrap();
+        assert_eq!(string(line), "one");
+        let rest = call(&runtime, &stream("stdout"), "readToString", &[])
+            .ok()
+            .unwrap();
+        assert_eq!(string(rest), "two\n");
+        assert!(call(
+            &runtime,
+            &stream("stdin"),
+            "write",
+            &[runtime.new_string("three")]
+        )
+        .is_err());
+        assert!(matches!(
+            call(&runtime, &child, "wait", &[]),
+            Ok(Value::Integer(0))
+        ));
+
+        assert!(call(
+            &runtime,
+            &process,
+            "run",
+            &[runtime.new_string("burrow-missing-program")]
+        )
+        .is_err());
+
+        let options = new_object(
+            &runtime,
+            vec![("args", runtime.new_array(vec![runtime.new_string("30")]))],
+        );
+        let child = call(
+            &runtime,
+            &process,
+            "spawn",
+            &[runtime.new_string("sleep"), options],
+        )
+        .ok()
+        .unwrap();
+        with_native(
+            &runtime,
+            &child,
+            "child process",
+            |process: &ChildProcess| {
+                process.cleanup();
+                return Ok(());
+            },
+        )
+        .ok()
+        .unwrap();
+        assert!(matches!(
+            call(&runtime, &child, "wait", &[]),
+            Ok(Value::None)
+        ));
+    }
+}
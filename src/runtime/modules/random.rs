@@ -0,0 +1,428 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::BuildHasher,
+    sync::{Arc, Mutex},
+};
+
+use crate::runtime::{
+    error::{insert_value, ErrorKind},
+    value::{
+        array::try_with_values,
+        object_pool::{MarkChildren, ObjectReference},
+        NativeValue, Value,
+    },
+    Runtime,
+};
+
+use super::{insert_function, integer_param, param_error, with_native};
+
+/// Creates the export of `#random`, with `new(seed)` to create generators and a `default` one.
+/// Generators created without a seed use the sandbox's `random_seed` if it's set, and a random one otherwise
+pub fn create(runtime: &Runtime) -> Value {
+    let module = runtime.object_pool.new_object().unwrap();
+
+    let prototype = runtime.object_pool.new_object().unwrap();
+    insert_function(runtime, &prototype, "seed", generator_seed);
+    insert_function(runtime, &prototype, "int", generator_int);
+    insert_function(runtime, &prototype, "float", generator_float);
+    insert_function(runtime, &prototype, "choice", generator_choice);
+    insert_function(runtime, &prototype, "shuffle", generator_shuffle);
+    insert_function(
+        runtime,
+        &prototype,
+        "weighted_choice",
+        generator_weighted_choice,
+    );
+
+    let default = new_generator(runtime, &prototype, default_seed(runtime));
+    insert_value(&runtime.string_pool, &module, "default", default);
+
+    insert_function(
+        runtime,
+        &module,
+        "new",
+        move |runtime, _this_obj, params| {
+            let seed = match params.first() {
+                None | Some(Value::None) => default_seed(&runtime),
+                _ => integer_param(&runtime, params, 0, "new")? as u64,
+            };
+
+            return Ok(new_generator(&runtime, &prototype, seed));
+        },
+    );
+
+    return Value::Object(module);
+}
+
+fn default_seed(runtime: &Runtime) -> u64 {
+    return runtime
+        .sandbox
+        .random_seed
+        .unwrap_or_else(|| RandomState::new().hash_one(0));
+}
+
+fn new_generator(runtime: &Runtime, prototype: &ObjectReference, seed: u64) -> Value {
+    let generator = Generator {
+        state: Mutex::new(Xoshiro::new(seed)),
+    };
+
+    let obj = runtime
+        .object_pool
+        .new_native_object_prototype(Arc::new(generator), prototype.clone())
+        .unwrap();
+
+    return Value::Object(obj);
+}
+
+/// The xoshiro256** generator, which is fast and good enough for anything but cryptography
+struct Xoshiro {
+    state: [u64; 4],
+}
+
+impl Xoshiro {
+    /// Spreads the seed over the state with splitmix64, which never makes the all zero state
+    fn new(seed: u64) -> Self {
+        let mut seed = seed;
+        let mut state = [0; 4];
+        for it in state.iter_mut() {
+            seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            *it = z ^ (z >> 31);
+        }
+
+        return Self { state };
+    }
+
+    fn next(&mut self) -> u64 {
+        let [s0, s1, s2, s3] = &mut self.state;
+        let result = s1.wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+
+        let t = *s1 << 17;
+        *s2 ^= *s0;
+        *s3 ^= *s1;
+        *s1 ^= *s2;
+        *s0 ^= *s3;
+        *s2 ^= t;
+        *s3 = s3.rotate_left(45);
+
+        return result;
+    }
+
+    /// A uniform value below the bound, rejecting the values that would make the lower ones more likely
+    fn below(&mut self, bound: u64) -> u64 {
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let value = self.next();
+            if value >= threshold {
+                return value % bound;
+            }
+        }
+    }
+
+    /// A uniform value from 0 up to but not including 1
+    fn unit(&mut self) -> f64 {
+        return (self.next() >> 11) as f64 / (1u64 << 53) as f64;
+    }
+}
+
+struct Generator {
+    state: Mutex<Xoshiro>,
+}
+
+impl NativeValue for Generator {
+    fn mark_children(&self, _marker: &mut MarkChildren) {}
+}
+
+fn with_generator<R>(
+    runtime: &Runtime,
+    this_obj: &Value,
+    f: impl FnOnce(&mut Xoshiro) -> Result<R, Value>,
+) -> Result<R, Value> {
+    return with_native(
+        runtime,
+        this_obj,
+        "random generator",
+        |generator: &Generator| f(&mut generator.state.lock().unwrap()),
+    );
+}
+
+/// `seed(seed)`, restarts the generator as if it was created with the seed
+fn generator_seed(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    params: &[Value],
+) -> Result<Value, Value> {
+    let seed = integer_param(&runtime, params, 0, "seed")? as u64;
+
+    return with_generator(&runtime, this_obj, |state| {
+        *state = Xoshiro::new(seed);
+        Ok(Value::None)
+    });
+}
+
+/// `int(min, max)`, an integer from `min` up to and including `max`
+fn generator_int(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    params: &[Value],
+) -> Result<Value, Value> {
+    let min = integer_param(&runtime, params, 0, "int")?;
+    let max = integer_param(&runtime, params, 1, "int")?;
+
+    if min > max {
+        return Err(runtime.new_error(ErrorKind::RangeError, "int expects min to be at most max"));
+    }
+
+    return with_generator(&runtime, this_obj, |state| {
+        let span = max.wrapping_sub(min) as u64;
+        let offset = match span.checked_add(1) {
+            Some(bound) => state.below(bound),
+            None => state.next(),
+        };
+
+        Ok(Value::Integer(min.wrapping_add(offset as isize)))
+    });
+}
+
+/// `float()`, a float from 0 up to but not including 1
+fn generator_float(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    _params: &[Value],
+) -> Result<Value, Value> {
+    return with_generator(&runtime, this_obj, |state| {
+        // Rounding to a float could give exactly 1
+        Ok(Value::Float(
+            (state.unit() as f32).min(1.0 - f32::EPSILON / 2.0),
+        ))
+    });
+}
+
+/// `choice(array)`, a random value of the array, throwing a `RangeError` if it's empty
+fn generator_choice(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    params: &[Value],
+) -> Result<Value, Value> {
+    let Some(values) = params.first().and_then(|it| runtime.array_values(it)) else {
+        return Err(param_error(&runtime, 0, "choice", "an array"));
+    };
+
+    if values.is_empty() {
+        return Err(runtime.new_error(ErrorKind::RangeError, "Can't choose from an empty array"));
+    }
+
+    return with_generator(&runtime, this_obj, |state| {
+        Ok(values[state.below(values.len() as u64) as usize].clone())
+    });
+}
+
+/// `shuffle(array)`, shuffles the array in place and returns it
+fn generator_shuffle(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    params: &[Value],
+) -> Result<Value, Value> {
+    let array = params.first().cloned().unwrap_or(Value::None);
+
+    return with_generator(&runtime, this_obj, |state| {
+        let shuffled = try_with_values(&array, |values| {
+            for index in (1..values.len()).rev() {
+                values.swap(index, state.below(index as u64 + 1) as usize);
+            }
+        });
+
+        return match shuffled {
+            Some(()) => Ok(array.clone()),
+            None => Err(param_error(&runtime, 0, "shuffle", "an array")),
+        };
+    });
+}
+
+/// `weighted_choice(values, weights)`, a random value where each is as likely as its weight
+fn generator_weighted_choice(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    params: &[Value],
+) -> Result<Value, Value> {
+    let Some(values) = params.first().and_then(|it| runtime.array_values(it)) else {
+        return Err(param_error(&runtime, 0, "weighted_choice", "an array"));
+    };
+    let weights = params
+        .get(1)
+        .and_then(|it| runtime.array_values(it))
+        .and_then(|weights| {
+            weights
+                .iter()
+                .map(|it| match it {
+                    Value::Integer(value) => Some(*value as f64),
+                    Value::Float(value) => Some(*value as f64),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()
+        })
+        .filter(|weights| weights.iter().all(|it| it.is_finite() && *it >= 0.0));
+    let Some(weights) = weights else {
+        return Err(param_error(
+            &runtime,
+            1,
+            "weighted_choice",
+            "an array of non-negative numbers",
+        ));
+    };
+
+    if values.len() != weights.len() {
+        return Err(runtime.new_error(
+            ErrorKind::RangeError,
+            "weighted_choice expects a weight for every value",
+        ));
+    }
+
+    let total = weights.iter().sum::<f64>();
+    if total <= 0.0 {
+        return Err(runtime.new_error(
+            ErrorKind::RangeError,
+            "weighted_choice expects a weight above zero",
+        ));
+    }
+
+    return with_generator(&runtime, this_obj, |state| {
+        let mut target = state.unit() * total;
+        let mut chosen = 0;
+        for (index, weight) in weights.iter().enumerate() {
+            if *weight == 0.0 {
+                continue;
+            }
+
+            // Kept on the last value with weight, in case rounding leaves the target past the end
+            chosen = index;
+            if target < *weight {
+                break;
+            }
+            target -= weight;
+        }
+
+        Ok(values[chosen].clone())
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use crate::runtime::{
+        modules::{call, shared},
+        sandbox::Sandbox,
+        value::Value,
+        Runtime,
+    };
+
+    fn integers(runtime: &Runtime, value: &Value) -> Vec<isize> {
+        return runtime
+            .array_values(value)
+            .unwrap()
+            .iter()
+            .map(|it| match it {
+                Value::Integer(value) => *value,
+                _ => panic!("Expected an integer"),
+            })
+            .collect();
+    }
+
+    #[test]
+    fn seeded_generators() {
+        let runtime = shared(Runtime::new());
+        let random = runtime.native_module("#random").unwrap();
+
+        let first = call(&runtime, &random, "new", &[Value::Integer(42)])
+            .ok()
+            .unwrap();
+        let second = call(&runtime, &random, "new", &[Value::Integer(42)])
+            .ok()
+            .unwrap();
+        for _ in 0..100 {
+            let Ok(Value::Integer(value)) = call(
+                &runtime,
+                &first,
+                "int",
+                &[Value::Integer(-3), Value::Integer(3)],
+            ) else {
+                panic!("Expected an integer");
+            };
+            assert!((-3..=3).contains(&value));
+            assert!(
+                matches!(call(&runtime, &second, "int", &[Value::Integer(-3), Value::Integer(3)]), Ok(Value::Integer(other)) if other == value)
+            );
+
+            let Ok(Value::Float(value)) = call(&runtime, &first, "float", &[]) else {
+                panic!("Expected a float");
+            };
+            assert!((0.0..1.0).contains(&value));
+            call(&runtime, &second, "float", &[]).ok().unwrap();
+        }
+
+        let array = runtime.new_array((0..10).map(Value::Integer).collect());
+        call(&runtime, &first, "shuffle", std::slice::from_ref(&array))
+            .ok()
+            .unwrap();
+        let mut shuffled = integers(&runtime, &array);
+        shuffled.sort();
+        assert_eq!(shuffled, (0..10).collect::<Vec<_>>());
+
+        let values = runtime.new_array(vec![Value::Integer(1), Value::Integer(2)]);
+        let weights = runtime.new_array(vec![Value::Integer(0), Value::Float(0.5)]);
+        for _ in 0..20 {
+            assert!(matches!(
+                call(
+                    &runtime,
+                    &first,
+                    "weighted_choice",
+                    &[values.clone(), weights.clone()]
+                ),
+                Ok(Value::Integer(2))
+            ));
+        }
+
+        assert!(call(&runtime, &first, "choice", &[runtime.new_array(vec![])]).is_err());
+        assert!(call(
+            &runtime,
+            &first,
+            "int",
+            &[Value::Integer(1), Value::Integer(0)]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn sandbox_seed() {
+        let sequence = || {
+            let runtime = shared(Runtime::with_sandbox(Sandbox {
+                random_seed: Some(7),
+                ..Default::default()
+            }));
+            let random = runtime.native_module("#random").unwrap();
+            let default = random
+                .get_property(runtime.clone(), &runtime.new_string("default"))
+                .ok()
+                .unwrap();
+            let generator = call(&runtime, &random, "new", &[]).ok().unwrap();
+
+            return (0..10)
+                .map(|index| {
+                    let generator = if index % 2 == 0 { &default } else { &generator };
+                    match call(
+                        &runtime,
+                        generator,
+                        "int",
+                        &[Value::Integer(0), Value::Integer(isize::MAX)],
+                    ) {
+                        Ok(Value::Integer(value)) => value,
+                        _ => panic!("Expected an integer"),
+                    }
+                })
+                .collect::<Vec<_>>();
+        };
+
+        assert_eq!(sequence(), sequence());
+    }
+}
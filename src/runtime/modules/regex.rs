@@ -0,0 +1,1465 @@
+use std::{collections::HashSet, sync::Arc};
+
+use crate::runtime::{
+    error::{insert_value, ErrorKind},
+    value::{object_pool::MarkChildren, NativeValue, Value},
+    Runtime,
+};
+
+use super::{insert_function, new_object, param_error, string_param, with_native};
+
+/// How many instructions a compiled pattern can have when the sandbox doesn't say
+const DEFAULT_SIZE_LIMIT: usize = 100_000;
+
+/// The most a counted repetition like `a{2,5}` can repeat
+const MAX_REPEAT: u32 = 1000;
+
+/// How deeply groups can nest, parsing and compiling them recurses
+const MAX_DEPTH: usize = 256;
+
+/// Creates the export of `#regex`, with `compile(pattern, flags)` for regex objects.
+///
+/// Patterns have literals, `.`, classes like `[a-z]`, `\d`, `\w` and `\s`, the anchors `^`, `$` and `\b`, groups
+/// which can be named like `(?<year>\d+)` or not capture like `(?:ab)`, alternation, and greedy and lazy quantifiers.
+/// The flags are `i` to ignore case, `m` for `^` and `$` to match at lines and `s` for `.` to match newlines.
+/// Positions are character indices, like the string methods use
+pub fn create(runtime: &Runtime) -> Value {
+    let module = runtime.object_pool.new_object().unwrap();
+
+    let prototype = runtime.object_pool.new_object().unwrap();
+    insert_function(runtime, &prototype, "test", regex_test);
+    insert_function(runtime, &prototype, "find", regex_find);
+    insert_function(runtime, &prototype, "find_all", regex_find_all);
+    insert_function(runtime, &prototype, "captures", regex_captures);
+    insert_function(runtime, &prototype, "replace", regex_replace);
+    insert_function(runtime, &prototype, "split", regex_split);
+
+    insert_function(runtime, &module, "escape", escape);
+    insert_function(
+        runtime,
+        &module,
+        "compile",
+        move |runtime, _this_obj, params| {
+            let pattern = string_param(&runtime, params, 0, "compile")?;
+            let flags = match params.get(1) {
+                None | Some(Value::None) => "".into(),
+                _ => string_param(&runtime, params, 1, "compile")?,
+            };
+            let size_limit = runtime
+                .sandbox
+                .regex_size_limit
+                .unwrap_or(DEFAULT_SIZE_LIMIT);
+
+            let program = Program::compile(&pattern, &flags, size_limit).map_err(|err| {
+                let kind = match err.too_large {
+                    true => ErrorKind::RangeError,
+                    false => ErrorKind::SyntaxError,
+                };
+                runtime.new_error(
+                    kind,
+                    &format!(
+                        "Invalid pattern {:?} at {}: {}",
+                        pattern,
+                        err.position + 1,
+                        err.message
+                    ),
+                )
+            })?;
+
+            let obj = runtime
+                .object_pool
+                .new_native_object_prototype(Arc::new(Regex { program }), prototype.clone())
+                .unwrap();
+            insert_value(
+                &runtime.string_pool,
+                &obj,
+                "pattern",
+                runtime.new_string(&pattern),
+            );
+            insert_value(
+                &runtime.string_pool,
+                &obj,
+                "flags",
+                runtime.new_string(&flags),
+            );
+
+            return Ok(Value::Object(obj));
+        },
+    );
+
+    return Value::Object(module);
+}
+
+struct CompileError {
+    /// The character index in the pattern
+    position: usize,
+    message: String,
+    /// Set when the pattern is valid, but goes over the size limit
+    too_large: bool,
+}
+
+/// A class escape like `\d`, or `\D` when negated
+#[derive(Clone, Copy)]
+enum Perl {
+    Digit,
+    Word,
+    Space,
+}
+
+impl Perl {
+    fn matches(self, char: char) -> bool {
+        return match self {
+            Perl::Digit => char.is_ascii_digit(),
+            Perl::Word => is_word(char),
+            Perl::Space => char.is_whitespace(),
+        };
+    }
+}
+
+fn is_word(char: char) -> bool {
+    return char.is_alphanumeric() || char == '_';
+}
+
+#[derive(Clone)]
+enum ClassItem {
+    Range(char, char),
+    Perl(Perl, bool),
+}
+
+#[derive(Clone)]
+struct Class {
+    items: Vec<ClassItem>,
+    negated: bool,
+}
+
+impl Class {
+    fn matches(&self, char: char, ignore_case: bool) -> bool {
+        let contains = |char: char| {
+            self.items.iter().any(|it| match it {
+                ClassItem::Range(start, end) => (*start..=*end).contains(&char),
+                ClassItem::Perl(perl, negated) => perl.matches(char) != *negated,
+            })
+        };
+
+        let matched = match ignore_case {
+            true => contains(char) || contains(fold(char)) || contains(upper(char)),
+            false => contains(char),
+        };
+
+        return matched != self.negated;
+    }
+}
+
+/// The lowercase of the character, if it's a single character
+fn fold(char: char) -> char {
+    let mut lower = char.to_lowercase();
+    return match (lower.next(), lower.next()) {
+        (Some(lower), None) => lower,
+        _ => char,
+    };
+}
+
+fn upper(char: char) -> char {
+    let mut upper = char.to_uppercase();
+    return match (upper.next(), upper.next()) {
+        (Some(upper), None) => upper,
+        _ => char,
+    };
+}
+
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    WordBoundary(bool),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+struct Parser {
+    chars: Vec<char>,
+    index: usize,
+    /// The names of the capture groups after the whole match, none for unnamed ones
+    names: Vec<Option<String>>,
+    /// How many groups the parser is in
+    depth: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> CompileError {
+        return CompileError {
+            position: self.index,
+            message: message.into(),
+            too_large: false,
+        };
+    }
+
+    fn peek(&self) -> Option<char> {
+        return self.chars.get(self.index).copied();
+    }
+
+    fn eat(&mut self, char: char) -> bool {
+        if self.peek() == Some(char) {
+            self.index += 1;
+            return true;
+        }
+
+        return false;
+    }
+
+    fn next(&mut self) -> Result<char, CompileError> {
+        let Some(char) = self.peek() else {
+            return Err(self.error("Unexpected end of the pattern"));
+        };
+        self.index += 1;
+
+        return Ok(char);
+    }
+
+    fn parse_alternation(&mut self) -> Result<Node, CompileError> {
+        let mut alternatives = vec![self.parse_concat()?];
+        while self.eat('|') {
+            alternatives.push(self.parse_concat()?);
+        }
+
+        return Ok(match alternatives.len() {
+            1 => alternatives.pop().unwrap(),
+            _ => Node::Alternation(alternatives),
+        });
+    }
+
+    fn parse_concat(&mut self) -> Result<Node, CompileError> {
+        let mut nodes = vec![];
+        while let Some(char) = self.peek() {
+            if char == '|' || char == ')' {
+                break;
+            }
+
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_quantifier(atom)?);
+        }
+
+        return Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.pop().unwrap(),
+            _ => Node::Concat(nodes),
+        });
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, CompileError> {
+        let char = self.next()?;
+
+        return match char {
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Start),
+            '$' => Ok(Node::End),
+            '[' => Ok(Node::Class(self.parse_class()?)),
+            '(' => self.parse_group(),
+            '\\' => self.parse_escape(),
+            '*' | '+' | '?' => {
+                self.index -= 1;
+                Err(self.error("Nothing to repeat"))
+            }
+            char => Ok(Node::Char(char)),
+        };
+    }
+
+    fn parse_group(&mut self) -> Result<Node, CompileError> {
+        if self.depth >= MAX_DEPTH {
+            self.index -= 1;
+            return Err(self.error("Groups are nested too deeply"));
+        }
+
+        let index = match self.eat('?') {
+            false => {
+                self.names.push(None);
+                Some(self.names.len())
+            }
+            true if self.eat(':') => None,
+            true => {
+                // Both `(?<name>...)` and `(?P<name>...)`
+                self.eat('P');
+                if !self.eat('<') {
+                    return Err(self.error("Expected a group name or ':' after '(?'"));
+                }
+
+                let start = self.index;
+                while self.peek().is_some_and(is_word) {
+                    self.index += 1;
+                }
+                let name = self.chars[start..self.index].iter().collect::<String>();
+
+                if name.is_empty() || name.starts_with(|it: char| it.is_ascii_digit()) {
+                    return Err(self.error("Expected a group name"));
+                }
+                if self.names.contains(&Some(name.clone())) {
+                    return Err(self.error(&format!("The group name {} is used twice", name)));
+                }
+                if !self.eat('>') {
+                    return Err(self.error("Expected '>' after the group name"));
+                }
+
+                self.names.push(Some(name));
+                Some(self.names.len())
+            }
+        };
+
+        self.depth += 1;
+        let node = self.parse_alternation()?;
+        self.depth -= 1;
+
+        if !self.eat(')') {
+            return Err(self.error("Expected ')'"));
+        }
+
+        return Ok(Node::Group(Box::new(node), index));
+    }
+
+    /// An escape that stands for a character, or a class like `\d`
+    fn parse_escaped_char(&mut self) -> Result<Result<char, ClassItem>, CompileError> {
+        let char = self.next()?;
+
+        return Ok(Ok(match char {
+            'd' => return Ok(Err(ClassItem::Perl(Perl::Digit, false))),
+            'D' => return Ok(Err(ClassItem::Perl(Perl::Digit, true))),
+            'w' => return Ok(Err(ClassItem::Perl(Perl::Word, false))),
+            'W' => return Ok(Err(ClassItem::Perl(Perl::Word, true))),
+            's' => return Ok(Err(ClassItem::Perl(Perl::Space, false))),
+            'S' => return Ok(Err(ClassItem::Perl(Perl::Space, true))),
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'f' => '\x0C',
+            'v' => '\x0B',
+            '0' => '\0',
+            'x' => self.parse_hex(2)?,
+            'u' => match self.eat('{') {
+                true => {
+                    let start = self.index;
+                    while self.peek().is_some_and(|it| it.is_ascii_hexdigit()) {
+                        self.index += 1;
+                    }
+                    let digits = self.chars[start..self.index].iter().collect::<String>();
+                    if !self.eat('}') {
+                        return Err(self.error("Expected '}' after the code point"));
+                    }
+                    self.code_point(&digits)?
+                }
+                false => self.parse_hex(4)?,
+            },
+            char if char.is_ascii_alphanumeric() => {
+                self.index -= 1;
+                return Err(self.error(&format!("Unknown escape \\{}", char)));
+            }
+            char => char,
+        }));
+    }
+
+    fn parse_hex(&mut self, count: usize) -> Result<char, CompileError> {
+        let start = self.index;
+        for _ in 0..count {
+            if !self.peek().is_some_and(|it| it.is_ascii_hexdigit()) {
+                return Err(self.error(&format!("Expected {} hex digits", count)));
+            }
+            self.index += 1;
+        }
+
+        let digits = self.chars[start..self.index].iter().collect::<String>();
+        return self.code_point(&digits);
+    }
+
+    fn code_point(&self, digits: &str) -> Result<char, CompileError> {
+        return u32::from_str_radix(digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error("Invalid code point"));
+    }
+
+    fn parse_escape(&mut self) -> Result<Node, CompileError> {
+        return match self.peek() {
+            Some('b') => {
+                self.index += 1;
+                Ok(Node::WordBoundary(false))
+            }
+            Some('B') => {
+                self.index += 1;
+                Ok(Node::WordBoundary(true))
+            }
+            _ => Ok(match self.parse_escaped_char()? {
+                Ok(char) => Node::Char(char),
+                Err(item) => Node::Class(Class {
+                    items: vec![item],
+                    negated: false,
+                }),
+            }),
+        };
+    }
+
+    /// A class after the `[`, where a `]` right at the start is part of it
+    fn parse_class(&mut self) -> Result<Class, CompileError> {
+        let negated = self.eat('^');
+        let mut items = vec![];
+
+        let mut first = true;
+        loop {
+            let char = match self.next()? {
+                ']' if !first => break,
+                '\\' => match self.parse_escaped_char()? {
+                    Ok(char) => char,
+                    Err(item) => {
+                        items.push(item);
+                        first = false;
+                        continue;
+                    }
+                },
+                char => char,
+            };
+            first = false;
+
+            if self.peek() == Some('-')
+                && self.chars.get(self.index + 1).is_some_and(|it| *it != ']')
+            {
+                self.index += 1;
+                let end = match self.next()? {
+                    '\\' => match self.parse_escaped_char()? {
+                        Ok(char) => char,
+                        Err(_) => return Err(self.error("A class can't end a range")),
+                    },
+                    char => char,
+                };
+
+                if end < char {
+                    return Err(self.error("The range is out of order"));
+                }
+                items.push(ClassItem::Range(char, end));
+            } else {
+                items.push(ClassItem::Range(char, char));
+            }
+        }
+
+        return Ok(Class { items, negated });
+    }
+
+    fn parse_number(&mut self) -> Option<u32> {
+        let start = self.index;
+        while self.peek().is_some_and(|it| it.is_ascii_digit()) {
+            self.index += 1;
+        }
+
+        return self.chars[start..self.index]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok();
+    }
+
+    /// The bounds of `{n}`, `{n,}` or `{n,m}`, or none if the `{` doesn't start one and is just a character
+    fn parse_bounds(&mut self) -> Result<Option<(u32, Option<u32>)>, CompileError> {
+        let start = self.index;
+        self.index += 1;
+
+        let bounds = self.parse_number().and_then(|min| {
+            if self.eat('}') {
+                return Some((min, Some(min)));
+            }
+            if !self.eat(',') {
+                return None;
+            }
+            if self.eat('}') {
+                return Some((min, None));
+            }
+
+            let max = self.parse_number()?;
+            return self.eat('}').then_some((min, Some(max)));
+        });
+
+        let Some((min, max)) = bounds else {
+            self.index = start;
+            return Ok(None);
+        };
+
+        if max.is_some_and(|max| max < min) {
+            return Err(self.error("The repetition's minimum is above its maximum"));
+        }
+        if max.unwrap_or(min) > MAX_REPEAT {
+            return Err(self.error(&format!("A repetition can't be over {}", MAX_REPEAT)));
+        }
+
+        return Ok(Some((min, max)));
+    }
+
+    fn parse_quantifier(&mut self, node: Node) -> Result<Node, CompileError> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => match self.parse_bounds()? {
+                Some(bounds) => {
+                    // Moved back onto the `}`, so it's skipped like the other quantifiers
+                    self.index -= 1;
+                    bounds
+                }
+                None => return Ok(node),
+            },
+            _ => return Ok(node),
+        };
+        self.index += 1;
+
+        let greedy = !self.eat('?');
+
+        if matches!(self.peek(), Some('*' | '+' | '?')) {
+            return Err(self.error("Nothing to repeat"));
+        }
+
+        return Ok(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+            greedy,
+        });
+    }
+}
+
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    WordBoundary(bool),
+    /// Tries the first instruction, then the second if that doesn't match
+    Split(usize, usize),
+    Jump(usize),
+    /// Saves the position in a slot, two for each group
+    Save(usize),
+    Match,
+}
+
+struct Program {
+    insts: Vec<Inst>,
+    /// The names of the capture groups after the whole match
+    names: Vec<Option<String>>,
+    ignore_case: bool,
+    multiline: bool,
+    dot_all: bool,
+    size_limit: usize,
+}
+
+impl Program {
+    fn compile(pattern: &str, flags: &str, size_limit: usize) -> Result<Self, CompileError> {
+        let mut program = Self {
+            insts: vec![],
+            names: vec![],
+            ignore_case: false,
+            multiline: false,
+            dot_all: false,
+            size_limit,
+        };
+
+        for (index, flag) in flags.chars().enumerate() {
+            let set = match flag {
+                'i' => &mut program.ignore_case,
+                'm' => &mut program.multiline,
+                's' => &mut program.dot_all,
+                _ => {
+                    return Err(CompileError {
+                        position: index,
+                        message: format!("Unknown flag {:?}", flag),
+                        too_large: false,
+                    })
+                }
+            };
+            *set = true;
+        }
+
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            index: 0,
+            names: vec![],
+            depth: 0,
+        };
+        let node = parser.parse_alternation()?;
+        if parser.index < parser.chars.len() {
+            return Err(parser.error("Unmatched ')'"));
+        }
+
+        program.names = parser.names;
+        program.emit(Inst::Save(0), pattern)?;
+        program.compile_node(&node, pattern)?;
+        program.emit(Inst::Save(1), pattern)?;
+        program.emit(Inst::Match, pattern)?;
+
+        return Ok(program);
+    }
+
+    fn emit(&mut self, inst: Inst, pattern: &str) -> Result<usize, CompileError> {
+        if self.insts.len() >= self.size_limit {
+            return Err(CompileError {
+                position: pattern.chars().count(),
+                message: format!(
+                    "The pattern compiles to more than {} instructions",
+                    self.size_limit
+                ),
+                too_large: true,
+            });
+        }
+
+        self.insts.push(inst);
+        return Ok(self.insts.len() - 1);
+    }
+
+    fn patch(&mut self, index: usize, target: usize) {
+        match &mut self.insts[index] {
+            Inst::Split(_, second) => *second = target,
+            Inst::Jump(to) => *to = target,
+            _ => unreachable!(),
+        }
+    }
+
+    fn compile_node(&mut self, node: &Node, pattern: &str) -> Result<(), CompileError> {
+        match node {
+            Node::Empty => {}
+            Node::Char(char) => {
+                let char = if self.ignore_case { fold(*char) } else { *char };
+                self.emit(Inst::Char(char), pattern)?;
+            }
+            Node::Any => {
+                self.emit(Inst::Any, pattern)?;
+            }
+            Node::Class(class) => {
+                self.emit(Inst::Class(class.clone()), pattern)?;
+            }
+            Node::Start => {
+                self.emit(Inst::Start, pattern)?;
+            }
+            Node::End => {
+                self.emit(Inst::End, pattern)?;
+            }
+            Node::WordBoundary(negated) => {
+                self.emit(Inst::WordBoundary(*negated), pattern)?;
+            }
+            Node::Group(node, None) => self.compile_node(node, pattern)?,
+            Node::Group(node, Some(index)) => {
+                self.emit(Inst::Save(index * 2), pattern)?;
+                self.compile_node(node, pattern)?;
+                self.emit(Inst::Save(index * 2 + 1), pattern)?;
+            }
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.compile_node(node, pattern)?;
+                }
+            }
+            Node::Alternation(nodes) => {
+                let mut jumps = vec![];
+                for (index, node) in nodes.iter().enumerate() {
+                    if index == nodes.len() - 1 {
+                        self.compile_node(node, pattern)?;
+                        break;
+                    }
+
+                    let split = self.emit(Inst::Split(0, 0), pattern)?;
+                    self.insts[split] = Inst::Split(split + 1, 0);
+                    self.compile_node(node, pattern)?;
+                    jumps.push(self.emit(Inst::Jump(0), pattern)?);
+                    self.patch(split, self.insts.len());
+                }
+
+                for jump in jumps {
+                    self.patch(jump, self.insts.len());
+                }
+            }
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => {
+                for _ in 0..*min {
+                    self.compile_node(node, pattern)?;
+                }
+
+                // Lazy repetitions prefer to stop
+                let split = |body: usize, exit: usize| match greedy {
+                    true => Inst::Split(body, exit),
+                    false => Inst::Split(exit, body),
+                };
+
+                match max {
+                    None => {
+                        let start = self.emit(Inst::Split(0, 0), pattern)?;
+                        self.compile_node(node, pattern)?;
+                        self.emit(Inst::Jump(start), pattern)?;
+                        self.insts[start] = split(start + 1, self.insts.len());
+                    }
+                    Some(max) => {
+                        let mut splits = vec![];
+                        for _ in *min..*max {
+                            splits.push(self.emit(Inst::Split(0, 0), pattern)?);
+                            self.compile_node(node, pattern)?;
+                        }
+
+                        let exit = self.insts.len();
+                        for start in splits {
+                            self.insts[start] = split(start + 1, exit);
+                        }
+                    }
+                }
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+/// The `(instruction, position)` states searches of one text have been in. A state that failed once fails again,
+/// so skipping them keeps searches linear in the size of the pattern and the text
+struct Visited {
+    states: States,
+    /// The states marked since the last run started, which are unmarked again if it matched
+    trail: Vec<usize>,
+}
+
+enum States {
+    Bits(Vec<u64>),
+    /// For long texts, where the bits would take too much memory
+    Set(HashSet<usize>),
+}
+
+impl Visited {
+    fn new(states: usize) -> Self {
+        let states = match states <= 1 << 26 {
+            true => States::Bits(vec![0; states.div_ceil(64)]),
+            false => States::Set(HashSet::new()),
+        };
+
+        return Self {
+            states,
+            trail: vec![],
+        };
+    }
+
+    /// Marks the state, returning whether it's new
+    fn insert(&mut self, state: usize) -> bool {
+        let new = match &mut self.states {
+            States::Bits(bits) => {
+                let mask = 1 << (state % 64);
+                let new = bits[state / 64] & mask == 0;
+                bits[state / 64] |= mask;
+                new
+            }
+            States::Set(set) => set.insert(state),
+        };
+
+        if new {
+            self.trail.push(state);
+        }
+        return new;
+    }
+
+    /// Unmarks the states of a run that matched. Those can still lead to a match when a later search
+    /// reaches them, only the states of runs that failed are known to fail
+    fn forget_trail(&mut self) {
+        for state in self.trail.drain(..) {
+            match &mut self.states {
+                States::Bits(bits) => bits[state / 64] &= !(1 << (state % 64)),
+                States::Set(set) => {
+                    set.remove(&state);
+                }
+            }
+        }
+    }
+}
+
+enum Job {
+    Step(usize, usize),
+    Restore(usize, Option<usize>),
+}
+
+/// The start and end of every group, which are none for groups that didn't take part
+type Slots = Vec<Option<usize>>;
+
+impl Program {
+    fn visited(&self, text: &[char]) -> Visited {
+        return Visited::new(self.insts.len() * (text.len() + 1));
+    }
+
+    /// The first match starting at or after `start`. The visited states can be shared by searches of the same text
+    fn search(&self, text: &[char], start: usize, visited: &mut Visited) -> Option<Slots> {
+        let mut slots = vec![None; (self.names.len() + 1) * 2];
+
+        for position in start..=text.len() {
+            visited.trail.clear();
+            if self.run(text, position, visited, &mut slots) {
+                visited.forget_trail();
+                return Some(slots);
+            }
+        }
+
+        return None;
+    }
+
+    fn run(&self, text: &[char], start: usize, visited: &mut Visited, slots: &mut Slots) -> bool {
+        let mut stack = vec![Job::Step(0, start)];
+
+        while let Some(job) = stack.pop() {
+            let (mut pc, mut position) = match job {
+                Job::Step(pc, position) => (pc, position),
+                Job::Restore(slot, value) => {
+                    slots[slot] = value;
+                    continue;
+                }
+            };
+
+            while visited.insert(pc * (text.len() + 1) + position) {
+                let char = text.get(position).copied();
+
+                let matched = match &self.insts[pc] {
+                    Inst::Char(expected) => char.is_some_and(|char| match self.ignore_case {
+                        true => fold(char) == *expected,
+                        false => char == *expected,
+                    }),
+                    Inst::Any => char.is_some_and(|char| self.dot_all || char != '\n'),
+                    Inst::Class(class) => {
+                        char.is_some_and(|it| class.matches(it, self.ignore_case))
+                    }
+                    Inst::Start => {
+                        if position == 0 || (self.multiline && text[position - 1] == '\n') {
+                            pc += 1;
+                            continue;
+                        }
+                        break;
+                    }
+                    Inst::End => {
+                        if char.is_none() || (self.multiline && char == Some('\n')) {
+                            pc += 1;
+                            continue;
+                        }
+                        break;
+                    }
+                    Inst::WordBoundary(negated) => {
+                        let before = position > 0 && is_word(text[position - 1]);
+                        if (before != char.is_some_and(is_word)) != *negated {
+                            pc += 1;
+                            continue;
+                        }
+                        break;
+                    }
+                    Inst::Split(first, second) => {
+                        stack.push(Job::Step(*second, position));
+                        pc = *first;
+                        continue;
+                    }
+                    Inst::Jump(to) => {
+                        pc = *to;
+                        continue;
+                    }
+                    Inst::Save(slot) => {
+                        stack.push(Job::Restore(*slot, slots[*slot]));
+                        slots[*slot] = Some(position);
+                        pc += 1;
+                        continue;
+                    }
+                    Inst::Match => return true,
+                };
+
+                if !matched {
+                    break;
+                }
+                pc += 1;
+                position += 1;
+            }
+        }
+
+        return false;
+    }
+
+    /// Every match from the start of the text, at most `limit`. After an empty match the search moves on a character
+    fn search_all(&self, text: &[char], limit: Option<usize>) -> Vec<Slots> {
+        let mut matches = vec![];
+        let mut start = 0;
+        let mut visited = self.visited(text);
+
+        while start <= text.len() && limit.is_none_or(|limit| matches.len() < limit) {
+            let Some(slots) = self.search(text, start, &mut visited) else {
+                break;
+            };
+
+            let (match_start, match_end) = (slots[0].unwrap(), slots[1].unwrap());
+            start = if match_end > match_start {
+                match_end
+            } else {
+                match_end + 1
+            };
+            matches.push(slots);
+        }
+
+        return matches;
+    }
+}
+
+struct Regex {
+    program: Program,
+}
+
+impl NativeValue for Regex {
+    fn mark_children(&self, _marker: &mut MarkChildren) {}
+}
+
+fn with_program<R>(
+    runtime: &Runtime,
+    this_obj: &Value,
+    f: impl FnOnce(&Program) -> Result<R, Value>,
+) -> Result<R, Value> {
+    return with_native(
+        runtime,
+        this_obj,
+        "regex",
+        |regex: &Regex| f(&regex.program),
+    );
+}
+
+/// The text of the group, or none if it didn't take part in the match
+fn group_text(text: &[char], slots: &Slots, group: usize) -> Option<String> {
+    return match (slots[group * 2], slots[group * 2 + 1]) {
+        (Some(start), Some(end)) => Some(text[start..end].iter().collect()),
+        _ => None,
+    };
+}
+
+/// `{ text, start, end }` of the whole match
+fn match_object(runtime: &Runtime, text: &[char], slots: &Slots) -> Value {
+    return new_object(
+        runtime,
+        vec![
+            (
+                "text",
+                runtime.new_string(&group_text(text, slots, 0).unwrap()),
+            ),
+            ("start", Value::Integer(slots[0].unwrap() as isize)),
+            ("end", Value::Integer(slots[1].unwrap() as isize)),
+        ],
+    );
+}
+
+/// The match object with `groups`, an array of every group starting with the whole match, and `named`, an object
+/// of the named groups
+fn captures_object(runtime: &Runtime, program: &Program, text: &[char], slots: &Slots) -> Value {
+    let group = |index| match group_text(text, slots, index) {
+        Some(text) => runtime.new_string(&text),
+        None => Value::None,
+    };
+
+    let groups = (0..=program.names.len()).map(group).collect();
+    let named = program
+        .names
+        .iter()
+        .enumerate()
+        .filter_map(|(index, name)| Some((name.as_deref()?, group(index + 1))))
+        .collect();
+
+    let obj = match_object(runtime, text, slots);
+    if let Value::Object(obj) = &obj {
+        insert_value(
+            &runtime.string_pool,
+            obj,
+            "groups",
+            runtime.new_array(groups),
+        );
+        insert_value(
+            &runtime.string_pool,
+            obj,
+            "named",
+            new_object(runtime, named),
+        );
+    }
+
+    return obj;
+}
+
+fn text_param(
+    runtime: &Runtime,
+    params: &[Value],
+    index: usize,
+    function: &str,
+) -> Result<Vec<char>, Value> {
+    return Ok(string_param(runtime, params, index, function)?
+        .chars()
+        .collect());
+}
+
+/// A limit on how many times to match, which can be left out or none for no limit
+fn limit_param(
+    runtime: &Runtime,
+    params: &[Value],
+    index: usize,
+    function: &str,
+) -> Result<Option<usize>, Value> {
+    return match params.get(index) {
+        None | Some(Value::None) => Ok(None),
+        Some(Value::Integer(limit)) if *limit > 0 => Ok(Some(*limit as usize)),
+        _ => Err(param_error(runtime, index, function, "a positive integer")),
+    };
+}
+
+/// The optional character index a search starts at, which counts from the end when negative
+fn start_param(
+    runtime: &Runtime,
+    params: &[Value],
+    index: usize,
+    function: &str,
+    len: usize,
+) -> Result<usize, Value> {
+    return match params.get(index) {
+        None | Some(Value::None) => Ok(0),
+        Some(Value::Integer(start)) if *start < 0 => Ok(len.saturating_sub(start.unsigned_abs())),
+        Some(Value::Integer(start)) => Ok((*start as usize).min(len)),
+        _ => Err(param_error(runtime, index, function, "an integer")),
+    };
+}
+
+/// `test(text)`, whether the pattern matches anywhere in the text
+fn regex_test(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let text = text_param(&runtime, params, 0, "test")?;
+
+    return with_program(&runtime, this_obj, |program| {
+        Ok(Value::Boolean(
+            program
+                .search(&text, 0, &mut program.visited(&text))
+                .is_some(),
+        ))
+    });
+}
+
+/// `find(text, start)`, the first match as `{ text, start, end }`, or none
+fn regex_find(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let text = text_param(&runtime, params, 0, "find")?;
+    let start = start_param(&runtime, params, 1, "find", text.len())?;
+
+    return with_program(&runtime, this_obj, |program| {
+        Ok(
+            match program.search(&text, start, &mut program.visited(&text)) {
+                Some(slots) => match_object(&runtime, &text, &slots),
+                None => Value::None,
+            },
+        )
+    });
+}
+
+/// `find_all(text)`, an array of every match that doesn't overlap another
+fn regex_find_all(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    params: &[Value],
+) -> Result<Value, Value> {
+    let text = text_param(&runtime, params, 0, "find_all")?;
+
+    return with_program(&runtime, this_obj, |program| {
+        let matches = program
+            .search_all(&text, None)
+            .iter()
+            .map(|slots| match_object(&runtime, &text, slots))
+            .collect();
+
+        Ok(runtime.new_array(matches))
+    });
+}
+
+/// `captures(text, start)`, the first match with its groups, or none
+fn regex_captures(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    params: &[Value],
+) -> Result<Value, Value> {
+    let text = text_param(&runtime, params, 0, "captures")?;
+    let start = start_param(&runtime, params, 1, "captures", text.len())?;
+
+    return with_program(&runtime, this_obj, |program| {
+        Ok(
+            match program.search(&text, start, &mut program.visited(&text)) {
+                Some(slots) => captures_object(&runtime, program, &text, &slots),
+                None => Value::None,
+            },
+        )
+    });
+}
+
+/// Fills in a replacement, where `$0` or `$&` is the match, `$1` a group, `$<name>` a named group and `$$` a `$`
+fn expand(template: &str, program: &Program, text: &[char], slots: &Slots) -> String {
+    let template = template.chars().collect::<Vec<_>>();
+    let mut expanded = String::new();
+
+    let mut index = 0;
+    while index < template.len() {
+        let rest = &template[index + 1..];
+        let (group, skip) = match (template[index], rest) {
+            ('$', ['$', ..]) => {
+                expanded.push('$');
+                index += 2;
+                continue;
+            }
+            ('$', ['&', ..]) => (Some(0), 2),
+            ('$', [digit, ..]) if digit.is_ascii_digit() => {
+                // Two digits if they name a group, like `$12`, otherwise one
+                let digits = rest
+                    .iter()
+                    .take(2)
+                    .map_while(|it| it.to_digit(10))
+                    .collect::<Vec<_>>();
+                let groups = program.names.len() as u32;
+
+                match digits[..] {
+                    [tens, ones] if tens * 10 + ones <= groups => {
+                        (Some((tens * 10 + ones) as usize), 3)
+                    }
+                    [digit, ..] if digit <= groups => (Some(digit as usize), 2),
+                    _ => (None, 1),
+                }
+            }
+            ('$', ['<', ..]) => match rest.iter().position(|it| *it == '>') {
+                Some(end) => {
+                    let name = rest[1..end].iter().collect::<String>();
+                    let group = program
+                        .names
+                        .iter()
+                        .position(|it| it.as_deref() == Some(&name));
+                    (group.map(|it| it + 1), end + 2)
+                }
+                None => (None, 1),
+            },
+            _ => (None, 1),
+        };
+
+        match group {
+            Some(group) => expanded.push_str(&group_text(text, slots, group).unwrap_or_default()),
+            None => expanded.extend(&template[index..index + skip]),
+        }
+        index += skip;
+    }
+
+    return expanded;
+}
+
+/// `replace(text, replacement, limit)`, replaces every match, or at most `limit` of them. The replacement is a string
+/// like `$1-$<name>`, see `expand`, or a function that gets the captures of each match and returns its replacement
+fn regex_replace(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    params: &[Value],
+) -> Result<Value, Value> {
+    let text = text_param(&runtime, params, 0, "replace")?;
+    let replacement = params.get(1).cloned().unwrap_or(Value::None);
+    let limit = limit_param(&runtime, params, 2, "replace")?;
+
+    if !matches!(replacement, Value::String(_) | Value::Object(_)) {
+        return Err(param_error(
+            &runtime,
+            1,
+            "replace",
+            "a string or a function",
+        ));
+    }
+
+    return with_program(&runtime, this_obj, |program| {
+        let mut replaced = String::new();
+        let mut last = 0;
+
+        for slots in program.search_all(&text, limit) {
+            let (start, end) = (slots[0].unwrap(), slots[1].unwrap());
+            replaced.extend(&text[last..start]);
+
+            match &replacement {
+                Value::String(template) => {
+                    replaced.push_str(&expand(&template.get(), program, &text, &slots));
+                }
+                callback => {
+                    let captures = captures_object(&runtime, program, &text, &slots);
+                    match callback.invoke(runtime.clone(), &Value::None, &[captures])? {
+                        Value::String(str) => replaced.push_str(&str.get()),
+                        _ => {
+                            return Err(runtime.new_error(
+                                ErrorKind::TypeError,
+                                "replace expects the callback to return a string",
+                            ))
+                        }
+                    }
+                }
+            }
+
+            last = end;
+        }
+        replaced.extend(&text[last..]);
+
+        Ok(runtime.new_string(&replaced))
+    });
+}
+
+/// `split(text, limit)`, the parts of the text between matches, at most `limit` of them with the last having the rest
+fn regex_split(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let text = text_param(&runtime, params, 0, "split")?;
+    let limit = limit_param(&runtime, params, 1, "split")?;
+
+    return with_program(&runtime, this_obj, |program| {
+        let mut parts = vec![];
+        let mut last = 0;
+
+        for slots in program.search_all(&text, limit.map(|it| it - 1)) {
+            let (start, end) = (slots[0].unwrap(), slots[1].unwrap());
+            parts.push(runtime.new_string(&text[last..start].iter().collect::<String>()));
+            last = end;
+        }
+        parts.push(runtime.new_string(&text[last..].iter().collect::<String>()));
+
+        Ok(runtime.new_array(parts))
+    });
+}
+
+/// `escape(text)`, the text with every character that means something in a pattern escaped
+fn escape(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let text = string_param(&runtime, params, 0, "escape")?;
+
+    let mut escaped = String::new();
+    for char in text.chars() {
+        if "\\.^$|?*+()[]{}-".contains(char) {
+            escaped.push('\\');
+        }
+        escaped.push(char);
+    }
+
+    return Ok(runtime.new_string(&escaped));
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::runtime::{
+        modules::{call, shared},
+        sandbox::Sandbox,
+        value::Value,
+        Runtime,
+    };
+
+    fn string(runtime: &Arc<Runtime>, value: &Value, property: &str) -> String {
+        let value = match property {
+            "" => value.clone(),
+            _ => value
+                .get_property(runtime.clone(), &runtime.new_string(property))
+                .ok()
+                .unwrap(),
+        };
+
+        return match value {
+            Value::String(str) => str.get().to_string(),
+            Value::None => "none".into(),
+            _ => panic!("Expected a string"),
+        };
+    }
+
+    #[test]
+    fn matching() {
+        let runtime = shared(Runtime::new());
+        let module = runtime.native_module("#regex").unwrap();
+        let compile = |pattern: &str, flags: &str| {
+            call(
+                &runtime,
+                &module,
+                "compile",
+                &[runtime.new_string(pattern), runtime.new_string(flags)],
+            )
+        };
+        let text = |text: &str| [runtime.new_string(text)];
+
+        let date = compile(r"(?<year>\d{4})-(?<month>\d\d)(-(\d\d))?", "")
+            .ok()
+            .unwrap();
+        let captures = call(&runtime, &date, "captures", &text("since 2024-03, ünïcode"))
+            .ok()
+            .unwrap();
+        assert_eq!(string(&runtime, &captures, "text"), "2024-03");
+        assert!(matches!(
+            captures.get_property(runtime.clone(), &runtime.new_string("start")),
+            Ok(Value::Integer(6))
+        ));
+        let named = captures
+            .get_property(runtime.clone(), &runtime.new_string("named"))
+            .ok()
+            .unwrap();
+        assert_eq!(string(&runtime, &named, "month"), "03");
+        let groups = captures
+            .get_property(runtime.clone(), &runtime.new_string("groups"))
+            .ok()
+            .unwrap();
+        assert!(matches!(
+            runtime.array_values(&groups).unwrap()[3],
+            Value::None
+        ));
+
+        let swapped = call(
+            &runtime,
+            &date,
+            "replace",
+            &[
+                runtime.new_string("2024-03 or 1999-12-31"),
+                runtime.new_string("$2/$<year>$$"),
+            ],
+        )
+        .ok()
+        .unwrap();
+        assert_eq!(string(&runtime, &swapped, ""), "03/2024$ or 12/1999$");
+
+        let numbers = compile(r"\d+", "").ok().unwrap();
+        let found = call(&runtime, &numbers, "find_all", &text("a1b22c333"))
+            .ok()
+            .unwrap();
+        let found = runtime.array_values(&found).unwrap();
+        assert_eq!(found.len(), 3);
+        assert_eq!(string(&runtime, &found[2], "text"), "333");
+
+        let double = runtime
+            .object_pool
+            .new_native_object(Arc::new(
+                |runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]| {
+                    let number = string(&runtime, &params[0], "text");
+                    return Ok(
+                        runtime.new_string(&(number.parse::<i32>().unwrap() * 2).to_string())
+                    );
+                },
+            ))
+            .unwrap();
+        let doubled = call(
+            &runtime,
+            &numbers,
+            "replace",
+            &[runtime.new_string("3 and 21"), Value::Object(double)],
+        )
+        .ok()
+        .unwrap();
+        assert_eq!(string(&runtime, &doubled, ""), "6 and 42");
+
+        let separator = compile(r",\s*", "").ok().unwrap();
+        let parts = call(&runtime, &separator, "split", &text("a, b,c,  d"))
+            .ok()
+            .unwrap();
+        let parts = runtime.array_values(&parts).unwrap();
+        assert_eq!(
+            parts
+                .iter()
+                .map(|it| string(&runtime, it, ""))
+                .collect::<Vec<_>>(),
+            ["a", "b", "c", "d"]
+        );
+
+        let words = compile(r"^\w+?$", "im").ok().unwrap();
+        let found = call(&runtime, &words, "find", &text("--\nHeLLo\n"))
+            .ok()
+            .unwrap();
+        assert_eq!(string(&runtime, &found, "text"), "HeLLo");
+
+        let hello = compile("héllo", "i").ok().unwrap();
+        assert!(matches!(
+            call(&runtime, &hello, "test", &text("HÉLLO")),
+            Ok(Value::Boolean(true))
+        ));
+
+        // Would take exponential time to fail without remembering the states that already failed
+        let nested = compile("(a*)*b", "").ok().unwrap();
+        assert!(matches!(
+            call(&runtime, &nested, "test", &text(&"a".repeat(5000))),
+            Ok(Value::Boolean(false))
+        ));
+
+        let empty = compile("x*", "").ok().unwrap();
+        let found = call(&runtime, &empty, "find_all", &text("axx"))
+            .ok()
+            .unwrap();
+        let found = runtime.array_values(&found).unwrap();
+        assert_eq!(
+            found
+                .iter()
+                .map(|it| string(&runtime, it, "text"))
+                .collect::<Vec<_>>(),
+            ["", "xx", ""]
+        );
+
+        // Each match reuses the states that failed before it, rather than starting over
+        let pairs = compile("ab", "").ok().unwrap();
+        let found = call(&runtime, &pairs, "find_all", &text(&"ab".repeat(50_000)))
+            .ok()
+            .unwrap();
+        assert_eq!(runtime.array_values(&found).unwrap().len(), 50_000);
+
+        let Err(error) = call(
+            &runtime,
+            &pairs,
+            "replace",
+            &[runtime.new_string("no match"), Value::Integer(1)],
+        ) else {
+            panic!("Replaced with an integer");
+        };
+        assert_eq!(string(&runtime, &error, "name"), "TypeError");
+    }
+
+    #[test]
+    fn compile_errors() {
+        let runtime = shared(Runtime::with_sandbox(Sandbox {
+            regex_size_limit: Some(50),
+            ..Default::default()
+        }));
+        let module = runtime.native_module("#regex").unwrap();
+
+        // Would overflow the stack without a limit on nesting
+        let nested = "(?:".repeat(100_000);
+
+        for (pattern, flags, name) in [
+            ("(a", "", "SyntaxError"),
+            ("a)", "", "SyntaxError"),
+            ("a**", "", "SyntaxError"),
+            ("[z-a]", "", "SyntaxError"),
+            (r"\q", "", "SyntaxError"),
+            ("(?<1a>b)", "", "SyntaxError"),
+            ("a", "g", "SyntaxError"),
+            ("a{100}", "", "RangeError"),
+            (&nested, "", "SyntaxError"),
+        ] {
+            let Err(error) = call(
+                &runtime,
+                &module,
+                "compile",
+                &[runtime.new_string(pattern), runtime.new_string(flags)],
+            ) else {
+                panic!("Compiled {:?}", pattern);
+            };
+
+            assert_eq!(string(&runtime, &error, "name"), name, "{}", pattern);
+        }
+
+        let escaped = call(
+            &runtime,
+            &module,
+            "escape",
+            &[runtime.new_string("1+1=(2)")],
+        )
+        .ok()
+        .unwrap();
+        let literal = call(&runtime, &module, "compile", &[escaped]).ok().unwrap();
+        assert!(matches!(
+            call(
+                &runtime,
+                &literal,
+                "test",
+                &[runtime.new_string("is 1+1=(2)?")]
+            ),
+            Ok(Value::Boolean(true))
+        ));
+    }
+}
@@ -0,0 +1,805 @@
+use std::{any::Any, cmp::Ordering, sync::Arc, time::Duration as StdDuration};
+
+use crate::runtime::{
+    error::ErrorKind,
+    value::{
+        object_pool::{MarkChildren, ObjectReference},
+        NativeValue, Value,
+    },
+    Runtime,
+};
+
+use super::{insert_function, integer_param, new_object, param_error, string_param, with_native};
+
+const NANOS_PER_MILLI: i64 = 1_000_000;
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+/// Creates the export of `#time`. Every time comes from the runtime's clock.
+///
+/// Timestamps are integers of milliseconds since the unix epoch in UTC, like the times `#fs` gives,
+/// and spans of time are `Duration` objects with nanosecond precision
+pub fn create(runtime: &Runtime) -> Value {
+    let module = runtime.object_pool.new_object().unwrap();
+
+    let prototype = runtime.object_pool.new_object().unwrap();
+    insert_function(runtime, &prototype, "seconds", duration_seconds);
+    insert_function(runtime, &prototype, "millis", duration_millis);
+    insert_function(runtime, &prototype, "nanos", duration_nanos);
+    insert_function(runtime, &prototype, "compare", duration_compare);
+    insert_function(runtime, &prototype, "to_string", duration_to_string);
+    insert_duration_function(
+        runtime,
+        &prototype,
+        &prototype,
+        "add",
+        |runtime, this_obj, params| {
+            let nanos = duration_nanos_of(runtime, this_obj)?;
+            let other = duration_param(runtime, params, 0, "add")?;
+            return nanos
+                .checked_add(other)
+                .ok_or_else(|| overflow_error(runtime));
+        },
+    );
+    insert_duration_function(
+        runtime,
+        &prototype,
+        &prototype,
+        "sub",
+        |runtime, this_obj, params| {
+            let nanos = duration_nanos_of(runtime, this_obj)?;
+            let other = duration_param(runtime, params, 0, "sub")?;
+            return nanos
+                .checked_sub(other)
+                .ok_or_else(|| overflow_error(runtime));
+        },
+    );
+    insert_duration_function(
+        runtime,
+        &prototype,
+        &prototype,
+        "mul",
+        |runtime, this_obj, params| {
+            let nanos = duration_nanos_of(runtime, this_obj)?;
+            return match params.first() {
+                Some(Value::Integer(factor)) => i64::try_from(*factor)
+                    .ok()
+                    .and_then(|factor| nanos.checked_mul(factor))
+                    .ok_or_else(|| overflow_error(runtime)),
+                Some(Value::Float(factor)) => float_nanos(runtime, nanos as f64 * *factor as f64),
+                _ => Err(param_error(runtime, 0, "mul", "a number")),
+            };
+        },
+    );
+
+    insert_duration_function(
+        runtime,
+        &module,
+        &prototype,
+        "now",
+        |runtime, _this_obj, _params| {
+            return std_nanos(runtime, runtime.clock.monotonic());
+        },
+    );
+    insert_duration_function(
+        runtime,
+        &module,
+        &prototype,
+        "elapsed",
+        |runtime, _this_obj, params| {
+            let start = duration_param(runtime, params, 0, "elapsed")?;
+            let now = std_nanos(runtime, runtime.clock.monotonic())?;
+            return now
+                .checked_sub(start)
+                .ok_or_else(|| overflow_error(runtime));
+        },
+    );
+    insert_duration_function(
+        runtime,
+        &module,
+        &prototype,
+        "duration",
+        |runtime, _this_obj, params| {
+            return seconds_param(runtime, params, 0, "duration");
+        },
+    );
+    insert_duration_function(
+        runtime,
+        &module,
+        &prototype,
+        "millis",
+        |runtime, _this_obj, params| {
+            return i64::try_from(integer_param(runtime, params, 0, "millis")?)
+                .ok()
+                .and_then(|millis| millis.checked_mul(NANOS_PER_MILLI))
+                .ok_or_else(|| overflow_error(runtime));
+        },
+    );
+
+    insert_function(runtime, &module, "timestamp", timestamp);
+    insert_function(runtime, &module, "sleep", sleep);
+    insert_function(runtime, &module, "components", components);
+    insert_function(runtime, &module, "from_components", from_components);
+    insert_function(runtime, &module, "format_iso", format_iso);
+    insert_function(runtime, &module, "parse_iso", parse_iso);
+
+    return Value::Object(module);
+}
+
+/// A span of time, which can be negative
+struct Duration {
+    nanos: i64,
+}
+
+impl NativeValue for Duration {
+    fn mark_children(&self, _marker: &mut MarkChildren) {}
+}
+
+/// Adds a function that returns a number of nanoseconds as a `Duration` with the prototype
+fn insert_duration_function(
+    runtime: &Runtime,
+    obj: &ObjectReference,
+    prototype: &ObjectReference,
+    name: &str,
+    f: fn(&Runtime, &Value, &[Value]) -> Result<i64, Value>,
+) {
+    let prototype = prototype.clone();
+
+    insert_function(runtime, obj, name, move |runtime, this_obj, params| {
+        let nanos = f(&runtime, this_obj, params)?;
+        let duration = Duration { nanos };
+
+        let obj = runtime
+            .object_pool
+            .new_native_object_prototype(Arc::new(duration), prototype.clone())
+            .unwrap();
+
+        return Ok(Value::Object(obj));
+    });
+}
+
+fn overflow_error(runtime: &Runtime) -> Value {
+    return runtime.new_error(ErrorKind::RangeError, "The duration doesn't fit");
+}
+
+fn std_nanos(runtime: &Runtime, duration: StdDuration) -> Result<i64, Value> {
+    return i64::try_from(duration.as_nanos()).map_err(|_| overflow_error(runtime));
+}
+
+fn float_nanos(runtime: &Runtime, nanos: f64) -> Result<i64, Value> {
+    // i64::MAX rounds up as a float, so it's excluded
+    if !(i64::MIN as f64..i64::MAX as f64).contains(&nanos) {
+        return Err(overflow_error(runtime));
+    }
+
+    return Ok(nanos as i64);
+}
+
+fn duration_nanos_of(runtime: &Runtime, value: &Value) -> Result<i64, Value> {
+    return with_native(runtime, value, "duration", |duration: &Duration| {
+        Ok(duration.nanos)
+    });
+}
+
+fn duration_param(
+    runtime: &Runtime,
+    params: &[Value],
+    index: usize,
+    function: &str,
+) -> Result<i64, Value> {
+    let native_value = match params.get(index) {
+        Some(Value::Object(obj)) => obj.get().native_value.read().unwrap().clone(),
+        _ => None,
+    };
+
+    return match native_value
+        .as_deref()
+        .and_then(|it| (it as &dyn Any).downcast_ref::<Duration>())
+    {
+        Some(duration) => Ok(duration.nanos),
+        None => Err(param_error(runtime, index, function, "a duration")),
+    };
+}
+
+/// A duration, or a number of seconds
+fn seconds_param(
+    runtime: &Runtime,
+    params: &[Value],
+    index: usize,
+    function: &str,
+) -> Result<i64, Value> {
+    return match params.get(index) {
+        Some(Value::Integer(seconds)) => i64::try_from(*seconds)
+            .ok()
+            .and_then(|seconds| seconds.checked_mul(NANOS_PER_SECOND))
+            .ok_or_else(|| overflow_error(runtime)),
+        Some(Value::Float(seconds)) if seconds.is_finite() => {
+            float_nanos(runtime, *seconds as f64 * NANOS_PER_SECOND as f64)
+        }
+        Some(Value::Object(_)) => duration_param(runtime, params, index, function),
+        _ => Err(param_error(
+            runtime,
+            index,
+            function,
+            "a duration or a number of seconds",
+        )),
+    };
+}
+
+/// `seconds()`, the duration in seconds as a float
+fn duration_seconds(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    _params: &[Value],
+) -> Result<Value, Value> {
+    let nanos = duration_nanos_of(&runtime, this_obj)?;
+
+    return Ok(Value::Float(
+        (nanos as f64 / NANOS_PER_SECOND as f64) as f32,
+    ));
+}
+
+/// `millis()`, the whole milliseconds of the duration
+fn duration_millis(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    _params: &[Value],
+) -> Result<Value, Value> {
+    let nanos = duration_nanos_of(&runtime, this_obj)?;
+
+    return Ok(Value::Integer((nanos / NANOS_PER_MILLI) as isize));
+}
+
+fn duration_nanos(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    _params: &[Value],
+) -> Result<Value, Value> {
+    let nanos = duration_nanos_of(&runtime, this_obj)?;
+
+    return Ok(Value::Integer(nanos as isize));
+}
+
+/// `compare(other)`, -1, 0 or 1 as the duration is shorter, the same or longer than the other
+fn duration_compare(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    params: &[Value],
+) -> Result<Value, Value> {
+    let nanos = duration_nanos_of(&runtime, this_obj)?;
+    let other = duration_param(&runtime, params, 0, "compare")?;
+
+    return Ok(Value::Integer(match nanos.cmp(&other) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    }));
+}
+
+/// `to_string()`, like `1h2m3.5s`, or `1.5ms` for durations under a second
+fn duration_to_string(
+    runtime: Arc<Runtime>,
+    this_obj: &Value,
+    _params: &[Value],
+) -> Result<Value, Value> {
+    let nanos = duration_nanos_of(&runtime, this_obj)?;
+
+    return Ok(runtime.new_string(&format_duration(nanos)));
+}
+
+fn format_duration(nanos: i64) -> String {
+    let sign = if nanos < 0 { "-" } else { "" };
+    let nanos = nanos.unsigned_abs();
+
+    /// The value divided by the unit, without trailing zeros
+    fn fraction(value: u64, unit: u64) -> String {
+        let digits = unit.ilog10() as usize;
+        let fraction = format!("{:0digits$}", value % unit);
+        let fraction = fraction.trim_end_matches('0');
+
+        return match fraction.is_empty() {
+            true => format!("{}", value / unit),
+            false => format!("{}.{}", value / unit, fraction),
+        };
+    }
+
+    let nanos_per_second = NANOS_PER_SECOND as u64;
+    return match nanos {
+        0 => "0s".into(),
+        1..1_000 => format!("{}{}ns", sign, nanos),
+        1_000..1_000_000 => format!("{}{}µs", sign, fraction(nanos, 1_000)),
+        1_000_000..1_000_000_000 => format!("{}{}ms", sign, fraction(nanos, 1_000_000)),
+        _ => {
+            let seconds = nanos / nanos_per_second;
+            let rest = fraction(
+                seconds % 60 * nanos_per_second + nanos % nanos_per_second,
+                nanos_per_second,
+            );
+
+            match (seconds / 3600, seconds / 60 % 60) {
+                (0, 0) => format!("{}{}s", sign, rest),
+                (0, minutes) => format!("{}{}m{}s", sign, minutes, rest),
+                (hours, minutes) => format!("{}{}h{}m{}s", sign, hours, minutes, rest),
+            }
+        }
+    };
+}
+
+/// `timestamp()`, the current wall-clock time
+fn timestamp(runtime: Arc<Runtime>, _this_obj: &Value, _params: &[Value]) -> Result<Value, Value> {
+    return Ok(Value::Integer(runtime.clock.wall().as_millis() as isize));
+}
+
+/// `sleep(duration)`, takes a duration or a number of seconds
+fn sleep(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let nanos = seconds_param(&runtime, params, 0, "sleep")?;
+
+    if nanos > 0 {
+        runtime.clock.sleep(StdDuration::from_nanos(nanos as u64));
+    }
+
+    return Ok(Value::None);
+}
+
+/// A timestamp parameter that defaults to the current time
+fn timestamp_param(
+    runtime: &Runtime,
+    params: &[Value],
+    index: usize,
+    function: &str,
+) -> Result<i64, Value> {
+    return match params.get(index) {
+        None | Some(Value::None) => Ok(runtime.clock.wall().as_millis() as i64),
+        _ => Ok(integer_param(runtime, params, index, function)? as i64),
+    };
+}
+
+fn is_leap_year(year: i64) -> bool {
+    return year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    return match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+}
+
+/// The days since the epoch of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    return era * 146_097 + day_of_era - 719_468;
+}
+
+/// The year, month and day of the days since the epoch
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+
+    return (era * 400 + year_of_era + (month <= 2) as i64, month, day);
+}
+
+struct DateTime {
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: i64,
+    millisecond: i64,
+}
+
+impl DateTime {
+    fn from_timestamp(timestamp: i64) -> Self {
+        let (year, month, day) = civil_from_days(timestamp.div_euclid(MILLIS_PER_DAY));
+        let millis = timestamp.rem_euclid(MILLIS_PER_DAY);
+
+        return Self {
+            year,
+            month,
+            day,
+            hour: millis / 3_600_000,
+            minute: millis / 60_000 % 60,
+            second: millis / 1000 % 60,
+            millisecond: millis % 1000,
+        };
+    }
+
+    /// The timestamp, or an error message if a component is out of range
+    fn to_timestamp(&self) -> Result<i64, String> {
+        let checks = [
+            ("month", self.month, 1, 12),
+            ("day", self.day, 1, days_in_month(self.year, self.month)),
+            ("hour", self.hour, 0, 23),
+            ("minute", self.minute, 0, 59),
+            ("second", self.second, 0, 59),
+            ("millisecond", self.millisecond, 0, 999),
+            // Keeps the days from overflowing
+            ("year", self.year, -1_000_000, 1_000_000),
+        ];
+        for (name, value, min, max) in checks {
+            if !(min..=max).contains(&value) {
+                return Err(format!(
+                    "The {} {} isn't between {} and {}",
+                    name, value, min, max
+                ));
+            }
+        }
+
+        let days = days_from_civil(self.year, self.month, self.day);
+        return Ok(days * MILLIS_PER_DAY
+            + self.hour * 3_600_000
+            + self.minute * 60_000
+            + self.second * 1000
+            + self.millisecond);
+    }
+}
+
+/// `components(timestamp)`, the UTC date and time of the timestamp as an object. The weekday is 1 for Monday up to 7 for Sunday
+fn components(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let timestamp = timestamp_param(&runtime, params, 0, "components")?;
+    let date_time = DateTime::from_timestamp(timestamp);
+
+    let days = timestamp.div_euclid(MILLIS_PER_DAY);
+    // The epoch was a Thursday
+    let weekday = (days + 3).rem_euclid(7) + 1;
+    let day_of_year = days - days_from_civil(date_time.year, 1, 1) + 1;
+
+    let component = |value: i64| Value::Integer(value as isize);
+    return Ok(new_object(
+        &runtime,
+        vec![
+            ("year", component(date_time.year)),
+            ("month", component(date_time.month)),
+            ("day", component(date_time.day)),
+            ("hour", component(date_time.hour)),
+            ("minute", component(date_time.minute)),
+            ("second", component(date_time.second)),
+            ("millisecond", component(date_time.millisecond)),
+            ("weekday", component(weekday)),
+            ("day_of_year", component(day_of_year)),
+        ],
+    ));
+}
+
+/// `from_components(components)`, the timestamp of a UTC date and time. Only the year is required,
+/// the month and day default to 1 and the time to midnight
+fn from_components(
+    runtime: Arc<Runtime>,
+    _this_obj: &Value,
+    params: &[Value],
+) -> Result<Value, Value> {
+    let Some(obj @ Value::Object(_)) = params.first() else {
+        return Err(param_error(&runtime, 0, "from_components", "an object"));
+    };
+
+    let component = |name: &str, default: Option<i64>| -> Result<i64, Value> {
+        return match (
+            obj.get_property(runtime.clone(), &runtime.new_string(name))?,
+            default,
+        ) {
+            (Value::Integer(value), _) => Ok(value as i64),
+            (Value::None | Value::Uninitialized, Some(default)) => Ok(default),
+            _ => Err(runtime.new_error(
+                ErrorKind::TypeError,
+                &format!("from_components expects {} to be an integer", name),
+            )),
+        };
+    };
+
+    let date_time = DateTime {
+        year: component("year", None)?,
+        month: component("month", Some(1))?,
+        day: component("day", Some(1))?,
+        hour: component("hour", Some(0))?,
+        minute: component("minute", Some(0))?,
+        second: component("second", Some(0))?,
+        millisecond: component("millisecond", Some(0))?,
+    };
+
+    return date_time
+        .to_timestamp()
+        .map(|it| Value::Integer(it as isize))
+        .map_err(|message| runtime.new_error(ErrorKind::RangeError, &message));
+}
+
+/// `format_iso(timestamp)`, like `2024-02-29T13:45:00.000Z`
+fn format_iso(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let timestamp = timestamp_param(&runtime, params, 0, "format_iso")?;
+    let date_time = DateTime::from_timestamp(timestamp);
+
+    // Years that don't fit in 4 digits use the expanded form with a sign
+    let year = match date_time.year {
+        0..=9999 => format!("{:04}", date_time.year),
+        year => format!("{}{:06}", if year < 0 { '-' } else { '+' }, year.abs()),
+    };
+
+    return Ok(runtime.new_string(&format!(
+        "{}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        date_time.month,
+        date_time.day,
+        date_time.hour,
+        date_time.minute,
+        date_time.second,
+        date_time.millisecond
+    )));
+}
+
+struct IsoParser<'a> {
+    bytes: &'a [u8],
+    index: usize,
+}
+
+impl IsoParser<'_> {
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.bytes.get(self.index) == Some(&byte) {
+            self.index += 1;
+            return true;
+        }
+
+        return false;
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.eat(byte) {
+            return Ok(());
+        }
+
+        return Err(format!("Expected '{}' at {}", byte as char, self.index + 1));
+    }
+
+    fn digits(&mut self, count: usize) -> Result<i64, String> {
+        let start = self.index;
+        let mut value = 0;
+        for _ in 0..count {
+            match self.bytes.get(self.index) {
+                Some(digit @ b'0'..=b'9') => value = value * 10 + (digit - b'0') as i64,
+                _ => return Err(format!("Expected {} digits at {}", count, start + 1)),
+            }
+            self.index += 1;
+        }
+
+        return Ok(value);
+    }
+
+    /// `YYYY-MM-DD`, then optionally `THH:MM`, seconds with a fraction and `Z` or an offset like `+02:00`.
+    /// Times without an offset are in UTC
+    fn parse(&mut self) -> Result<i64, String> {
+        let year = match self.bytes.first() {
+            Some(b'+') => {
+                self.index += 1;
+                self.digits(6)?
+            }
+            Some(b'-') => {
+                self.index += 1;
+                -self.digits(6)?
+            }
+            _ => self.digits(4)?,
+        };
+        self.expect(b'-')?;
+        let month = self.digits(2)?;
+        self.expect(b'-')?;
+        let day = self.digits(2)?;
+
+        let mut date_time = DateTime {
+            year,
+            month,
+            day,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            millisecond: 0,
+        };
+        let mut offset = 0;
+
+        if self.eat(b'T') || self.eat(b't') || self.eat(b' ') {
+            date_time.hour = self.digits(2)?;
+            self.expect(b':')?;
+            date_time.minute = self.digits(2)?;
+
+            if self.eat(b':') {
+                date_time.second = self.digits(2)?;
+
+                if self.eat(b'.') || self.eat(b',') {
+                    let start = self.index;
+                    while let Some(b'0'..=b'9') = self.bytes.get(self.index) {
+                        self.index += 1;
+                    }
+                    if self.index == start {
+                        return Err(format!("Expected a fraction at {}", start + 1));
+                    }
+
+                    // Precision past milliseconds is dropped
+                    let fraction = &self.bytes[start..self.index.min(start + 3)];
+                    date_time.millisecond = fraction
+                        .iter()
+                        .chain(b"00")
+                        .take(3)
+                        .fold(0, |value, digit| value * 10 + (digit - b'0') as i64);
+                }
+            }
+
+            if !(self.eat(b'Z') || self.eat(b'z')) {
+                let sign = match self.bytes.get(self.index) {
+                    Some(b'+') => Some(1),
+                    Some(b'-') => Some(-1),
+                    _ => None,
+                };
+
+                if let Some(sign) = sign {
+                    self.index += 1;
+                    let hours = self.digits(2)?;
+                    self.eat(b':');
+                    let minutes = self.digits(2)?;
+
+                    if hours > 23 || minutes > 59 {
+                        return Err("The offset is out of range".into());
+                    }
+                    offset = sign * (hours * 3_600_000 + minutes * 60_000);
+                }
+            }
+        }
+
+        if self.index != self.bytes.len() {
+            return Err(format!("Unexpected character at {}", self.index + 1));
+        }
+
+        return Ok(date_time.to_timestamp()? - offset);
+    }
+}
+
+/// `parse_iso(str)`, the timestamp of an ISO-8601 date and time, throwing a `SyntaxError` if it isn't one
+fn parse_iso(runtime: Arc<Runtime>, _this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let str = string_param(&runtime, params, 0, "parse_iso")?;
+
+    let mut parser = IsoParser {
+        bytes: str.as_bytes(),
+        index: 0,
+    };
+
+    return parser
+        .parse()
+        .map(|it| Value::Integer(it as isize))
+        .map_err(|message| {
+            runtime.new_error(
+                ErrorKind::SyntaxError,
+                &format!("Invalid date {:?}: {}", str, message),
+            )
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::runtime::{
+        clock::ManualClock,
+        modules::{call, new_object, shared},
+        value::Value,
+        Runtime,
+    };
+
+    fn string(value: Result<Value, Value>) -> String {
+        return match value {
+            Ok(Value::String(str)) => str.get().to_string(),
+            _ => panic!("Expected a string"),
+        };
+    }
+
+    #[test]
+    fn fake_clock() {
+        // 2024-02-29T12:00:00Z
+        let runtime =
+            shared(Runtime::new().with_clock(ManualClock::new(Duration::from_secs(1_709_208_000))));
+        let time = runtime.native_module("#time").unwrap();
+
+        let start = call(&runtime, &time, "now", &[]).ok().unwrap();
+        call(&runtime, &time, "sleep", &[Value::Float(1.5)])
+            .ok()
+            .unwrap();
+        let elapsed = call(&runtime, &time, "elapsed", std::slice::from_ref(&start))
+            .ok()
+            .unwrap();
+        assert!(matches!(
+            call(&runtime, &elapsed, "millis", &[]),
+            Ok(Value::Integer(1500))
+        ));
+        assert_eq!(string(call(&runtime, &elapsed, "to_string", &[])), "1.5s");
+
+        let minute = call(&runtime, &time, "duration", &[Value::Integer(60)])
+            .ok()
+            .unwrap();
+        let longer = call(&runtime, &elapsed, "add", std::slice::from_ref(&minute))
+            .ok()
+            .unwrap();
+        assert_eq!(string(call(&runtime, &longer, "to_string", &[])), "1m1.5s");
+        assert!(matches!(
+            call(&runtime, &minute, "compare", &[longer]),
+            Ok(Value::Integer(-1))
+        ));
+
+        assert_eq!(
+            string(call(&runtime, &time, "format_iso", &[])),
+            "2024-02-29T12:00:01.500Z"
+        );
+
+        let components = call(&runtime, &time, "components", &[]).ok().unwrap();
+        let component = |name: &str| {
+            components
+                .get_property(runtime.clone(), &runtime.new_string(name))
+                .ok()
+                .unwrap()
+        };
+        assert!(matches!(component("day"), Value::Integer(29)));
+        assert!(matches!(component("weekday"), Value::Integer(4)));
+        assert!(matches!(component("day_of_year"), Value::Integer(60)));
+        assert!(matches!(
+            call(
+                &runtime,
+                &time,
+                "from_components",
+                std::slice::from_ref(&components)
+            ),
+            Ok(Value::Integer(1_709_208_001_500))
+        ));
+    }
+
+    #[test]
+    fn iso_dates() {
+        let runtime = shared(Runtime::new());
+        let time = runtime.native_module("#time").unwrap();
+
+        let parse = |str: &str| call(&runtime, &time, "parse_iso", &[runtime.new_string(str)]);
+        let date = new_object(
+            &runtime,
+            vec![("year", Value::Integer(1970)), ("day", Value::Integer(2))],
+        );
+        assert!(matches!(
+            call(&runtime, &time, "from_components", &[date]),
+            Ok(Value::Integer(86_400_000))
+        ));
+        assert!(matches!(parse("1970-01-01"), Ok(Value::Integer(0))));
+        assert!(matches!(
+            parse("1970-01-01T01:00:00.25+01:00"),
+            Ok(Value::Integer(250))
+        ));
+        assert!(matches!(
+            parse("1969-12-31T23:59:59.999Z"),
+            Ok(Value::Integer(-1))
+        ));
+        assert!(parse("2023-02-29").is_err());
+        assert!(parse("2024-01-01T24:00").is_err());
+        assert!(parse("2024-01-01Tnoon").is_err());
+
+        let Ok(timestamp) = parse("+012345-06-07T08:09:10Z") else {
+            panic!("Expected a timestamp");
+        };
+        assert_eq!(
+            string(call(&runtime, &time, "format_iso", &[timestamp])),
+            "+012345-06-07T08:09:10.000Z"
+        );
+        assert_eq!(
+            string(call(&runtime, &time, "format_iso", &[Value::Integer(-1)])),
+            "1969-12-31T23:59:59.999Z"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// How many symlinks resolving a path can go through, so a cycle of them ends
+const MAX_SYMLINKS: usize = 40;
+
+/// Modules that can reach the whole system, like running programs or ending the process.
+/// A restricted sandbox denies them unless they're in `allowed_modules`
+const PRIVILEGED_MODULES: [&str; 2] = ["#process", "#os"];
+
+/// Limits on what scripts can reach outside of the runtime, set by the embedder.
+/// Once it restricts files or modules, `#process` and `#os` are denied too unless they're allowed again,
+/// since either one could get around the other restrictions
+#[derive(Debug, Clone, Default)]
+pub struct Sandbox {
+    /// When set, `#fs` can only reach files under this directory, and every path is relative to it
+    pub fs_root: Option<PathBuf>,
+    /// When set, `#random` generators created without a seed use this one, so runs can be replayed
+    pub random_seed: Option<u64>,
+    /// Native modules scripts can't import, by name like `#process`
+    pub denied_modules: HashSet<String>,
+    /// Privileged modules a restricted sandbox still lets scripts import, by name like `#os`
+    pub allowed_modules: HashSet<String>,
+    /// The most instructions a `#regex` pattern can compile to, when it should be lower or higher than the default
+    pub regex_size_limit: Option<usize>,
+}
+
+impl Sandbox {
+    /// Whether the sandbox limits files or modules. Seeds and size limits don't count
+    pub fn is_restricted(&self) -> bool {
+        return self.fs_root.is_some() || !self.denied_modules.is_empty();
+    }
+
+    pub fn allows_module(&self, name: &str) -> bool {
+        if self.denied_modules.contains(name) {
+            return false;
+        }
+
+        if self.is_restricted() && PRIVILEGED_MODULES.contains(&name) {
+            return self.allowed_modules.contains(name);
+        }
+
+        return true;
+    }
+
+    /// Maps a path from a script to the path on disk, or none if it would leave the root
+    pub fn resolve_path(&self, path: &str) -> Option<PathBuf> {
+        let Some(root) = &self.fs_root else {
+            return Some(PathBuf::from(path));
+        };
+
+        let mut relative = PathBuf::new();
+        for component in Path::new(path).components() {
+            match component {
+                Component::Normal(part) => relative.push(part),
+                Component::ParentDir => {
+                    if !relative.pop() {
+                        return None;
+                    }
+                }
+                // Absolute paths start at the root
+                Component::RootDir | Component::Prefix(_) | Component::CurDir => {}
+            }
+        }
+
+        let resolved = root.join(&relative);
+
+        // A symlink inside the root could still point out of it, even one whose target doesn't exist yet
+        let root = root.canonicalize().ok()?;
+        let real = real_path(root.clone(), &relative, &mut 0)?;
+
+        return real.starts_with(&root).then_some(resolved);
+    }
+}
+
+/// Follows the path from a directory without symlinks in it one part at a time, like opening it would.
+/// Symlinks are followed even when their target doesn't exist, and the parts that don't exist are kept as they are
+fn real_path(mut current: PathBuf, path: &Path, links: &mut usize) -> Option<PathBuf> {
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => current.push(component),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                current.pop();
+            }
+            Component::Normal(part) => {
+                let next = current.join(part);
+                let is_symlink = next
+                    .symlink_metadata()
+                    .is_ok_and(|it| it.file_type().is_symlink());
+
+                if !is_symlink {
+                    current = next;
+                    continue;
+                }
+
+                *links += 1;
+                if *links > MAX_SYMLINKS {
+                    return None;
+                }
+
+                // Relative targets start from the directory the link is in
+                let target = fs::read_link(&next).ok()?;
+                current = real_path(current, &target, links)?;
+            }
+        }
+    }
+
+    return Some(current);
+}
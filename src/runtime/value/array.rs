@@ -0,0 +1,658 @@
+use std::{
+    any::Any,
+    cmp::Ordering,
+    sync::{Arc, RwLock},
+};
+
+use crate::runtime::{
+    error::ErrorKind,
+    modules::{insert_function, integer_param, param_error},
+    Runtime,
+};
+
+use super::{
+    object_pool::{MarkChildren, Property},
+    NativeValue, Value,
+};
+
+/// The longest an array can be made by setting its length
+const MAX_LENGTH: usize = 1 << 26;
+
+/// The native value behind array objects
+pub struct Array {
+    pub values: RwLock<Vec<Value>>,
+}
+
+impl NativeValue for Array {
+    fn mark_children(&self, marker: &mut MarkChildren) {
+        for value in self.values.read().unwrap().iter() {
+            marker.mark_value(value);
+        }
+    }
+}
+
+impl Runtime {
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new_array(&self, values: Vec<Value>) -> Value {
+        let array = Array {
+            values: RwLock::new(values),
+        };
+
+        return Value::Object(
+            self.object_pool
+                .new_native_object_prototype(Arc::new(array), self.array_prototype.clone())
+                .unwrap(),
+        );
+    }
+
+    /// A copy of the values in the array, or none if the value isn't an array
+    pub fn array_values(&self, value: &Value) -> Option<Vec<Value>> {
+        return try_with_values(value, |values| values.clone());
+    }
+}
+
+/// Runs the function with the values of the array, or returns none if the value isn't an array
+pub(in crate::runtime) fn try_with_values<R>(
+    value: &Value,
+    f: impl FnOnce(&mut Vec<Value>) -> R,
+) -> Option<R> {
+    let Value::Object(obj) = value else {
+        return None;
+    };
+
+    let native_value = obj.get().native_value.read().unwrap().clone()?;
+    let array = (native_value.as_ref() as &dyn Any).downcast_ref::<Array>()?;
+
+    return Some(f(&mut array.values.write().unwrap()));
+}
+
+/// Like `try_with_values`, but throws a `TypeError` if `this` isn't an array
+fn with_values<R>(
+    runtime: &Runtime,
+    this_obj: &Value,
+    f: impl FnOnce(&mut Vec<Value>) -> Result<R, Value>,
+) -> Result<R, Value> {
+    return try_with_values(this_obj, f).unwrap_or_else(|| {
+        Err(runtime.new_error(ErrorKind::TypeError, "Expected this to be an array"))
+    });
+}
+
+/// Fills in the array prototype, the runtime creates it empty
+pub(in crate::runtime) fn install_prototype(runtime: &Runtime) {
+    let prototype = &runtime.array_prototype;
+
+    insert_function(runtime, prototype, "__get_index__", get_index);
+    insert_function(runtime, prototype, "__set_index__", set_index);
+
+    let get = runtime
+        .object_pool
+        .new_native_object(Arc::new(get_length))
+        .unwrap();
+    let set = runtime
+        .object_pool
+        .new_native_object(Arc::new(set_length))
+        .unwrap();
+    prototype.get().values.write().unwrap().insert(
+        runtime.string_pool.acquire("length".into()).unwrap(),
+        RwLock::new(Property::GetSet {
+            get: Value::Object(get),
+            set: Value::Object(set),
+        }),
+    );
+
+    insert_function(runtime, prototype, "push", push);
+    insert_function(runtime, prototype, "pop", pop);
+    insert_function(runtime, prototype, "insert", insert);
+    insert_function(runtime, prototype, "remove", remove);
+    insert_function(runtime, prototype, "slice", slice);
+    insert_function(runtime, prototype, "concat", concat);
+    insert_function(runtime, prototype, "join", join);
+    insert_function(runtime, prototype, "reverse", reverse);
+    insert_function(runtime, prototype, "sort", sort);
+    insert_function(runtime, prototype, "map", map);
+    insert_function(runtime, prototype, "filter", filter);
+    insert_function(runtime, prototype, "reduce", reduce);
+    insert_function(runtime, prototype, "find", find);
+    insert_function(runtime, prototype, "index_of", index_of);
+    insert_function(runtime, prototype, "contains", contains);
+}
+
+fn out_of_bounds(runtime: &Runtime, index: isize, len: usize) -> Value {
+    return runtime.new_error(
+        ErrorKind::RangeError,
+        &format!(
+            "Index {} is out of bounds for an array of length {}",
+            index, len
+        ),
+    );
+}
+
+/// Counts negative indices from the end, and clamps the index to the length
+pub(super) fn clamp_index(index: isize, len: usize) -> usize {
+    if index < 0 {
+        return len.saturating_sub(index.unsigned_abs());
+    }
+
+    return (index as usize).min(len);
+}
+
+/// Calls the callback with a value, it's index and the array, like `map` and `filter` do
+fn call_each(
+    runtime: &Arc<Runtime>,
+    this_obj: &Value,
+    callback: &Value,
+    index: usize,
+    value: &Value,
+) -> Result<Value, Value> {
+    return callback.invoke(
+        runtime.clone(),
+        &Value::None,
+        &[
+            value.clone(),
+            Value::Integer(index as isize),
+            this_obj.clone(),
+        ],
+    );
+}
+
+fn callback_param(
+    runtime: &Runtime,
+    params: &[Value],
+    index: usize,
+    function: &str,
+) -> Result<Value, Value> {
+    return match params.get(index) {
+        Some(callback @ Value::Object(_)) => Ok(callback.clone()),
+        _ => Err(param_error(runtime, index, function, "a function")),
+    };
+}
+
+/// Integer indices read the value, or none past the end. Anything else is left to the properties
+fn get_index(_runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let Some(Value::Integer(index)) = params.first() else {
+        return Ok(Value::Uninitialized);
+    };
+
+    let value = try_with_values(this_obj, |values| {
+        usize::try_from(*index)
+            .ok()
+            .and_then(|index| values.get(index).cloned())
+            .unwrap_or(Value::None)
+    });
+
+    return Ok(value.unwrap_or(Value::Uninitialized));
+}
+
+/// Integer indices replace a value, or push one when the index is the length
+fn set_index(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let [Value::Integer(index), value, ..] = params else {
+        return Ok(Value::Uninitialized);
+    };
+
+    let result = try_with_values(this_obj, |values| {
+        match usize::try_from(*index) {
+            Ok(index) if index < values.len() => values[index] = value.clone(),
+            Ok(index) if index == values.len() => values.push(value.clone()),
+            _ => return Err(values.len()),
+        }
+
+        return Ok(());
+    });
+
+    return match result {
+        None => Ok(Value::Uninitialized),
+        Some(Ok(())) => Ok(Value::None),
+        Some(Err(len)) => Err(out_of_bounds(&runtime, *index, len)),
+    };
+}
+
+fn get_length(runtime: Arc<Runtime>, this_obj: &Value, _params: &[Value]) -> Result<Value, Value> {
+    return with_values(&runtime, this_obj, |values| {
+        Ok(Value::Integer(values.len() as isize))
+    });
+}
+
+/// Setting the length truncates the array, or fills it with none
+fn set_length(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let len = match params.get(1) {
+        Some(Value::Integer(len)) if *len >= 0 => *len as usize,
+        _ => {
+            return Err(runtime.new_error(
+                ErrorKind::RangeError,
+                "The length of an array must be a positive integer",
+            ));
+        }
+    };
+
+    if len > MAX_LENGTH {
+        return Err(runtime.new_error(
+            ErrorKind::RangeError,
+            &format!("The length of an array can't be more than {}", MAX_LENGTH),
+        ));
+    }
+
+    with_values(&runtime, this_obj, |values| {
+        values.resize(len, Value::None);
+        return Ok(());
+    })?;
+
+    return Ok(Value::None);
+}
+
+/// `push(values...)`, returns the new length
+fn push(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    return with_values(&runtime, this_obj, |values| {
+        values.extend_from_slice(params);
+        return Ok(Value::Integer(values.len() as isize));
+    });
+}
+
+/// `pop()`, the last value, or none if the array is empty
+fn pop(runtime: Arc<Runtime>, this_obj: &Value, _params: &[Value]) -> Result<Value, Value> {
+    return with_values(&runtime, this_obj, |values| {
+        Ok(values.pop().unwrap_or(Value::None))
+    });
+}
+
+/// `insert(index, value)`, shifting the values after it up
+fn insert(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let index = integer_param(&runtime, params, 0, "insert")?;
+    let value = params.get(1).cloned().unwrap_or(Value::None);
+
+    return with_values(&runtime, this_obj, |values| {
+        match usize::try_from(index) {
+            Ok(position) if position <= values.len() => values.insert(position, value),
+            _ => return Err(out_of_bounds(&runtime, index, values.len())),
+        }
+
+        return Ok(Value::None);
+    });
+}
+
+/// `remove(index)`, returns the removed value
+fn remove(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let index = integer_param(&runtime, params, 0, "remove")?;
+
+    return with_values(&runtime, this_obj, |values| {
+        return match usize::try_from(index) {
+            Ok(position) if position < values.len() => Ok(values.remove(position)),
+            _ => Err(out_of_bounds(&runtime, index, values.len())),
+        };
+    });
+}
+
+/// `slice(start, end)`, a new array from `start` up to `end`, which can count from the end if negative
+fn slice(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let start = match params.first() {
+        None | Some(Value::None) => 0,
+        _ => integer_param(&runtime, params, 0, "slice")?,
+    };
+    let end = match params.get(1) {
+        None | Some(Value::None) => None,
+        _ => Some(integer_param(&runtime, params, 1, "slice")?),
+    };
+
+    let sliced = with_values(&runtime, this_obj, |values| {
+        let start = clamp_index(start, values.len());
+        let end = end.map_or(values.len(), |end| clamp_index(end, values.len()));
+
+        return Ok(values[start..end.max(start)].to_vec());
+    })?;
+
+    return Ok(runtime.new_array(sliced));
+}
+
+/// `concat(arrays...)`, a new array with the values of this one followed by the others
+fn concat(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let mut values = with_values(&runtime, this_obj, |values| Ok(values.clone()))?;
+
+    for (index, param) in params.iter().enumerate() {
+        let Some(other) = runtime.array_values(param) else {
+            return Err(param_error(&runtime, index, "concat", "an array"));
+        };
+        values.extend(other);
+    }
+
+    return Ok(runtime.new_array(values));
+}
+
+/// `join(separator)`, the values as strings with the separator between them, a comma by default
+fn join(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let separator = match params.first() {
+        None | Some(Value::None) => ",".to_string(),
+        Some(Value::String(separator)) => separator.to_string(),
+        _ => return Err(param_error(&runtime, 0, "join", "a string")),
+    };
+
+    let joined = with_values(&runtime, this_obj, |values| {
+        Ok(values
+            .iter()
+            .map(|it| it.to_string())
+            .collect::<Vec<_>>()
+            .join(&separator))
+    })?;
+
+    return Ok(runtime.new_string(&joined));
+}
+
+/// `reverse()`, reverses the array in place and returns it
+fn reverse(runtime: Arc<Runtime>, this_obj: &Value, _params: &[Value]) -> Result<Value, Value> {
+    with_values(&runtime, this_obj, |values| {
+        values.reverse();
+        return Ok(());
+    })?;
+
+    return Ok(this_obj.clone());
+}
+
+/// Orders numbers, strings and booleans, the types can't be mixed other than integers and floats
+fn default_order(runtime: &Runtime, lhs: &Value, rhs: &Value) -> Result<Ordering, Value> {
+    return match (lhs, rhs) {
+        (Value::Integer(lhs), Value::Integer(rhs)) => Ok(lhs.cmp(rhs)),
+        (Value::Integer(lhs), Value::Float(rhs)) => Ok((*lhs as f32).total_cmp(rhs)),
+        (Value::Float(lhs), Value::Integer(rhs)) => Ok(lhs.total_cmp(&(*rhs as f32))),
+        (Value::Float(lhs), Value::Float(rhs)) => Ok(lhs.total_cmp(rhs)),
+        (Value::String(lhs), Value::String(rhs)) => Ok(lhs.get().cmp(&rhs.get())),
+        (Value::Boolean(lhs), Value::Boolean(rhs)) => Ok(lhs.cmp(rhs)),
+        _ => Err(runtime.new_error(
+            ErrorKind::TypeError,
+            &format!("Cannot compare {} and {}", lhs, rhs),
+        )),
+    };
+}
+
+/// A stable merge sort, the comparator comes from the script so it can fail, and might not be consistent
+fn merge_sort(
+    mut values: Vec<Value>,
+    compare: &mut impl FnMut(&Value, &Value) -> Result<Ordering, Value>,
+) -> Result<Vec<Value>, Value> {
+    if values.len() <= 1 {
+        return Ok(values);
+    }
+
+    let rhs = values.split_off(values.len() / 2);
+    let mut lhs = merge_sort(values, compare)?.into_iter().peekable();
+    let mut rhs = merge_sort(rhs, compare)?.into_iter().peekable();
+
+    let mut merged = vec![];
+    while let (Some(next_lhs), Some(next_rhs)) = (lhs.peek(), rhs.peek()) {
+        if compare(next_rhs, next_lhs)? == Ordering::Less {
+            merged.extend(rhs.next());
+        } else {
+            merged.extend(lhs.next());
+        }
+    }
+    merged.extend(lhs);
+    merged.extend(rhs);
+
+    return Ok(merged);
+}
+
+/// `sort(comparator)`, sorts the array in place and returns it. The comparator returns a negative
+/// number if the first value goes first, a positive one if the second does, or zero to keep their order
+fn sort(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let comparator = match params.first() {
+        None | Some(Value::None) => None,
+        _ => Some(callback_param(&runtime, params, 0, "sort")?),
+    };
+
+    let values = with_values(&runtime, this_obj, |values| Ok(values.clone()))?;
+
+    let sorted = merge_sort(values, &mut |lhs, rhs| {
+        let Some(comparator) = &comparator else {
+            return default_order(&runtime, lhs, rhs);
+        };
+
+        return match comparator.invoke(
+            runtime.clone(),
+            &Value::None,
+            &[lhs.clone(), rhs.clone()],
+        )? {
+            Value::Integer(order) => Ok(order.cmp(&0)),
+            Value::Float(order) => Ok(order.partial_cmp(&0.0).unwrap_or(Ordering::Equal)),
+            _ => Err(runtime.new_error(
+                ErrorKind::TypeError,
+                "sort expects the comparator to return a number",
+            )),
+        };
+    })?;
+
+    with_values(&runtime, this_obj, |values| {
+        *values = sorted;
+        return Ok(());
+    })?;
+
+    return Ok(this_obj.clone());
+}
+
+/// `map(callback)`, a new array with what the callback returns for each value
+fn map(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let callback = callback_param(&runtime, params, 0, "map")?;
+    let values = with_values(&runtime, this_obj, |values| Ok(values.clone()))?;
+
+    let mut mapped = vec![];
+    for (index, value) in values.iter().enumerate() {
+        mapped.push(call_each(&runtime, this_obj, &callback, index, value)?);
+    }
+
+    return Ok(runtime.new_array(mapped));
+}
+
+/// `filter(callback)`, a new array with the values the callback returns something truthy for
+fn filter(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let callback = callback_param(&runtime, params, 0, "filter")?;
+    let values = with_values(&runtime, this_obj, |values| Ok(values.clone()))?;
+
+    let mut kept = vec![];
+    for (index, value) in values.into_iter().enumerate() {
+        if call_each(&runtime, this_obj, &callback, index, &value)?.is_truthy() {
+            kept.push(value);
+        }
+    }
+
+    return Ok(runtime.new_array(kept));
+}
+
+/// `reduce(callback, initial)`, calls `callback(accumulator, value, index, array)` for each value.
+/// Without an initial value the first value is used, which fails if the array is empty
+fn reduce(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let callback = callback_param(&runtime, params, 0, "reduce")?;
+    let values = with_values(&runtime, this_obj, |values| Ok(values.clone()))?;
+
+    let (mut accumulator, start) = match params.get(1) {
+        Some(initial) => (initial.clone(), 0),
+        None => match values.first() {
+            Some(first) => (first.clone(), 1),
+            None => {
+                return Err(runtime.new_error(
+                    ErrorKind::TypeError,
+                    "reduce of an empty array needs an initial value",
+                ));
+            }
+        },
+    };
+
+    for (index, value) in values.iter().enumerate().skip(start) {
+        accumulator = callback.invoke(
+            runtime.clone(),
+            &Value::None,
+            &[
+                accumulator,
+                value.clone(),
+                Value::Integer(index as isize),
+                this_obj.clone(),
+            ],
+        )?;
+    }
+
+    return Ok(accumulator);
+}
+
+/// `find(callback)`, the first value the callback returns something truthy for, or none
+fn find(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let callback = callback_param(&runtime, params, 0, "find")?;
+    let values = with_values(&runtime, this_obj, |values| Ok(values.clone()))?;
+
+    for (index, value) in values.into_iter().enumerate() {
+        if call_each(&runtime, this_obj, &callback, index, &value)?.is_truthy() {
+            return Ok(value);
+        }
+    }
+
+    return Ok(Value::None);
+}
+
+/// `index_of(value)`, the index of the first equal value, or -1
+fn index_of(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let search = params.first().cloned().unwrap_or(Value::None);
+
+    return with_values(&runtime, this_obj, |values| {
+        let index = values.iter().position(|it| it.equals(&search));
+        return Ok(Value::Integer(index.map_or(-1, |it| it as isize)));
+    });
+}
+
+fn contains(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let search = params.first().cloned().unwrap_or(Value::None);
+
+    return with_values(&runtime, this_obj, |values| {
+        Ok(Value::Boolean(values.iter().any(|it| it.equals(&search))))
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::runtime::{
+        modules::{call, shared},
+        value::Value,
+        Runtime,
+    };
+
+    fn native(
+        runtime: &Runtime,
+        f: impl Fn(Arc<Runtime>, &Value, &[Value]) -> Result<Value, Value> + 'static,
+    ) -> Value {
+        return Value::Object(runtime.object_pool.new_native_object(Arc::new(f)).unwrap());
+    }
+
+    fn integers(runtime: &Runtime, array: &Value) -> Vec<isize> {
+        return runtime
+            .array_values(array)
+            .unwrap()
+            .iter()
+            .map(|it| match it {
+                Value::Integer(value) => *value,
+                _ => panic!("Expected an integer"),
+            })
+            .collect();
+    }
+
+    #[test]
+    fn array_methods() {
+        let runtime = shared(Runtime::new());
+        let array = runtime.new_array(vec![
+            Value::Integer(3),
+            Value::Integer(1),
+            Value::Integer(2),
+        ]);
+        let Value::Object(reference) = &array else {
+            panic!("Expected an object");
+        };
+
+        // Indexing and the length come from the prototype
+        let get = |property: Value| {
+            reference
+                .get_property(runtime.clone(), &array, &property)
+                .ok()
+                .unwrap()
+        };
+        assert!(matches!(get(Value::Integer(0)), Value::Integer(3)));
+        assert!(matches!(get(Value::Integer(5)), Value::None));
+        assert!(matches!(
+            get(runtime.new_string("length")),
+            Value::Integer(3)
+        ));
+
+        assert!(reference
+            .set_property(
+                runtime.clone(),
+                &array,
+                &Value::Integer(3),
+                &Value::Integer(4)
+            )
+            .is_ok());
+        assert!(reference
+            .set_property(runtime.clone(), &array, &Value::Integer(9), &Value::None)
+            .is_err());
+        assert_eq!(integers(&runtime, &array), [3, 1, 2, 4]);
+        assert!(reference
+            .set_property(
+                runtime.clone(),
+                &array,
+                &runtime.new_string("length"),
+                &Value::Integer(isize::MAX)
+            )
+            .is_err());
+        assert_eq!(integers(&runtime, &array), [3, 1, 2, 4]);
+
+        assert!(call(&runtime, &array, "sort", &[]).is_ok());
+        assert_eq!(integers(&runtime, &array), [1, 2, 3, 4]);
+
+        let descending = native(&runtime, |_, _, params| match params {
+            [Value::Integer(lhs), Value::Integer(rhs), ..] => Ok(Value::Integer(rhs - lhs)),
+            _ => Ok(Value::None),
+        });
+        assert!(call(&runtime, &array, "sort", &[descending]).is_ok());
+        assert_eq!(integers(&runtime, &array), [4, 3, 2, 1]);
+
+        let double = native(&runtime, |_, _, params| match params {
+            [Value::Integer(value), ..] => Ok(Value::Integer(value * 2)),
+            _ => Ok(Value::None),
+        });
+        let doubled = call(&runtime, &array, "map", &[double]).ok().unwrap();
+        assert_eq!(integers(&runtime, &doubled), [8, 6, 4, 2]);
+
+        let sum = native(&runtime, |_, _, params| match params {
+            [Value::Integer(lhs), Value::Integer(rhs), ..] => Ok(Value::Integer(lhs + rhs)),
+            _ => Ok(Value::None),
+        });
+        let total = call(&runtime, &doubled, "reduce", std::slice::from_ref(&sum))
+            .ok()
+            .unwrap();
+        assert!(matches!(total, Value::Integer(20)));
+
+        let last_two = call(&runtime, &array, "slice", &[Value::Integer(-2)])
+            .ok()
+            .unwrap();
+        assert_eq!(integers(&runtime, &last_two), [2, 1]);
+
+        let joined = call(&runtime, &array, "join", &[runtime.new_string(", ")])
+            .ok()
+            .unwrap();
+        assert_eq!(joined.to_string(), "4, 3, 2, 1");
+
+        let index = call(&runtime, &array, "index_of", &[Value::Float(2.0)])
+            .ok()
+            .unwrap();
+        assert!(matches!(index, Value::Integer(2)));
+
+        // 16777217 can't be a float, and rounds to 16777216.0 when converted to one
+        let large = runtime.new_array(vec![Value::Integer(16_777_217)]);
+        let found = call(&runtime, &large, "contains", &[Value::Float(16_777_216.0)])
+            .ok()
+            .unwrap();
+        assert!(matches!(found, Value::Boolean(false)));
+        let found = call(&runtime, &large, "contains", &[Value::Float(f32::INFINITY)])
+            .ok()
+            .unwrap();
+        assert!(matches!(found, Value::Boolean(false)));
+
+        // Errors from callbacks and mixed types are thrown to the caller
+        let mixed = runtime.new_array(vec![Value::Integer(1), runtime.new_string("a")]);
+        assert!(call(&runtime, &mixed, "sort", &[]).is_err());
+        let empty = runtime.new_array(vec![]);
+        assert!(call(&runtime, &empty, "reduce", &[sum]).is_err());
+    }
+}
@@ -1,13 +1,21 @@
-use std::sync::Arc;
+use std::{
+    any::Any,
+    fmt::{self, Display},
+    sync::Arc,
+};
 
 use object_pool::{MarkChildren, ObjectReference};
 use string_pool::StrReference;
 
-use super::Runtime;
+use super::{error::ErrorKind, Runtime};
 
+pub mod array;
 pub mod object_pool;
+pub mod string;
 pub mod string_pool;
 
+/// Values aren't Send or Sync, since native values needn't be. Anything holding them is still shared
+/// through an `Arc` the way the pools are, which is why `clippy::arc_with_non_send_sync` is allowed where it's built
 #[derive(Clone)]
 pub enum Value {
     String(StrReference),
@@ -20,8 +28,7 @@ pub enum Value {
 }
 
 impl Value {
-    #[allow(unused_variables)]
-    fn invoke(
+    pub fn invoke(
         &self,
         runtime: Arc<Runtime>,
         this_obj: &Value,
@@ -31,21 +38,83 @@ impl Value {
             let native_value = obj.get().native_value.read().unwrap().clone();
 
             let Some(native_value) = native_value else {
-                return Err(Value::String(runtime.string_pool.acquire("Cannot invoke value".into()).unwrap()));
+                return Err(runtime.new_error(ErrorKind::TypeError, "Cannot invoke value"));
             };
 
             if !native_value.has_invoker(runtime.clone()) {
-                return Err(Value::String(runtime.string_pool.acquire("Cannot invoke value".into()).unwrap()));
+                return Err(runtime.new_error(ErrorKind::TypeError, "Cannot invoke value"));
             }
 
             return native_value.invoke(runtime.clone(), this_obj, params);
         }
 
-        return Err(Value::String(runtime.string_pool.acquire("Cannot invoke value".into()).unwrap()));
+        return Err(runtime.new_error(ErrorKind::TypeError, "Cannot invoke value"));
+    }
+
+    /// Reads a property of the value, strings get theirs from the string prototype
+    pub fn get_property(&self, runtime: Arc<Runtime>, property: &Value) -> Result<Value, Value> {
+        return match self {
+            Self::Object(obj) => obj.get_property(runtime, self, property),
+            Self::String(_) => runtime
+                .string_prototype
+                .get_property(runtime.clone(), self, property),
+            _ => Err(runtime.new_error(
+                ErrorKind::TypeError,
+                &format!("Cannot read property {} of {}", property, self),
+            )),
+        };
+    }
+
+    /// None, false, zero, NaN and the empty string are falsy, everything else is truthy
+    pub fn is_truthy(&self) -> bool {
+        return match self {
+            Value::String(str) => !str.get().is_empty(),
+            Value::Object(_) => true,
+            Value::Integer(value) => *value != 0,
+            Value::Float(value) => *value != 0.0 && !value.is_nan(),
+            Value::Boolean(value) => *value,
+            Value::None | Value::Uninitialized => false,
+        };
+    }
+
+    /// Equality without any conversions, other than comparing integers and floats by value.
+    /// Objects are only equal to themselves
+    pub fn equals(&self, other: &Value) -> bool {
+        return match (self, other) {
+            (Value::String(lhs), Value::String(rhs)) => lhs == rhs,
+            (Value::Object(lhs), Value::Object(rhs)) => lhs == rhs,
+            (Value::Integer(lhs), Value::Integer(rhs)) => lhs == rhs,
+            // Converting the integer would round it, so the float is converted when it's whole and in range
+            (Value::Integer(lhs), Value::Float(rhs)) | (Value::Float(rhs), Value::Integer(lhs)) => {
+                rhs.fract() == 0.0
+                    && *rhs >= isize::MIN as f32
+                    && *rhs < isize::MAX as f32
+                    && *rhs as isize == *lhs
+            }
+            (Value::Float(lhs), Value::Float(rhs)) => lhs == rhs,
+            (Value::Boolean(lhs), Value::Boolean(rhs)) => lhs == rhs,
+            (Value::None, Value::None) => true,
+            _ => false,
+        };
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Value::String(str) => write!(f, "{}", str),
+            Value::Object(_) => write!(f, "[object]"),
+            Value::Integer(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", value),
+            Value::Boolean(value) => write!(f, "{}", value),
+            Value::None => write!(f, "none"),
+            Value::Uninitialized => write!(f, "uninitialized"),
+        };
     }
 }
 
-pub trait NativeValue {
+/// Native values can be downcast through `Any` to get back at their data
+pub trait NativeValue: Any {
     /// This function should not create or modify any values, as that will cause a deadlock.
     fn mark_children(&self, marker: &mut MarkChildren);
 
@@ -68,7 +137,7 @@ pub trait NativeValue {
     }
 }
 
-impl<TFn: Fn(Arc<Runtime>, &Value, &[Value]) -> Result<Value, Value>> NativeValue for TFn {
+impl<TFn: Fn(Arc<Runtime>, &Value, &[Value]) -> Result<Value, Value> + 'static> NativeValue for TFn {
     #[allow(unused_variables)]
     fn mark_children(&self, marker: &mut MarkChildren) {}
 
@@ -294,6 +294,30 @@ impl ObjectReference {
     fn set_index(&self, runtime: Arc<runtime::Runtime>, this_obj: &Value, property: &Value, value: &Value) -> Result<Value, Value>{
         let obj = self.get();
 
+        // Setters are inherited, so they are looked for on every prototype
+        if let Value::String(str) = property {
+            let setter = {
+                let values = obj.values.read().unwrap();
+                match values.get(str) {
+                    Some(prop) => {
+                        let prop = prop.read().unwrap();
+
+                        match (&prop) as &Property {
+                            Property::GetSet { get: _, set } => Some(set.clone()),
+                            Property::Value(_) => None,
+                        }
+                    },
+                    None => None,
+                }
+            };
+
+            if let Some(set) = setter {
+                let Value::Uninitialized = set else {
+                    return set.invoke(runtime, this_obj, &[property.clone(), value.clone()]);
+                };
+            }
+        }
+
         {
             let values = obj.values.read().unwrap();
 
@@ -303,8 +327,8 @@ impl ObjectReference {
                 let prop = values.get(&get_index).unwrap();
                 let prop = prop.read().unwrap();
 
-                if let Property::Value(value) = (&prop) as &Property {
-                    let result = value.invoke(runtime.clone(), this_obj, &[property.clone(), value.clone()])?;
+                if let Property::Value(setter) = (&prop) as &Property {
+                    let result = setter.invoke(runtime.clone(), this_obj, &[property.clone(), value.clone()])?;
 
                     let Value::Uninitialized = result else {
                         return Ok(result);
@@ -336,6 +360,7 @@ impl ObjectReference {
                 match (&mut prop) as &mut Property {
                     Property::Value(val) => {
                         *val = value.clone();
+                        return Ok(Value::None);
                     },
                     Property::GetSet { get: _, set } => {
                         let Value::Uninitialized = set else {
@@ -362,6 +387,14 @@ impl Clone for ObjectReference {
     }
 }
 
+impl PartialEq for ObjectReference {
+    fn eq(&self, other: &Self) -> bool {
+        return Arc::ptr_eq(&self.pool, &other.pool) && self.index == other.index;
+    }
+}
+
+impl Eq for ObjectReference {}
+
 impl Drop for Object {
     fn drop(&mut self) {
         if let Some(native_value) = self.native_value.read().unwrap().clone() {
@@ -0,0 +1,436 @@
+use std::sync::{Arc, RwLock};
+
+use crate::runtime::{
+    error::ErrorKind,
+    modules::{insert_function, integer_param, param_error, string_param},
+    Runtime,
+};
+
+use super::{array::clamp_index, object_pool::Property, Value};
+
+/// The most bytes `repeat` and the padding functions will build a string of
+const MAX_LENGTH: usize = 1 << 28;
+
+/// Fills in the string prototype, which strings get their properties from.
+/// Every index and length counts characters, not bytes
+pub(in crate::runtime) fn install_prototype(runtime: &Runtime) {
+    let prototype = &runtime.string_prototype;
+
+    insert_function(runtime, prototype, "__get_index__", get_index);
+
+    let get = runtime
+        .object_pool
+        .new_native_object(Arc::new(get_length))
+        .unwrap();
+    prototype.get().values.write().unwrap().insert(
+        runtime.string_pool.acquire("length".into()).unwrap(),
+        RwLock::new(Property::GetSet {
+            get: Value::Object(get),
+            set: Value::Uninitialized,
+        }),
+    );
+
+    insert_function(runtime, prototype, "upper", upper);
+    insert_function(runtime, prototype, "lower", lower);
+    insert_function(runtime, prototype, "trim", trim);
+    insert_function(runtime, prototype, "split", split);
+    insert_function(runtime, prototype, "replace", replace);
+    insert_function(runtime, prototype, "starts_with", starts_with);
+    insert_function(runtime, prototype, "ends_with", ends_with);
+    insert_function(runtime, prototype, "find", find);
+    insert_function(runtime, prototype, "substring", substring);
+    insert_function(runtime, prototype, "repeat", repeat);
+    insert_function(runtime, prototype, "pad_left", pad_left);
+    insert_function(runtime, prototype, "pad_right", pad_right);
+    insert_function(runtime, prototype, "chars", chars);
+    insert_function(runtime, prototype, "format", format);
+}
+
+fn this_string(runtime: &Runtime, this_obj: &Value) -> Result<Arc<str>, Value> {
+    return match this_obj {
+        Value::String(str) => Ok(str.get()),
+        _ => Err(runtime.new_error(ErrorKind::TypeError, "Expected this to be a string")),
+    };
+}
+
+/// The byte offset of the character at the index, or the length of the string past the end
+fn byte_offset(str: &str, index: usize) -> usize {
+    return str
+        .char_indices()
+        .nth(index)
+        .map_or(str.len(), |(offset, _)| offset);
+}
+
+/// Integer indices read the character there as a string, or none past the end
+fn get_index(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let (Some(Value::Integer(index)), Value::String(str)) = (params.first(), this_obj) else {
+        return Ok(Value::Uninitialized);
+    };
+
+    let char = usize::try_from(*index)
+        .ok()
+        .and_then(|index| str.get().chars().nth(index));
+
+    return Ok(char.map_or(Value::None, |it| {
+        runtime.new_string(it.encode_utf8(&mut [0; 4]))
+    }));
+}
+
+fn get_length(runtime: Arc<Runtime>, this_obj: &Value, _params: &[Value]) -> Result<Value, Value> {
+    let str = this_string(&runtime, this_obj)?;
+
+    return Ok(Value::Integer(str.chars().count() as isize));
+}
+
+fn upper(runtime: Arc<Runtime>, this_obj: &Value, _params: &[Value]) -> Result<Value, Value> {
+    let str = this_string(&runtime, this_obj)?;
+
+    return Ok(runtime.new_string(&str.to_uppercase()));
+}
+
+fn lower(runtime: Arc<Runtime>, this_obj: &Value, _params: &[Value]) -> Result<Value, Value> {
+    let str = this_string(&runtime, this_obj)?;
+
+    return Ok(runtime.new_string(&str.to_lowercase()));
+}
+
+fn trim(runtime: Arc<Runtime>, this_obj: &Value, _params: &[Value]) -> Result<Value, Value> {
+    let str = this_string(&runtime, this_obj)?;
+
+    return Ok(runtime.new_string(str.trim()));
+}
+
+/// `split(separator)`, splits on whitespace without a separator, and into characters if it's empty
+fn split(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let str = this_string(&runtime, this_obj)?;
+
+    let parts = match params.first() {
+        None | Some(Value::None) => str
+            .split_whitespace()
+            .map(|it| runtime.new_string(it))
+            .collect(),
+        _ => {
+            let separator = string_param(&runtime, params, 0, "split")?;
+
+            if separator.is_empty() {
+                str.chars()
+                    .map(|it| runtime.new_string(it.encode_utf8(&mut [0; 4])))
+                    .collect()
+            } else {
+                str.split(separator.as_ref())
+                    .map(|it| runtime.new_string(it))
+                    .collect()
+            }
+        }
+    };
+
+    return Ok(runtime.new_array(parts));
+}
+
+/// `replace(from, to)`, replaces every occurrence
+fn replace(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let str = this_string(&runtime, this_obj)?;
+    let from = string_param(&runtime, params, 0, "replace")?;
+    let to = string_param(&runtime, params, 1, "replace")?;
+
+    if from.is_empty() {
+        return Ok(this_obj.clone());
+    }
+
+    return Ok(runtime.new_string(&str.replace(from.as_ref(), &to)));
+}
+
+fn starts_with(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let str = this_string(&runtime, this_obj)?;
+    let prefix = string_param(&runtime, params, 0, "starts_with")?;
+
+    return Ok(Value::Boolean(str.starts_with(prefix.as_ref())));
+}
+
+fn ends_with(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let str = this_string(&runtime, this_obj)?;
+    let suffix = string_param(&runtime, params, 0, "ends_with")?;
+
+    return Ok(Value::Boolean(str.ends_with(suffix.as_ref())));
+}
+
+/// `find(search, start)`, the character index of the first match at or after `start`, or -1
+fn find(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let str = this_string(&runtime, this_obj)?;
+    let search = string_param(&runtime, params, 0, "find")?;
+    let start = match params.get(1) {
+        None | Some(Value::None) => 0,
+        _ => clamp_index(
+            integer_param(&runtime, params, 1, "find")?,
+            str.chars().count(),
+        ),
+    };
+
+    let offset = byte_offset(&str, start);
+    let index = str[offset..]
+        .find(search.as_ref())
+        .map(|found| start + str[offset..offset + found].chars().count());
+
+    return Ok(Value::Integer(index.map_or(-1, |it| it as isize)));
+}
+
+/// `substring(start, end)`, the characters from `start` up to `end`, which can count from the end if negative
+fn substring(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let str = this_string(&runtime, this_obj)?;
+    let len = str.chars().count();
+
+    let start = clamp_index(integer_param(&runtime, params, 0, "substring")?, len);
+    let end = match params.get(1) {
+        None | Some(Value::None) => len,
+        _ => clamp_index(integer_param(&runtime, params, 1, "substring")?, len),
+    };
+
+    let start_offset = byte_offset(&str, start);
+    let end_offset = byte_offset(&str, end.max(start));
+
+    return Ok(runtime.new_string(&str[start_offset..end_offset]));
+}
+
+fn repeat(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let str = this_string(&runtime, this_obj)?;
+    let count = integer_param(&runtime, params, 0, "repeat")?;
+
+    let Ok(count) = usize::try_from(count) else {
+        return Err(runtime.new_error(
+            ErrorKind::RangeError,
+            "repeat expects a count of at least 0",
+        ));
+    };
+
+    let len = str.len().checked_mul(count);
+    if len.is_none_or(|len| len > MAX_LENGTH) {
+        return Err(too_long(&runtime));
+    }
+
+    return Ok(runtime.new_string(&str.repeat(count)));
+}
+
+fn too_long(runtime: &Runtime) -> Value {
+    return runtime.new_error(
+        ErrorKind::RangeError,
+        &format!("A string can't be more than {} bytes long", MAX_LENGTH),
+    );
+}
+
+/// The fill needed to pad the string to `width` characters, a space unless another one is given
+fn padding(
+    runtime: &Runtime,
+    str: &str,
+    params: &[Value],
+    function: &str,
+) -> Result<String, Value> {
+    let width = integer_param(runtime, params, 0, function)?;
+    let fill = match params.get(1) {
+        None | Some(Value::None) => ' ',
+        _ => {
+            let fill = string_param(runtime, params, 1, function)?;
+            let mut chars = fill.chars();
+
+            match (chars.next(), chars.next()) {
+                (Some(fill), None) => fill,
+                _ => return Err(param_error(runtime, 1, function, "a single character")),
+            }
+        }
+    };
+
+    let missing = usize::try_from(width)
+        .unwrap_or(0)
+        .saturating_sub(str.chars().count());
+
+    let len = missing
+        .checked_mul(fill.len_utf8())
+        .and_then(|len| len.checked_add(str.len()));
+    if len.is_none_or(|len| len > MAX_LENGTH) {
+        return Err(too_long(runtime));
+    }
+
+    return Ok(std::iter::repeat_n(fill, missing).collect());
+}
+
+/// `pad_left(width, fill)`, pads the start until the string is `width` characters long
+fn pad_left(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let str = this_string(&runtime, this_obj)?;
+    let padding = padding(&runtime, &str, params, "pad_left")?;
+
+    return Ok(runtime.new_string(&format!("{}{}", padding, str)));
+}
+
+/// `pad_right(width, fill)`, pads the end until the string is `width` characters long
+fn pad_right(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let str = this_string(&runtime, this_obj)?;
+    let padding = padding(&runtime, &str, params, "pad_right")?;
+
+    return Ok(runtime.new_string(&format!("{}{}", str, padding)));
+}
+
+/// `chars()`, an array with each character as a string
+fn chars(runtime: Arc<Runtime>, this_obj: &Value, _params: &[Value]) -> Result<Value, Value> {
+    let str = this_string(&runtime, this_obj)?;
+
+    return Ok(runtime.new_array(
+        str.chars()
+            .map(|it| runtime.new_string(it.encode_utf8(&mut [0; 4])))
+            .collect(),
+    ));
+}
+
+/// `format(values...)`, replaces `{}` with the next value and `{n}` with the value at `n`.
+/// `{{` and `}}` are literal braces
+fn format(runtime: Arc<Runtime>, this_obj: &Value, params: &[Value]) -> Result<Value, Value> {
+    let str = this_string(&runtime, this_obj)?;
+
+    let invalid = |message: &str| runtime.new_error(ErrorKind::TypeError, message);
+
+    let mut formatted = String::new();
+    let mut next = 0;
+    let mut chars = str.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        match char {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                formatted.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                formatted.push('}');
+            }
+            '{' => {
+                let mut index = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(digit) if digit.is_ascii_digit() => index.push(digit),
+                        _ => {
+                            return Err(invalid(
+                                "format expects a number or nothing between braces",
+                            ))
+                        }
+                    }
+                }
+
+                // Only an empty placeholder is automatic, an index too large for a usize has no value either
+                let value = match index.is_empty() {
+                    true => {
+                        index = next.to_string();
+                        next += 1;
+                        params.get(next - 1)
+                    }
+                    false => index.parse::<usize>().ok().and_then(|it| params.get(it)),
+                };
+
+                let Some(value) = value else {
+                    return Err(runtime.new_error(
+                        ErrorKind::RangeError,
+                        &format!("format has no value for placeholder {}", index),
+                    ));
+                };
+                formatted.push_str(&value.to_string());
+            }
+            '}' => {
+                return Err(invalid(
+                    "format expects a closing brace to be written as }}",
+                ))
+            }
+            char => formatted.push(char),
+        }
+    }
+
+    return Ok(runtime.new_string(&formatted));
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::runtime::{modules::shared, value::Value, Runtime};
+
+    fn call(runtime: &Arc<Runtime>, value: &Value, name: &str, params: &[Value]) -> String {
+        let function = value
+            .get_property(runtime.clone(), &runtime.new_string(name))
+            .ok()
+            .unwrap();
+        let Ok(result) = function.invoke(runtime.clone(), value, params) else {
+            panic!("{} threw", name);
+        };
+
+        return match runtime.array_values(&result) {
+            Some(values) => values
+                .iter()
+                .map(|it| it.to_string())
+                .collect::<Vec<_>>()
+                .join("|"),
+            None => result.to_string(),
+        };
+    }
+
+    fn throws(runtime: &Arc<Runtime>, value: &Value, name: &str, params: &[Value]) -> bool {
+        let function = value
+            .get_property(runtime.clone(), &runtime.new_string(name))
+            .ok()
+            .unwrap();
+
+        return function.invoke(runtime.clone(), value, params).is_err();
+    }
+
+    #[test]
+    fn unicode_methods() {
+        let runtime = shared(Runtime::new());
+        let str = runtime.new_string("  Grüße, 世界! ");
+        let trimmed = runtime.new_string("Grüße, 世界!");
+        let int = Value::Integer;
+
+        let length = str
+            .get_property(runtime.clone(), &runtime.new_string("length"))
+            .ok()
+            .unwrap();
+        assert!(matches!(length, Value::Integer(13)));
+        let char = str.get_property(runtime.clone(), &int(5)).ok().unwrap();
+        assert_eq!(char.to_string(), "ß");
+
+        assert_eq!(call(&runtime, &str, "trim", &[]), "Grüße, 世界!");
+        assert_eq!(call(&runtime, &trimmed, "upper", &[]), "GRÜSSE, 世界!");
+        assert_eq!(
+            call(&runtime, &trimmed, "find", &[runtime.new_string("世")]),
+            "7"
+        );
+        assert_eq!(
+            call(&runtime, &trimmed, "substring", &[int(7), int(-1)]),
+            "世界"
+        );
+        assert_eq!(
+            call(&runtime, &trimmed, "split", &[runtime.new_string(", ")]),
+            "Grüße|世界!"
+        );
+        assert_eq!(
+            call(&runtime, &trimmed, "chars", &[]).len(),
+            "Grüße, 世界!".len() + 9
+        );
+        assert_eq!(
+            call(
+                &runtime,
+                &runtime.new_string("界"),
+                "pad_left",
+                &[int(3), runtime.new_string("·")]
+            ),
+            "··界"
+        );
+
+        let template = runtime.new_string("{} + {} = {2}, {{ok}}");
+        assert_eq!(
+            call(&runtime, &template, "format", &[int(1), int(2), int(3)]),
+            "1 + 2 = 3, {ok}"
+        );
+
+        assert!(throws(&runtime, &template, "format", &[int(1)]));
+        let overflowing = runtime.new_string("{99999999999999999999}");
+        assert!(throws(&runtime, &overflowing, "format", &[int(1)]));
+        assert!(throws(&runtime, &trimmed, "repeat", &[int(isize::MAX)]));
+        assert!(throws(&runtime, &trimmed, "pad_right", &[int(isize::MAX)]));
+    }
+}
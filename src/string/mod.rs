@@ -18,6 +18,19 @@ impl StringSlice {
         return self.src[self.start..self.end].into();
     }
 
+    /// The line and column the slice starts at, both starting at 1
+    pub fn line_column(&self) -> (usize, usize) {
+        let before = self.src.get(..self.start).unwrap_or(&self.src);
+
+        let line = before.matches('\n').count() + 1;
+        let column = match before.rfind('\n') {
+            Some(idx) => before[idx + 1..].chars().count() + 1,
+            None => before.chars().count() + 1,
+        };
+
+        return (line, column);
+    }
+
     pub fn merge(&self, other: &Self) -> Self {
         let start = usize::min(self.start, other.start);
         let end = usize::max(self.end, other.end);
@@ -67,4 +80,11 @@ mod test {
             }
         );
     }
+
+    #[test]
+    fn line_column() {
+        let s: Arc<str> = "let a = 1\n  print(a)".into();
+        assert_eq!(s.slice(0, 3).line_column(), (1, 1));
+        assert_eq!(s.slice(12, 17).line_column(), (2, 3));
+    }
 }
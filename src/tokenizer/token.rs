@@ -107,6 +107,7 @@ keywords!(Keyword {
     Throw("throw"),
     Try("try"),
     Catch("catch"),
+    Finally("finally"),
 });
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -126,6 +127,13 @@ pub enum Symbol {
     Div, // /
     Rem, // %
 
+    // Compound assignment
+    AddAssign, // +=
+    SubAssign, // -=
+    MulAssign, // *=
+    DivAssign, // /=
+    RemAssign, // %=
+
     // Comparisons
     Greater,      // >
     Less,         // <
@@ -134,6 +142,10 @@ pub enum Symbol {
     Equal,        // ==
     NotEqual,     // !=
 
+    NoneCoalesce, // ??
+    OptionalDot,  // ?.
+    Question,     // ?
+
     // Other symbols
     Colon,     // :
     Assign,    // =
@@ -145,6 +157,10 @@ pub enum Symbol {
 impl Symbol {
     pub fn from(parser: &mut StringParser) -> Option<(StringSlice, Self)> {
         symbol_match!(parser,
+            "??" => Self::NoneCoalesce,
+            "?." => Self::OptionalDot,
+            "?" => Self::Question,
+
             ":" => Self::Colon,
             "." => Self::Dot,
             "," => Self::Comma,
@@ -158,6 +174,12 @@ impl Symbol {
             "==" => Self::Equal,
             "=" => Self::Assign,
 
+            "*=" => Self::MulAssign,
+            "%=" => Self::RemAssign,
+            "/=" => Self::DivAssign,
+            "+=" => Self::AddAssign,
+            "-=" => Self::SubAssign,
+
             "*" => Self::Mul,
             "%" => Self::Rem,
             "/" => Self::Div,